@@ -0,0 +1,197 @@
+use rand::prelude::*;
+use std::f32::consts::TAU;
+
+/// The number of audio samples per second.
+const SAMPLE_RATE: u32 = 22_050;
+
+/// The pitch of the voice, in Hz.
+const PITCH: f32 = 120.0;
+
+/// Harmonics of the voice above this frequency are left out, in Hz.
+const MAX_HARMONIC: f32 = 4000.0;
+
+/// The length of the fade at each end of a sound, in seconds, which keeps sounds from clicking.
+const FADE: f32 = 0.005;
+
+/// The first two formants (resonant frequencies, in Hz) of common vowel symbols. They give each
+/// vowel its distinctive quality.
+const VOWEL_FORMANTS: [(char, f32, f32); 24] = [
+    ('i', 270.0, 2290.0),
+    ('y', 270.0, 2100.0),
+    ('ɨ', 320.0, 1650.0),
+    ('ʉ', 320.0, 1500.0),
+    ('ɯ', 300.0, 1350.0),
+    ('u', 300.0, 870.0),
+    ('ɪ', 390.0, 1990.0),
+    ('ʊ', 440.0, 1020.0),
+    ('e', 400.0, 2000.0),
+    ('ø', 400.0, 1650.0),
+    ('ɤ', 460.0, 1300.0),
+    ('o', 450.0, 800.0),
+    ('ə', 500.0, 1500.0),
+    ('ɛ', 550.0, 1770.0),
+    ('œ', 550.0, 1500.0),
+    ('ʌ', 640.0, 1190.0),
+    ('ɔ', 570.0, 840.0),
+    ('æ', 690.0, 1660.0),
+    ('ɐ', 700.0, 1400.0),
+    ('a', 730.0, 1090.0),
+    ('ɑ', 750.0, 940.0),
+    ('ɒ', 700.0, 760.0),
+    ('ɜ', 560.0, 1480.0),
+    ('ɘ', 420.0, 1580.0),
+];
+
+/// A rough class of speech sound, which decides how a grapheme is synthesized.
+enum Sound {
+    Vowel(f32, f32), // (first formant, second formant)
+    Approximant(f32, f32),
+    Nasal,
+    Fricative { voiced: bool },
+    Stop { voiced: bool },
+    Silence,
+}
+
+impl Sound {
+    /// Guess the sound of a grapheme by reading its first letter as an IPA symbol.
+    fn of(grapheme: &str) -> Self {
+        let Some(letter) = grapheme
+            .chars()
+            .find(|chr| chr.is_alphabetic())
+            .and_then(|chr| chr.to_lowercase().next())
+        else {
+            return Self::Silence;
+        };
+        if let Some(&(_, f1, f2)) = VOWEL_FORMANTS.iter().find(|(vowel, ..)| *vowel == letter) {
+            return Self::Vowel(f1, f2);
+        }
+        match letter {
+            'm' | 'n' | 'ŋ' | 'ɲ' | 'ɳ' | 'ɴ' => Self::Nasal,
+            'l' | 'ɫ' | 'ʎ' | 'ɭ' => Self::Approximant(360.0, 1300.0),
+            'r' | 'ɾ' | 'ɹ' | 'ɻ' | 'ʀ' | 'ʁ' => Self::Approximant(420.0, 1150.0),
+            'w' => Self::Approximant(300.0, 700.0),
+            'j' => Self::Approximant(280.0, 2200.0),
+            'v' | 'z' | 'ʒ' | 'ð' | 'ʝ' | 'ɣ' | 'β' | 'ʕ' => Self::Fricative { voiced: true },
+            'f' | 's' | 'ʃ' | 'θ' | 'ç' | 'x' | 'χ' | 'h' | 'ħ' | 'ɸ' | 'ɬ' | 'c' => {
+                Self::Fricative { voiced: false }
+            }
+            'b' | 'd' | 'g' | 'ɡ' | 'ɖ' | 'ɟ' | 'ɢ' => Self::Stop { voiced: true },
+            'p' | 't' | 'k' | 'q' | 'ʈ' | 'ʔ' => Self::Stop { voiced: false },
+            _ => Self::Approximant(500.0, 1500.0),
+        }
+    }
+
+    /// Generate the audio samples for this sound.
+    fn samples(&self, rng: &mut impl Rng) -> Vec<f32> {
+        match *self {
+            Self::Vowel(f1, f2) => voice(0.18, &[(f1, 1.0), (f2, 0.5)]),
+            Self::Approximant(f1, f2) => voice(0.08, &[(f1, 0.7), (f2, 0.3)]),
+            Self::Nasal => voice(0.09, &[(250.0, 0.8), (2000.0, 0.1)]),
+            Self::Fricative { voiced } => {
+                let mut samples = noise(0.12, 0.25, rng);
+                if voiced {
+                    mix(&mut samples, &voice(0.12, &[(250.0, 0.5)]));
+                }
+                samples
+            }
+            Self::Stop { voiced } => {
+                let mut samples = if voiced {
+                    voice(0.05, &[(200.0, 0.3)])
+                } else {
+                    silence(0.05)
+                };
+                samples.extend(noise(0.02, 0.4, rng));
+                samples
+            }
+            Self::Silence => silence(0.05),
+        }
+    }
+}
+
+/// Play a rough approximation of how a word sounds, reading each grapheme as the IPA symbol it
+/// looks like. Playback happens on a background thread, and is silently skipped if there is no
+/// audio device.
+pub fn play_word(graphemes: &[&str]) {
+    let mut rng = thread_rng();
+    let samples: Vec<f32> = graphemes
+        .iter()
+        .flat_map(|grapheme| Sound::of(grapheme).samples(&mut rng))
+        .collect();
+    std::thread::spawn(move || {
+        let Ok((_stream, handle)) = rodio::OutputStream::try_default() else {
+            return;
+        };
+        let Ok(sink) = rodio::Sink::try_new(&handle) else {
+            return;
+        };
+        sink.append(rodio::buffer::SamplesBuffer::new(1, SAMPLE_RATE, samples));
+        sink.sleep_until_end();
+    });
+}
+
+/// Generate a voiced sound: the harmonics of the voice's pitch, each made louder the closer it is
+/// to one of the given (formant frequency, strength) pairs.
+fn voice(seconds: f32, formants: &[(f32, f32)]) -> Vec<f32> {
+    let harmonics: Vec<(f32, f32)> = (1..)
+        .map(|n| n as f32 * PITCH)
+        .take_while(|&freq| freq < MAX_HARMONIC)
+        .map(|freq| {
+            let gain: f32 = formants
+                .iter()
+                .map(|&(formant, strength)| {
+                    let distance = (freq - formant) / 100.0;
+                    strength / (1.0 + distance * distance)
+                })
+                .sum();
+            (freq, gain)
+        })
+        .collect();
+    let total_gain: f32 = harmonics.iter().map(|(_, gain)| gain).sum();
+    let mut samples: Vec<f32> = (0..num_samples(seconds))
+        .map(|i| {
+            let time = i as f32 / SAMPLE_RATE as f32;
+            let sum: f32 = harmonics
+                .iter()
+                .map(|&(freq, gain)| gain * (TAU * freq * time).sin())
+                .sum();
+            0.5 * sum / total_gain
+        })
+        .collect();
+    fade(&mut samples);
+    samples
+}
+
+/// Generate random noise, like the hiss of an [s].
+fn noise(seconds: f32, amplitude: f32, rng: &mut impl Rng) -> Vec<f32> {
+    let mut samples: Vec<f32> = (0..num_samples(seconds))
+        .map(|_| rng.gen_range(-amplitude..amplitude))
+        .collect();
+    fade(&mut samples);
+    samples
+}
+
+fn silence(seconds: f32) -> Vec<f32> {
+    vec![0.0; num_samples(seconds)]
+}
+
+/// Add the second list of samples onto the first, up to the length of the first.
+fn mix(samples: &mut [f32], other: &[f32]) {
+    for (sample, other) in samples.iter_mut().zip(other) {
+        *sample += other;
+    }
+}
+
+/// Ramp the volume up at the start of the samples and down at the end.
+fn fade(samples: &mut [f32]) {
+    let len = samples.len();
+    let fade_len = num_samples(FADE).min(len / 2);
+    for i in 0..fade_len {
+        let scale = i as f32 / fade_len as f32;
+        samples[i] *= scale;
+        samples[len - 1 - i] *= scale;
+    }
+}
+
+fn num_samples(seconds: f32) -> usize {
+    (seconds * SAMPLE_RATE as f32) as usize
+}
@@ -0,0 +1,382 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::grapheme::{self, GraphemeStorage};
+use crate::synthesis;
+
+/// The symbols that mark a deletion when used as the replacement of a sound change.
+const DELETION_SYMBOLS: [char; 2] = ['ε', '∅'];
+
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct EvolutionTab {
+    sound_changes: String, // one sound change per line
+    daughter_name: String,
+}
+
+/// One position in the target, replacement, or environment of a sound change.
+#[derive(Clone)]
+enum Segment {
+    Grapheme(String),
+    Set(Vec<String>), // also used for categories, which are expanded into their members
+    Boundary,         // the start or end of the word
+}
+
+impl Segment {
+    /// If the grapheme matches this segment, return its index within the segment. Single graphemes
+    /// always have an index of 0.
+    fn find(&self, grapheme: &str) -> Option<usize> {
+        match self {
+            Self::Grapheme(own) => (own == grapheme).then_some(0),
+            Self::Set(members) => members.iter().position(|member| member == grapheme),
+            Self::Boundary => None,
+        }
+    }
+}
+
+/// A context-sensitive rewrite rule over graphemes, like "p > f / _a".
+struct SoundChange {
+    target: Vec<Segment>,
+    replacement: Vec<Segment>,
+    before: Vec<Segment>, // the environment before the target
+    after: Vec<Segment>,  // the environment after the target
+}
+
+impl SoundChange {
+    /// Apply this change everywhere it matches in a word. Matches are found from left to right in
+    /// the word as it was before the change, so one replacement can't affect whether another
+    /// matches.
+    fn apply(&self, word: &[String]) -> Vec<String> {
+        let mut output = Vec::with_capacity(word.len());
+        let mut i = 0;
+        while i < word.len() {
+            match self.match_at(word, i) {
+                Some(set_indices) => {
+                    // each set in the replacement uses the grapheme at the same index as the one
+                    // matched by the corresponding set in the target
+                    let mut set_indices = set_indices.into_iter();
+                    for segment in &self.replacement {
+                        match segment {
+                            Segment::Grapheme(grapheme) => output.push(grapheme.clone()),
+                            Segment::Set(members) => {
+                                let index = set_indices.next().unwrap_or_default();
+                                output.push(members[index].clone());
+                            }
+                            Segment::Boundary => {}
+                        }
+                    }
+                    i += self.target.len();
+                }
+                None => {
+                    output.push(word[i].clone());
+                    i += 1;
+                }
+            }
+        }
+        output
+    }
+
+    /// If the target and environment match the word with the target starting at index `start`,
+    /// return the index of the grapheme matched by each set in the target.
+    fn match_at(&self, word: &[String], start: usize) -> Option<Vec<usize>> {
+        let end = start + self.target.len();
+        if end > word.len() {
+            return None;
+        }
+        let mut set_indices = vec![];
+        for (segment, grapheme) in self.target.iter().zip(&word[start..end]) {
+            let index = segment.find(grapheme)?;
+            if let Segment::Set(_) = segment {
+                set_indices.push(index);
+            }
+        }
+
+        // match the environment before the target from right to left
+        let mut pos = start;
+        for segment in self.before.iter().rev() {
+            match segment {
+                Segment::Boundary if pos == 0 => {}
+                _ if pos > 0 && segment.find(&word[pos - 1]).is_some() => pos -= 1,
+                _ => return None,
+            }
+        }
+
+        // match the environment after the target from left to right
+        let mut pos = end;
+        for segment in &self.after {
+            match segment {
+                Segment::Boundary if pos == word.len() => {}
+                _ if pos < word.len() && segment.find(&word[pos]).is_some() => pos += 1,
+                _ => return None,
+            }
+        }
+        Some(set_indices)
+    }
+}
+
+/// Render contents of the 'evolution' tab. If the user creates a daughter language, return it.
+pub fn draw_evolution_tab(
+    ui: &mut egui::Ui,
+    curr_lang: &mut crate::Language,
+) -> Option<crate::Language> {
+    let crate::Language {
+        name,
+        native_name,
+        lexicon_tab,
+        synthesis_tab,
+        evolution_tab,
+        ..
+    } = curr_lang;
+    let mut create_clicked = false;
+
+    ui.heading("Sound Changes");
+    ui.add_space(5.0);
+    ui.weak(
+        "Write one change per line, like \"p > f / _a\". Use {a e} for sets, [Vowel] for \
+        categories, # for the edge of a word, and ε to delete. Lines starting with // are ignored.",
+    );
+    ui.add(
+        egui::TextEdit::multiline(&mut evolution_tab.sound_changes)
+            .code_editor()
+            .desired_rows(6)
+            .desired_width(ui.available_width() * 0.8),
+    );
+    let changes = parse_sound_changes(&evolution_tab.sound_changes, synthesis_tab);
+
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        ui.label("Daughter language:");
+        ui.add(
+            egui::TextEdit::singleline(&mut evolution_tab.daughter_name)
+                .hint_text(format!("{name} (evolved)"))
+                .desired_width(120.0),
+        );
+        create_clicked = ui
+            .add_enabled(changes.is_ok(), egui::Button::new("Create"))
+            .on_hover_text("Create a copy of this language with the sound changes applied")
+            .on_disabled_hover_text("The sound changes contain errors.")
+            .clicked();
+    });
+
+    let changes = match changes {
+        Ok(changes) => changes,
+        Err(error) => {
+            ui.colored_label(egui::Color32::RED, error);
+            return None;
+        }
+    };
+
+    // draw a preview of the lexicon after the changes
+    ui.add_space(10.0);
+    let mut entries: Vec<_> = lexicon_tab.lexicon.iter().collect();
+    entries.sort_by_key(|&(native, _)| native);
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        ui.group(|ui| {
+            ui.heading(format!("{} Lexicon Preview", name));
+            ui.separator();
+            egui::Grid::new("evolution preview")
+                .striped(true)
+                .min_col_width(100.0)
+                .show(ui, |ui| {
+                    ui.strong(native_name.as_str());
+                    ui.strong("Before");
+                    ui.strong("After");
+                    ui.end_row();
+                    for (native, entry) in entries {
+                        let evolved = evolve(&entry.word, &changes, &synthesis_tab.graphemes);
+                        ui.label(native);
+                        ui.label(&entry.word);
+                        if evolved == entry.word {
+                            ui.weak(evolved);
+                        } else {
+                            ui.strong(evolved);
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+    });
+
+    create_clicked.then(|| create_daughter(curr_lang, &changes))
+}
+
+/// Create a copy of a language with the sound changes applied to every word in its lexicon. Any
+/// graphemes introduced by the changes are added to the copy's inventory.
+fn create_daughter(parent: &crate::Language, changes: &[SoundChange]) -> crate::Language {
+    let mut daughter = parent.clone();
+    daughter.name = match parent.evolution_tab.daughter_name.trim() {
+        "" => format!("{} (evolved)", parent.name),
+        name => name.to_owned(),
+    };
+    daughter.evolution_tab = Default::default();
+    for entry in daughter.lexicon_tab.lexicon.values_mut() {
+        entry.word = evolve(&entry.word, changes, &parent.synthesis_tab.graphemes);
+    }
+    let inventory = &mut daughter.synthesis_tab.graphemes;
+    for change in changes {
+        for segment in &change.replacement {
+            match segment {
+                Segment::Grapheme(grapheme) => inventory.add(grapheme::Grapheme::new(grapheme)),
+                Segment::Set(members) => members
+                    .iter()
+                    .for_each(|member| inventory.add(grapheme::Grapheme::new(member))),
+                Segment::Boundary => {}
+            }
+        }
+    }
+    daughter
+}
+
+/// Apply each sound change in order to a word, and return the evolved word.
+fn evolve(
+    word: &str,
+    changes: &[SoundChange],
+    inventory: &grapheme::MasterGraphemeStorage,
+) -> String {
+    let mut graphemes: Vec<String> = grapheme::segment(word, inventory)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    for change in changes {
+        graphemes = change.apply(&graphemes);
+    }
+    graphemes.concat()
+}
+
+/// Parse sound changes written one per line. Blank lines and lines starting with "//" are skipped.
+fn parse_sound_changes(
+    text: &str,
+    synthesis: &synthesis::SynthesisTab,
+) -> Result<Vec<SoundChange>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim().starts_with("//"))
+        .map(|(i, line)| {
+            parse_sound_change(line, synthesis).map_err(|error| format!("Line {}: {error}", i + 1))
+        })
+        .collect()
+}
+
+/// Parse a sound change like "target > replacement / before_after". The environment is optional.
+fn parse_sound_change(
+    line: &str,
+    synthesis: &synthesis::SynthesisTab,
+) -> Result<SoundChange, String> {
+    let (change, environment) = match line.split_once('/') {
+        Some((change, environment)) => (change, Some(environment)),
+        None => (line, None),
+    };
+    let (target, replacement) = change
+        .split_once('>')
+        .ok_or("Expected \">\" between the target and its replacement")?;
+    let target = parse_segments(target, synthesis)?;
+    let replacement = parse_segments(replacement, synthesis)?;
+    if target.is_empty() {
+        return Err("The target can't be empty".to_owned());
+    }
+    let is_boundary = |segment: &Segment| matches!(segment, Segment::Boundary);
+    if target.iter().chain(&replacement).any(is_boundary) {
+        return Err("Word edges (#) can only be used in the environment".to_owned());
+    }
+
+    // each set in the replacement must line up with a set of the same size in the target
+    let target_sets = target.iter().filter_map(|segment| match segment {
+        Segment::Set(members) => Some(members.len()),
+        _ => None,
+    });
+    let replacement_sets = replacement.iter().filter_map(|segment| match segment {
+        Segment::Set(members) => Some(members.len()),
+        _ => None,
+    });
+    let mut target_sets = target_sets.map(Some).chain(std::iter::repeat(None));
+    for len in replacement_sets {
+        if target_sets.next().flatten() != Some(len) {
+            return Err(
+                "Each set in the replacement needs a set of the same size in the target".to_owned(),
+            );
+        }
+    }
+
+    let (before, after) = match environment {
+        Some(environment) => {
+            let (before, after) = environment
+                .split_once('_')
+                .ok_or("Expected \"_\" in the environment to mark the target's position")?;
+            (
+                parse_segments(before, synthesis)?,
+                parse_segments(after, synthesis)?,
+            )
+        }
+        None => (vec![], vec![]),
+    };
+    let misplaced_boundary =
+        before.iter().skip(1).any(is_boundary) || after.iter().rev().skip(1).any(is_boundary);
+    if misplaced_boundary {
+        return Err("Word edges (#) must be at the start or end of the environment".to_owned());
+    }
+
+    Ok(SoundChange {
+        target,
+        replacement,
+        before,
+        after,
+    })
+}
+
+/// Parse one part of a sound change into segments. Text outside of sets and categories is split
+/// into graphemes from the inventory, so multigraphs like <ch> are treated as a single unit.
+fn parse_segments(text: &str, synthesis: &synthesis::SynthesisTab) -> Result<Vec<Segment>, String> {
+    let mut segments = vec![];
+    let mut rest = text.trim();
+    while let Some(first) = rest.chars().next() {
+        match first {
+            '{' => {
+                let end = rest.find('}').ok_or("Missing \"}\" at the end of a set")?;
+                let members: Vec<_> = rest[1..end].split_whitespace().map(str::to_owned).collect();
+                if members.is_empty() {
+                    return Err("Sets can't be empty".to_owned());
+                }
+                segments.push(Segment::Set(members));
+                rest = &rest[end + 1..];
+            }
+            '[' => {
+                let end = rest
+                    .find(']')
+                    .ok_or("Missing \"]\" at the end of a category")?;
+                let category = rest[1..end].trim();
+                let members: Vec<_> = synthesis
+                    .category_members(category)
+                    .map(|grapheme| grapheme.as_str().to_owned())
+                    .collect();
+                if members.is_empty() {
+                    return Err(format!("No graphemes are in the category \"{category}\""));
+                }
+                segments.push(Segment::Set(members));
+                rest = &rest[end + 1..];
+            }
+            '#' => {
+                segments.push(Segment::Boundary);
+                rest = &rest[1..];
+            }
+            _ if first.is_whitespace() || DELETION_SYMBOLS.contains(&first) => {
+                rest = &rest[first.len_utf8()..];
+            }
+            _ => {
+                let end = rest
+                    .find(|chr: char| {
+                        chr.is_whitespace()
+                            || "{[#".contains(chr)
+                            || DELETION_SYMBOLS.contains(&chr)
+                    })
+                    .unwrap_or(rest.len());
+                let graphemes = grapheme::segment(&rest[..end], &synthesis.graphemes);
+                segments.extend(
+                    graphemes
+                        .into_iter()
+                        .map(|grapheme| Segment::Grapheme(grapheme.to_owned())),
+                );
+                rest = &rest[end..];
+            }
+        }
+    }
+    Ok(segments)
+}
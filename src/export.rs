@@ -0,0 +1,115 @@
+use std::fmt::Write;
+use std::io;
+
+use crate::{grapheme, lexicon, synthesis};
+
+/// Ask the user where to save a file, suggesting `file_name`, and write `contents` there. Nothing
+/// is written if the user cancels the dialog.
+pub fn save_with_dialog(file_name: &str, extension: &str, contents: &str) -> io::Result<()> {
+    let path = rfd::FileDialog::new()
+        .set_file_name(file_name)
+        .add_filter(extension.to_uppercase(), &[extension])
+        .save_file();
+    match path {
+        Some(path) => std::fs::write(path, contents),
+        None => Ok(()),
+    }
+}
+
+/// Render a lexicon as a printable HTML dictionary. Entries are sorted by their conlang word in
+/// the given collation and grouped under the first grapheme of each word, with the word type and
+/// native gloss after the headword.
+pub fn dictionary_html(
+    lexicon: &lexicon::Lexicon,
+    lang_name: &str,
+    native_name: &str,
+    inventory: &grapheme::MasterGraphemeStorage,
+    collation: grapheme::Collation,
+) -> String {
+    let mut entries: Vec<_> = lexicon
+        .iter()
+        .filter(|(_, entry)| !entry.word.is_empty())
+        .collect();
+    entries.sort_by(|(native_a, a), (native_b, b)| {
+        collation
+            .compare(&a.word, &b.word, inventory)
+            .then_with(|| native_a.cmp(native_b))
+    });
+
+    let title = escape_html(&format!("{lang_name} to {native_name} Dictionary"));
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+        <style>\n\
+        body {{ font-family: serif; columns: 2; max-width: 50em; margin: auto; }}\n\
+        h1 {{ column-span: all; text-align: center; }}\n\
+        h2 {{ break-after: avoid; border-bottom: 1px solid; }}\n\
+        p {{ margin: 0.2em 0; break-inside: avoid; }}\n\
+        .type {{ font-style: italic; }}\n\
+        </style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+
+    let mut curr_heading = None;
+    for (native, entry) in entries {
+        let lowercase = entry.word.to_lowercase();
+        let heading = grapheme::segment(&lowercase, inventory)[0].to_uppercase();
+        if curr_heading.as_ref() != Some(&heading) {
+            let _ = writeln!(html, "<h2>{}</h2>", escape_html(&heading));
+            curr_heading = Some(heading);
+        }
+        let _ = write!(html, "<p><b>{}</b>", escape_html(&entry.word));
+        if let Some(word_type) = &entry.word_type {
+            let _ = write!(html, " <span class=\"type\">{}</span>", word_type.name());
+        }
+        let _ = writeln!(html, " &mdash; {}</p>", escape_html(native));
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Render a lexicon as a tab-separated file that Anki can import as flashcards, with the native
+/// word on the front of each card and the conlang word on the back, followed by its pronunciation
+/// in IPA. Entries without a conlang word are left out.
+pub fn anki_tsv(lexicon: &lexicon::Lexicon, synthesis: &synthesis::SynthesisTab) -> String {
+    let mut entries: Vec<_> = lexicon
+        .iter()
+        .filter(|(_, entry)| !entry.word.is_empty())
+        .collect();
+    entries.sort_by_key(|&(native, _)| native);
+
+    // tell Anki how to read the file, so the user doesn't have to pick the options by hand
+    let mut tsv = "#separator:tab\n#html:false\n".to_owned();
+    for (native, entry) in entries {
+        let ipa = format!("/{}/", synthesis::to_ipa(synthesis, &entry.word));
+        let _ = writeln!(
+            tsv,
+            "{}\t{}\t{}",
+            tsv_field(native),
+            tsv_field(&entry.word),
+            tsv_field(&ipa)
+        );
+    }
+    tsv
+}
+
+/// Make text safe to use as one field of a tab-separated file.
+fn tsv_field(text: &str) -> String {
+    text.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Replace the characters that have special meaning in HTML with character references.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for chr in text.chars() {
+        match chr {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(chr),
+        }
+    }
+    escaped
+}
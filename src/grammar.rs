@@ -1,20 +1,124 @@
+use crate::lexicon::Lexicon;
 use crate::util::{self, EditMode};
 use eframe::egui;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::rc::{Rc, Weak};
 
-#[derive(Default, Deserialize, Serialize)]
+/// The maximum nesting depth of deep match patterns, counting the top-level pattern.
+const MAX_MATCH_DEPTH: usize = 8;
+
+/// The text of a new "exact word" pattern, until the user enters the word they want.
+const PLACEHOLDER_WORD: &str = "word";
+
+/// The most times a phase's rules are applied while waiting for them to stop changing the words,
+/// in case some rules keep undoing each other.
+const MAX_PHASE_PASSES: usize = 32;
+
+/// The most times a repeating rule is applied in a row while waiting for it to stop changing the
+/// words, in case it keeps rewriting its own output.
+const MAX_RULE_REPEATS: usize = 32;
+
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct GrammarTab {
-    pub grammar_rules: Vec<GrammarRule>,
+    #[serde(default)]
+    phases: Vec<Phase>,
+    #[serde(default, skip_serializing)]
+    grammar_rules: Vec<GrammarRule>, // rules from older saves, which are moved into a phase on load
+    #[serde(default)]
+    affixes: Vec<Affix>,
     #[serde(skip)]
     grammar_edit_mode: EditMode,
+    #[serde(skip)]
+    tree_view: bool,
+    #[serde(skip)]
+    sample: String, // sample input for previewing the whole grammar
+    #[serde(skip)]
+    rule_search: String,
+}
+
+/// A named group of grammar rules. Phases apply in order, and the rules within a phase are applied
+/// repeatedly until they stop changing the words.
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct Phase {
+    name: String, // optional, so it may be empty
+    rules: Vec<GrammarRule>,
+}
+
+impl Phase {
+    /// Refer to the phase by its name, or by its position if it has no name.
+    fn title(&self, index: usize) -> String {
+        if self.name.is_empty() {
+            format!("Phase {}", index + 1)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// The settings shared by every rule while the rules are drawn.
+#[derive(Clone, Copy)]
+struct RulesView<'a> {
+    mode: EditMode,
+    tree_view: bool, // in view mode, show the find patterns as a tree instead of on one line
+    search: &'a str, // only rules that match the search are shown
+    lexicon: &'a Lexicon, // used to translate native words in the previews
+    phase_titles: &'a [String], // the destinations offered by each rule's "Move to Phase" menu
 }
 
 /// A word in the input text.
-#[derive(Deserialize, Serialize)]
-pub struct Word(String, WordType); // todo add Vec<WordAttribute>
+#[derive(Clone, Deserialize, PartialEq, Serialize)]
+pub struct Word {
+    pub text: String,
+    pub word_type: Option<WordType>,
+    pub attributes: Vec<WordAttribute>,
+}
+
+impl Word {
+    /// Parse a word written as "text/Type#tag#tag", where the type and tags are optional. Types
+    /// can be given by their full or abbreviated name.
+    fn parse(token: &str) -> Result<Self, String> {
+        let mut parts = token.split('#');
+        let head = parts.next().unwrap_or_default();
+        let (text, word_type) = match head.split_once('/') {
+            Some((text, ty)) => {
+                let word_type = WordType::iter()
+                    .find(|choice| {
+                        choice.short_name().eq_ignore_ascii_case(ty)
+                            || choice.name().replace(' ', "").eq_ignore_ascii_case(ty)
+                    })
+                    .ok_or_else(|| format!("Unknown word type \"{ty}\""))?;
+                (text, Some(word_type))
+            }
+            None => (head, None),
+        };
+        if text.is_empty() {
+            return Err(format!("Missing word in \"{token}\""));
+        }
+        let attributes = parts
+            .map(|tag| {
+                WordAttribute::from_tag(tag).ok_or_else(|| format!("Unknown tag \"#{tag}\""))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            text: text.to_owned(),
+            word_type,
+            attributes,
+        })
+    }
+
+    /// The word's text followed by its tags, e.g. "dogs#pl".
+    fn display(&self) -> String {
+        let mut text = self.text.clone();
+        for attribute in &self.attributes {
+            text.push('#');
+            text.push_str(attribute.tag());
+        }
+        text
+    }
+}
 
 /// A word type, roughly analogous to a part of speech, but simplified to support arbitrary languages.
 #[derive(Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -30,7 +134,7 @@ pub enum WordType {
 }
 
 impl WordType {
-    fn iter() -> impl Iterator<Item = Self> {
+    pub fn iter() -> impl Iterator<Item = Self> {
         [
             Self::Adposition,
             Self::Conjunction,
@@ -44,7 +148,7 @@ impl WordType {
         .into_iter()
     }
 
-    fn name(&self) -> &'static str {
+    pub fn name(&self) -> &'static str {
         match self {
             Self::Adposition => "Adposition",
             Self::Conjunction => "Conjunction",
@@ -71,6 +175,282 @@ impl WordType {
     }
 }
 
+/// A grammatical feature that a word can be marked with. Each variant is one kind of feature,
+/// holding the value it takes.
+#[derive(Clone, Copy, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum WordAttribute {
+    Number(Number),
+    Tense(Tense),
+    Case(Case),
+    Person(Person),
+}
+
+impl WordAttribute {
+    fn iter() -> impl Iterator<Item = Self> {
+        Number::iter()
+            .map(Self::Number)
+            .chain(Tense::iter().map(Self::Tense))
+            .chain(Case::iter().map(Self::Case))
+            .chain(Person::iter().map(Self::Person))
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Number(number) => number.name(),
+            Self::Tense(tense) => tense.name(),
+            Self::Case(case) => case.name(),
+            Self::Person(person) => person.name(),
+        }
+    }
+
+    /// The tag used to annotate a word with this attribute in the source text, e.g. "dog#pl".
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::Number(number) => number.tag(),
+            Self::Tense(tense) => tense.tag(),
+            Self::Case(case) => case.tag(),
+            Self::Person(person) => person.tag(),
+        }
+    }
+
+    /// Look up the attribute with the given annotation tag, ignoring case.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Self::iter().find(|attribute| attribute.tag().eq_ignore_ascii_case(tag))
+    }
+
+    /// The feature that this attribute is a value of.
+    pub fn feature(&self) -> Feature {
+        match self {
+            Self::Number(_) => Feature::Number,
+            Self::Tense(_) => Feature::Tense,
+            Self::Case(_) => Feature::Case,
+            Self::Person(_) => Feature::Person,
+        }
+    }
+
+    /// Return true if both attributes are values of the same feature, e.g. both are numbers.
+    pub fn same_feature(&self, other: &Self) -> bool {
+        self.feature() == other.feature()
+    }
+}
+
+/// A kind of grammatical feature, without a particular value.
+#[derive(Clone, Copy, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Feature {
+    Number,
+    Tense,
+    Case,
+    Person,
+}
+
+impl Feature {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Number, Self::Tense, Self::Case, Self::Person].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Number => "Number",
+            Self::Tense => "Tense",
+            Self::Case => "Case",
+            Self::Person => "Person",
+        }
+    }
+}
+
+/// Whether a word refers to one thing or several.
+#[derive(Clone, Copy, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Number {
+    Singular,
+    Plural,
+}
+
+impl Number {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Singular, Self::Plural].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Singular => "Singular",
+            Self::Plural => "Plural",
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::Singular => "sg",
+            Self::Plural => "pl",
+        }
+    }
+}
+
+/// When the action of a verb takes place.
+#[derive(Clone, Copy, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Tense {
+    Past,
+    Present,
+    Future,
+}
+
+impl Tense {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Past, Self::Present, Self::Future].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Past => "Past",
+            Self::Present => "Present",
+            Self::Future => "Future",
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::Past => "past",
+            Self::Present => "pres",
+            Self::Future => "fut",
+        }
+    }
+}
+
+/// The role a noun or pronoun plays in its clause.
+#[derive(Clone, Copy, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Case {
+    Nominative,
+    Accusative,
+    Genitive,
+    Dative,
+}
+
+impl Case {
+    fn iter() -> impl Iterator<Item = Self> {
+        [
+            Self::Nominative,
+            Self::Accusative,
+            Self::Genitive,
+            Self::Dative,
+        ]
+        .into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Nominative => "Nominative",
+            Self::Accusative => "Accusative",
+            Self::Genitive => "Genitive",
+            Self::Dative => "Dative",
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::Nominative => "nom",
+            Self::Accusative => "acc",
+            Self::Genitive => "gen",
+            Self::Dative => "dat",
+        }
+    }
+}
+
+/// Whether a word refers to the speaker, the listener, or someone else.
+#[derive(Clone, Copy, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Person {
+    First,
+    Second,
+    Third,
+}
+
+impl Person {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::First, Self::Second, Self::Third].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::First => "First Person",
+            Self::Second => "Second Person",
+            Self::Third => "Third Person",
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            Self::First => "1",
+            Self::Second => "2",
+            Self::Third => "3",
+        }
+    }
+}
+
+/// Where an affix attaches to the stem of a word.
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize)]
+enum AffixPosition {
+    Prefix,
+    Suffix,
+}
+
+impl AffixPosition {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Prefix, Self::Suffix].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Prefix => "Prefix",
+            Self::Suffix => "Suffix",
+        }
+    }
+}
+
+/// A morpheme that is attached to words of one type when they have a certain attribute, e.g. a
+/// suffix -ka on plural nouns.
+#[derive(Clone, Deserialize, Serialize)]
+struct Affix {
+    name: String,
+    word_type: WordType,
+    attribute: WordAttribute,
+    position: AffixPosition,
+    form: String, // the affix as written in the conlang
+}
+
+impl Default for Affix {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            word_type: WordType::Noun,
+            attribute: WordAttribute::Number(Number::Plural),
+            position: AffixPosition::Suffix,
+            form: String::new(),
+        }
+    }
+}
+
+impl GrammarTab {
+    /// Attach every affix for the given word type and attributes to a stem, in the order the
+    /// affixes are listed. Prefixes added later end up further from the stem.
+    pub fn inflect(
+        &self,
+        stem: &str,
+        word_type: &WordType,
+        attributes: &[WordAttribute],
+    ) -> String {
+        let mut word = stem.to_owned();
+        let applicable = self
+            .affixes
+            .iter()
+            .filter(|affix| affix.word_type == *word_type && attributes.contains(&affix.attribute));
+        for affix in applicable {
+            match affix.position {
+                AffixPosition::Prefix => word.insert_str(0, &affix.form),
+                AffixPosition::Suffix => word.push_str(&affix.form),
+            }
+        }
+        word
+    }
+}
+
 /// A phrase type, roughly analogous to a constituent type in linguistic syntax. A phrase is composed
 /// of words and other phrases.
 #[derive(Clone, Deserialize, Eq, Hash, PartialEq, Serialize)]
@@ -118,8 +498,12 @@ pub struct FindPattern {
     pattern: PatternType,
     multimatch: bool, // also match all adjacent constituents of same type
     optional: bool,   // also match even if not present
+    #[serde(default)]
+    attribute: Option<WordAttribute>, // only match words with this attribute
     children: Vec<FindPatternRef>,
-    label: String,
+    label: String, // unique within the rule
+    #[serde(default = "rand::random")]
+    uid: u64, // stable identifier, used to reconnect captures after loading
 }
 
 // A reference-counted FindPattern.
@@ -129,7 +513,7 @@ type FindPatternRef = Rc<RefCell<FindPattern>>;
 type FindPatternWeakRef = Weak<RefCell<FindPattern>>;
 
 // The unique portion of a FindPattern, used for equality checking and hashing.
-type FindPatternId = (PatternType, bool, bool);
+type FindPatternId = (PatternType, bool, bool, Option<WordAttribute>);
 
 impl FindPattern {
     fn new(pattern: PatternType) -> Self {
@@ -137,14 +521,21 @@ impl FindPattern {
             pattern,
             multimatch: false,
             optional: false,
+            attribute: None,
             children: vec![],
             label: String::new(),
+            uid: rand::random(),
         }
     }
 
     /// Get the unique portion of this pattern.
     fn id(&self) -> FindPatternId {
-        (self.pattern.clone(), self.multimatch, self.optional)
+        (
+            self.pattern.clone(),
+            self.multimatch,
+            self.optional,
+            self.attribute,
+        )
     }
 
     /// Compute and save this node's label. It can be accessed later through the `self.label` field.
@@ -162,6 +553,13 @@ impl FindPattern {
             }
         }
 
+        // add required attribute, e.g. Noun[pl]
+        if let Some(attribute) = &self.attribute {
+            self.label.push('[');
+            self.label.push_str(attribute.tag());
+            self.label.push(']');
+        }
+
         // add type modifiers (*, +, ?)
         match (self.multimatch, self.optional) {
             (true, true) => self.label.push('*'),
@@ -191,48 +589,290 @@ pub enum ReplacePattern {
     Capture {
         #[serde(skip)]
         capture: FindPatternWeakRef,
-        serde_label: String,
+        #[serde(default)]
+        serde_uid: u64,
+        #[serde(default, rename = "serde_label", skip_serializing)]
+        legacy_label: String, // older saves identified the capture by label instead of uid
+    },
+    Literal(String),    // conlang text, inserted as-is
+    Translated(String), // a native word, replaced by its translation from the lexicon
+    Placeholder {
+        translated: bool, // whether the word will be translated once the user enters it
+    },
+    Agree {
+        #[serde(skip)]
+        source: FindPatternWeakRef,
+        #[serde(skip)]
+        target: FindPatternWeakRef,
+        #[serde(default)]
+        serde_source_uid: u64,
+        #[serde(default)]
+        serde_target_uid: u64,
+        feature: Feature, // the feature copied from the source word to the target word
     },
-    Literal(String),
 }
 
 impl ReplacePattern {
-    fn is_valid(&self) -> bool {
+    /// Return true if every find pattern this refers to still exists in `find_patterns`, which
+    /// should be the find patterns of this pattern's own rule.
+    fn is_valid(&self, find_patterns: &[FindPatternRef]) -> bool {
         match self {
-            ReplacePattern::Capture {
-                capture: find_pattern,
-                ..
-            } => find_pattern.upgrade().is_some(),
-            ReplacePattern::Literal(_) => true,
+            ReplacePattern::Capture { capture, .. } => {
+                resolve_in_rule(capture, find_patterns).is_some()
+            }
+            ReplacePattern::Literal(_)
+            | ReplacePattern::Translated(_)
+            | ReplacePattern::Placeholder { .. } => true,
+            ReplacePattern::Agree { source, target, .. } => {
+                resolve_in_rule(source, find_patterns).is_some()
+                    && resolve_in_rule(target, find_patterns).is_some()
+            }
         }
     }
 
+    /// Return true if this is a word that hasn't been entered yet.
+    fn is_placeholder(&self) -> bool {
+        matches!(self, Self::Placeholder { .. })
+    }
+
     fn as_dbg_text(&self) -> String {
-        // todo replace this with a proper button
         match self {
-            ReplacePattern::Capture { capture, .. } => capture
-                .upgrade()
-                .map(|find_pattern| find_pattern.borrow().label.clone())
-                .unwrap_or_default(),
+            ReplacePattern::Capture { capture, .. } => capture.upgrade().map_or_else(
+                || "(deleted)".to_owned(),
+                |find_pattern| find_pattern.borrow().label.clone(),
+            ),
             ReplacePattern::Literal(literal) => format!("\"{literal}\""),
+            ReplacePattern::Translated(native) => format!("\"{native}\" (translated)"),
+            ReplacePattern::Placeholder { translated: false } => format!("\"{PLACEHOLDER_WORD}\""),
+            ReplacePattern::Placeholder { translated: true } => {
+                format!("\"{PLACEHOLDER_WORD}\" (translated)")
+            }
+            ReplacePattern::Agree {
+                source,
+                target,
+                feature,
+                ..
+            } => {
+                let label = |pattern: &FindPatternWeakRef| {
+                    pattern.upgrade().map_or_else(
+                        || "(deleted)".to_owned(),
+                        |find_pattern| find_pattern.borrow().label.clone(),
+                    )
+                };
+                format!("{}={}.{}", label(target), label(source), feature.name())
+            }
         }
     }
 }
 
 /// A rule in a language's grammar, which maps a "find pattern" to a "replace pattern".
 /// Analagous to a production in a context-sensitive grammar.
-#[derive(Default, Deserialize, Serialize)]
+#[derive(Deserialize, Serialize)]
 pub struct GrammarRule {
     find_patterns: Vec<FindPatternRef>,
     replace_patterns: Vec<ReplacePattern>,
+    #[serde(default)]
+    name: String, // optional, so it may be empty
+    #[serde(default)]
+    description: String,
+    #[serde(default = "default_enabled")]
+    enabled: bool, // disabled rules are skipped when applying the grammar
+    #[serde(default)]
+    repeat: bool, // whether to keep applying the rule until it stops changing the words
+    #[serde(skip)]
+    sample: String, // sample input for previewing the rule
+}
+
+impl Default for GrammarRule {
+    fn default() -> Self {
+        Self {
+            find_patterns: Vec::new(),
+            replace_patterns: Vec::new(),
+            name: String::new(),
+            description: String::new(),
+            enabled: default_enabled(),
+            repeat: false,
+            sample: String::new(),
+        }
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl GrammarRule {
+    /// Refer to the rule by its position, and by its name if it has one.
+    fn title(&self, index: usize) -> String {
+        if self.name.is_empty() {
+            format!("Grammar rule {}", index + 1)
+        } else {
+            format!("Grammar rule {} ({})", index + 1, self.name)
+        }
+    }
+
+    /// Return true if the search text appears in the rule's name, description, or find patterns,
+    /// ignoring case.
+    fn matches_search(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+        [
+            &self.name,
+            &self.description,
+            &summarize_find_patterns(self),
+        ]
+        .iter()
+        .any(|text| text.to_lowercase().contains(&search))
+    }
+}
+
+impl Clone for GrammarRule {
+    /// Clone the rule's find patterns, and point the clone's captures at the cloned find patterns
+    /// instead of the original ones.
+    fn clone(&self) -> Self {
+        let mut clones = HashMap::new();
+        let find_patterns = self
+            .find_patterns
+            .iter()
+            .map(|pattern| clone_find_pattern(pattern, &mut clones))
+            .collect();
+        let replace_patterns = self
+            .replace_patterns
+            .iter()
+            .map(|pattern| match pattern {
+                ReplacePattern::Capture {
+                    capture,
+                    serde_uid,
+                    legacy_label,
+                } => ReplacePattern::Capture {
+                    capture: capture
+                        .upgrade()
+                        .and_then(|original| clones.get(&Rc::as_ptr(&original)))
+                        .map_or_else(Weak::new, Rc::downgrade),
+                    serde_uid: *serde_uid,
+                    legacy_label: legacy_label.clone(),
+                },
+                ReplacePattern::Literal(literal) => ReplacePattern::Literal(literal.clone()),
+                ReplacePattern::Translated(native) => ReplacePattern::Translated(native.clone()),
+                ReplacePattern::Placeholder { translated } => ReplacePattern::Placeholder {
+                    translated: *translated,
+                },
+                ReplacePattern::Agree {
+                    source,
+                    target,
+                    serde_source_uid,
+                    serde_target_uid,
+                    feature,
+                } => {
+                    let remap = |pattern: &FindPatternWeakRef| {
+                        pattern
+                            .upgrade()
+                            .and_then(|original| clones.get(&Rc::as_ptr(&original)))
+                            .map_or_else(Weak::new, Rc::downgrade)
+                    };
+                    ReplacePattern::Agree {
+                        source: remap(source),
+                        target: remap(target),
+                        serde_source_uid: *serde_source_uid,
+                        serde_target_uid: *serde_target_uid,
+                        feature: *feature,
+                    }
+                }
+            })
+            .collect();
+        Self {
+            find_patterns,
+            replace_patterns,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            enabled: self.enabled,
+            repeat: self.repeat,
+            sample: self.sample.clone(),
+        }
+    }
+}
+
+/// Recursively clone a "find" pattern and its children. Each clone is recorded in `clones`, keyed by
+/// the address of the pattern it was cloned from. Clones get fresh uids, so they can't be confused
+/// with the originals when saving and loading or when drawing the find tree.
+fn clone_find_pattern(
+    pattern: &FindPatternRef,
+    clones: &mut HashMap<*const RefCell<FindPattern>, FindPatternRef>,
+) -> FindPatternRef {
+    let original = pattern.borrow();
+    let clone = Rc::new(RefCell::new(FindPattern {
+        pattern: original.pattern.clone(),
+        multimatch: original.multimatch,
+        optional: original.optional,
+        attribute: original.attribute,
+        children: original
+            .children
+            .iter()
+            .map(|child| clone_find_pattern(child, clones))
+            .collect(),
+        label: original.label.clone(),
+        uid: rand::random(),
+    }));
+    clones.insert(Rc::as_ptr(pattern), Rc::clone(&clone));
+    clone
+}
+
+impl GrammarTab {
+    /// Iterate over the rules of every phase, in the order they apply.
+    fn rules(&self) -> impl Iterator<Item = &GrammarRule> {
+        self.phases.iter().flat_map(|phase| &phase.rules)
+    }
+
+    fn rules_mut(&mut self) -> impl Iterator<Item = &mut GrammarRule> {
+        self.phases.iter_mut().flat_map(|phase| &mut phase.rules)
+    }
+
+    /// Return the number of rules across all phases.
+    pub fn num_rules(&self) -> usize {
+        self.rules().count()
+    }
+}
+
+/// Describe each enabled grammar rule whose find or replace pattern hasn't been set.
+pub fn config_errors(data: &GrammarTab) -> Vec<String> {
+    let mut errors = Vec::new();
+    let enabled_rules = data.rules().enumerate();
+    for (i, rule) in enabled_rules.filter(|(_, rule)| rule.enabled) {
+        if rule.find_patterns.is_empty() {
+            errors.push(format!("{} has no find pattern", rule.title(i)));
+        } else if rule.replace_patterns.is_empty() {
+            errors.push(format!("{} has no replace pattern", rule.title(i)));
+        } else if !rule
+            .replace_patterns
+            .iter()
+            .all(|pattern| pattern.is_valid(&rule.find_patterns))
+        {
+            errors.push(format!(
+                "{} refers to a find pattern that was deleted",
+                rule.title(i)
+            ));
+        }
+    }
+    errors
 }
 
 /// Render contents of the 'grammar' tab.
-pub fn draw_grammar_tab(ui: &mut egui::Ui, data: &mut GrammarTab) {
+pub fn draw_grammar_tab(ui: &mut egui::Ui, data: &mut GrammarTab, lexicon: &Lexicon) {
     egui::ScrollArea::vertical().show(ui, |ui| {
         ui.heading("Rules");
         ui.add_space(5.0);
-        EditMode::draw_mode_picker(ui, &mut data.grammar_edit_mode);
+        ui.horizontal(|ui| {
+            EditMode::draw_mode_picker(ui, &mut data.grammar_edit_mode);
+            ui.separator();
+            ui.checkbox(&mut data.tree_view, "Tree View")
+                .on_hover_text("In view mode, show each rule's find patterns as a tree");
+            ui.separator();
+            ui.add(
+                egui::TextEdit::singleline(&mut data.rule_search)
+                    .hint_text("Search rules...")
+                    .desired_width(120.0),
+            )
+            .on_hover_text("Show only rules whose name, description, or find patterns match");
+        });
         let mode = data.grammar_edit_mode;
         ui.add_space(5.0);
         ui.group(|ui| {
@@ -240,123 +880,627 @@ pub fn draw_grammar_tab(ui: &mut egui::Ui, data: &mut GrammarTab) {
             ui.add_space(ui.spacing().item_spacing.y); // match the extra space at the bottom
             ui.set_width(ui.available_width());
 
+            // rules are numbered continuously across phases, so each has a single number
+            let mut first_number = 0;
+            let mut deleted_phase = None;
             let mut moved_rule = None;
-            for (index, rule) in data.grammar_rules.iter_mut().enumerate() {
-                let rule_id = egui::Id::new(format!("rule {index}"));
-                let should_delete =
-                    util::draw_reorderable(mode, ui, rule_id, index, &mut moved_rule, |ui| {
-                        draw_rule(ui, rule, index, mode)
-                    });
-                if should_delete {
-                    data.grammar_rules.remove(index);
-                    break;
+            let phase_titles: Vec<_> = data
+                .phases
+                .iter()
+                .enumerate()
+                .map(|(index, phase)| phase.title(index))
+                .collect();
+            let view = RulesView {
+                mode,
+                tree_view: data.tree_view,
+                search: &data.rule_search,
+                lexicon,
+                phase_titles: &phase_titles,
+            };
+            for (index, phase) in data.phases.iter_mut().enumerate() {
+                if draw_phase(ui, phase, index, first_number, view, &mut moved_rule) {
+                    deleted_phase = Some(index);
                 }
-                ui.add_space(3.0);
+                first_number += phase.rules.len();
+            }
+            if let Some((rule, index)) = moved_rule {
+                data.phases[index].rules.push(rule);
+            }
+            if let Some(index) = deleted_phase {
+                data.phases.remove(index);
             }
 
-            if mode.is_edit() {
-                if !data.grammar_rules.is_empty() {
-                    // draw space before 'add rule' button, which doubles as the drop zone for dragging a rule to the end
-                    // we can't just call ui.add_space() because we need to check the space for hovers
-                    let response = ui.allocate_rect(
-                        egui::Rect::from_min_size(
-                            ui.cursor().left_top(),
-                            egui::Vec2::new(ui.available_width(), 10.0),
-                        ),
-                        egui::Sense::hover(),
+            if mode.is_edit() && ui.button("Add Phase").clicked() {
+                data.phases.push(Default::default());
+            }
+        });
+
+        if data.num_rules() > 0 {
+            ui.add_space(5.0);
+            draw_grammar_preview(ui, data, lexicon);
+        }
+
+        ui.add_space(10.0);
+        ui.heading("Affixes");
+        ui.add_space(5.0);
+        ui.group(|ui| {
+            ui.set_width(ui.available_width());
+            draw_affixes(ui, &mut data.affixes, mode);
+        });
+    });
+}
+
+/// Render the table of affixes. Affixes can be changed in edit mode and removed in delete mode.
+fn draw_affixes(ui: &mut egui::Ui, affixes: &mut Vec<Affix>, mode: EditMode) {
+    if affixes.is_empty() {
+        ui.weak("(no affixes)");
+    }
+    let mut deleted = None;
+    egui::Grid::new("affix table")
+        .striped(true)
+        .spacing((10.0, 4.0))
+        .show(ui, |ui| {
+            for (index, affix) in affixes.iter_mut().enumerate() {
+                if mode.is_edit() {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut affix.name)
+                            .hint_text("Name")
+                            .desired_width(80.0),
                     );
-                    util::draw_reorder_drop_area(
-                        ui,
-                        data.grammar_rules.len(),
-                        &mut moved_rule,
-                        &response,
+                    egui::ComboBox::from_id_source(("affix word type", index))
+                        .selected_text(affix.word_type.name())
+                        .show_ui(ui, |ui| {
+                            for choice in WordType::iter() {
+                                let name = choice.name();
+                                ui.selectable_value(&mut affix.word_type, choice, name);
+                            }
+                        });
+                    egui::ComboBox::from_id_source(("affix attribute", index))
+                        .selected_text(affix.attribute.name())
+                        .show_ui(ui, |ui| {
+                            for choice in WordAttribute::iter() {
+                                ui.selectable_value(&mut affix.attribute, choice, choice.name());
+                            }
+                        });
+                    egui::ComboBox::from_id_source(("affix position", index))
+                        .selected_text(affix.position.name())
+                        .show_ui(ui, |ui| {
+                            for choice in AffixPosition::iter() {
+                                ui.selectable_value(&mut affix.position, choice, choice.name());
+                            }
+                        });
+                    ui.add(
+                        egui::TextEdit::singleline(&mut affix.form)
+                            .hint_text("Form")
+                            .desired_width(60.0),
                     );
-
-                    // if any rules were dragged and released, move them now
-                    if let Some(reordering) = moved_rule {
-                        reordering.apply(&mut data.grammar_rules)
+                } else {
+                    ui.label(&affix.name);
+                    ui.label(affix.word_type.name());
+                    ui.label(affix.attribute.name());
+                    ui.label(affix.position.name());
+                    let form = match affix.position {
+                        AffixPosition::Prefix => format!("{}-", affix.form),
+                        AffixPosition::Suffix => format!("-{}", affix.form),
+                    };
+                    let form = ui.button(egui::RichText::new(form).monospace());
+                    if util::draw_deletion_overlay(mode, ui, &form) {
+                        deleted = Some(index);
                     }
                 }
-
-                if ui.button("Add Rule").clicked() {
-                    data.grammar_rules.push(Default::default());
-                }
+                ui.end_row();
             }
         });
-    });
+    if let Some(index) = deleted {
+        affixes.remove(index);
+    }
+    if mode.is_edit() && ui.button("Add Affix").clicked() {
+        affixes.push(Default::default());
+    }
 }
 
-/// Render the find and replace patterns for a grammar rule. Return the entire rule's Response, as well
-/// as just the number label's Response (used for drag detection).
+/// Render the find and replace patterns for a grammar rule under a collapsible header. Return the
+/// entire rule's Response, as well as just the number label's Response (used for drag detection).
+/// `phase` is the index of the phase the rule belongs to.
 fn draw_rule(
     ui: &mut egui::Ui,
     rule: &mut GrammarRule,
     index: usize,
-    mode: EditMode,
+    phase: usize,
+    view: RulesView<'_>,
+    duplicated: &mut Option<usize>,
+    moved: &mut Option<(usize, usize)>, // (rule number, destination phase)
 ) -> (egui::Response, egui::Response) {
-    let response = ui.horizontal_wrapped(|ui| {
-        let label_sense = match mode {
-            EditMode::View => egui::Sense::hover(),
-            EditMode::Edit => egui::Sense::drag(),
-            EditMode::Delete => egui::Sense::click(),
-        };
-        let number_label = egui::Label::new(format!("{}.", index + 1))
-            .selectable(mode.is_view())
-            .sense(label_sense);
-        let label_response = ui.add(number_label);
-        if rule.find_patterns.is_empty() {
-            // no find pattern has been set yet
-            draw_find_node_selector(ui, mode, |new| {
-                rule.find_patterns.push(new);
-                recompute_pattern_labels(rule);
+    let mode = view.mode;
+    let id = ui.make_persistent_id(("rule header", index));
+    let state =
+        egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true);
+    let is_open = state.is_open();
+    let (_, header, body) = state
+        .show_header(ui, |ui| {
+            let label_sense = match mode {
+                EditMode::View => egui::Sense::hover(),
+                EditMode::Edit => egui::Sense::drag(),
+                EditMode::Delete => egui::Sense::click(),
+            };
+            let number_label = egui::Label::new(format!("{}.", index + 1))
+                .selectable(mode.is_view())
+                .sense(label_sense);
+            let label_response = ui.add(number_label);
+            ui.checkbox(&mut rule.enabled, "").on_hover_text(
+                "Apply this rule. Turn it off to see what the grammar does without it.",
+            );
+            if !rule.enabled {
+                ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+            }
+            if mode.is_edit() {
+                ui.add(
+                    egui::TextEdit::singleline(&mut rule.name)
+                        .hint_text("Name (optional)")
+                        .desired_width(120.0),
+                );
+            } else if !rule.name.is_empty() {
+                ui.strong(&rule.name);
+            }
+            if mode.is_edit() {
+                ui.toggle_value(&mut rule.repeat, "↻").on_hover_text(
+                    "Repeat this rule, applying it to its own output until it stops changing \
+                    the words",
+                );
+            } else if rule.repeat {
+                ui.weak("↻")
+                    .on_hover_text("This rule repeats until it stops changing the words");
+            }
+            if mode.is_edit()
+                && ui
+                    .small_button("⧉")
+                    .on_hover_text("Duplicate this rule")
+                    .clicked()
+            {
+                *duplicated = Some(index);
+            }
+            if mode.is_edit() && view.phase_titles.len() > 1 {
+                ui.menu_button("➡", |ui| {
+                    ui.label("Move to Phase:");
+                    for (destination, title) in view.phase_titles.iter().enumerate() {
+                        let button = egui::Button::new(title);
+                        if ui.add_enabled(destination != phase, button).clicked() {
+                            *moved = Some((index, destination));
+                            ui.close_menu();
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Move this rule to the end of another phase");
+            }
+            if !is_open {
+                ui.weak(summarize_find_patterns(rule));
+            }
+            label_response
+        })
+        .body(|ui| {
+            if !rule.enabled {
+                ui.visuals_mut().override_text_color = Some(ui.visuals().weak_text_color());
+            }
+            if mode.is_edit() {
+                ui.add(
+                    egui::TextEdit::singleline(&mut rule.description)
+                        .hint_text("Description (optional)")
+                        .desired_width(ui.available_width() * 0.6),
+                );
+            } else if !rule.description.is_empty() {
+                ui.weak(&rule.description);
+            }
+            let show_tree = view.tree_view && mode.is_view() && !rule.find_patterns.is_empty();
+            if show_tree {
+                for pattern in &rule.find_patterns {
+                    draw_find_tree(ui, pattern);
+                }
+            }
+            ui.horizontal_wrapped(|ui| {
+                if rule.find_patterns.is_empty() {
+                    // no find pattern has been set yet
+                    draw_find_node_selector(ui, mode, |new| {
+                        rule.find_patterns.push(new);
+                        recompute_pattern_labels(rule);
+                    });
+                } else {
+                    // we have a find pattern
+                    if !show_tree {
+                        let mut was_modified = false;
+                        let patterns = &mut rule.find_patterns;
+                        draw_find_patterns(ui, patterns, &mut was_modified, mode, 0);
+                        if was_modified {
+                            recompute_pattern_labels(rule);
+                        }
+                    }
+                    ui.label("->");
+                    if !rule.replace_patterns.is_empty() {
+                        draw_replace_patterns(ui, rule, mode);
+                    } else if mode.is_edit() {
+                        draw_replace_node_selector(ui, mode, &rule.find_patterns, |new| {
+                            rule.replace_patterns.push(new)
+                        });
+                    } else {
+                        ui.colored_label(egui::Color32::RED, "(not set)");
+                    }
+                }
             });
-        } else {
-            // we have a find pattern
-            let mut was_modified = false;
-            draw_find_patterns(ui, &mut rule.find_patterns, &mut was_modified, mode);
-            if was_modified {
-                recompute_pattern_labels(rule);
-            }
-            ui.label("->");
-            if !rule.replace_patterns.is_empty() {
-                draw_replace_patterns(ui, rule, mode);
-            } else if mode.is_edit() {
-                draw_replace_node_selector(ui, mode, &rule.find_patterns, |new| {
-                    rule.replace_patterns.push(new)
-                });
+            if mode.is_view() && !rule.replace_patterns.is_empty() {
+                draw_rule_example(ui, rule, view.lexicon);
+            }
+            if !rule.find_patterns.is_empty() {
+                draw_rule_preview(ui, rule, view.lexicon);
+            }
+        });
+    let full_response = match body {
+        Some(body) => header.response.union(body.response),
+        None => header.response,
+    };
+    (full_response, header.inner)
+}
+
+/// Render a "find" pattern and its deep match patterns as an indented tree, with a collapsible
+/// header for each pattern that has deep matches.
+fn draw_find_tree(ui: &mut egui::Ui, pattern: &FindPatternRef) {
+    let node = pattern.borrow();
+    let text = egui::RichText::new(&node.label).monospace();
+    if node.children.is_empty() {
+        ui.horizontal(|ui| {
+            ui.add_space(ui.spacing().indent); // line up with the headers' text
+            ui.label(text);
+        });
+    } else {
+        egui::CollapsingHeader::new(text)
+            .id_source(("find tree", node.uid))
+            .default_open(true)
+            .show(ui, |ui| {
+                for child in &node.children {
+                    draw_find_tree(ui, child);
+                }
+            });
+    }
+}
+
+/// Render a phase's name and its rules, which can be reordered within the phase. `first_number`
+/// is the index of the phase's first rule among all rules. A rule moved out of the phase is put in
+/// `moved_rule` with the index of its new phase. Return true if the phase should be deleted.
+fn draw_phase(
+    ui: &mut egui::Ui,
+    phase: &mut Phase,
+    index: usize,
+    first_number: usize,
+    view: RulesView<'_>,
+    moved_rule: &mut Option<(GrammarRule, usize)>,
+) -> bool {
+    let mode = view.mode;
+    let id = ui.make_persistent_id(("phase header", index));
+    let state =
+        egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true);
+    let (_, header, body) = state
+        .show_header(ui, |ui| {
+            let label_response = if mode.is_edit() {
+                ui.add(
+                    egui::TextEdit::singleline(&mut phase.name)
+                        .hint_text(format!("Phase {}", index + 1))
+                        .desired_width(150.0),
+                )
             } else {
-                ui.colored_label(egui::Color32::RED, "(not set)");
+                let title = egui::RichText::new(phase.title(index)).strong();
+                let sense = if mode.is_delete() {
+                    egui::Sense::click()
+                } else {
+                    egui::Sense::hover()
+                };
+                ui.add(egui::Label::new(title).sense(sense))
+            };
+            let count = phase.rules.len();
+            let plural = if count == 1 { "" } else { "s" };
+            ui.weak(format!("({count} rule{plural})"));
+            label_response
+        })
+        .body(|ui| draw_phase_rules(ui, phase, index, first_number, view, moved_rule));
+    let full_response = match body {
+        Some(body) => header.response.union(body.response),
+        None => header.response,
+    };
+    util::draw_multipart_deletion_overlay(mode, ui, &header.inner, &full_response)
+}
+
+/// Render the rules of a phase, along with the controls to add more.
+fn draw_phase_rules(
+    ui: &mut egui::Ui,
+    phase: &mut Phase,
+    phase_index: usize,
+    first_number: usize,
+    view: RulesView<'_>,
+    moved_out: &mut Option<(GrammarRule, usize)>,
+) {
+    let mode = view.mode;
+    let rules = &mut phase.rules;
+    let mut moved_rule = None;
+    let mut dragging_rule = false;
+    let mut duplicated_rule = None;
+    let mut rule_to_move = None;
+    for (index, rule) in rules.iter_mut().enumerate() {
+        if !rule.matches_search(view.search) {
+            continue;
+        }
+        let number = first_number + index;
+        let rule_id = egui::Id::new(format!("rule {number}"));
+        dragging_rule |= ui.memory(|mem| mem.is_being_dragged(rule_id));
+        let should_delete =
+            util::draw_reorderable(mode, ui, rule_id, index, &mut moved_rule, |ui| {
+                draw_rule(
+                    ui,
+                    rule,
+                    number,
+                    phase_index,
+                    view,
+                    &mut duplicated_rule,
+                    &mut rule_to_move,
+                )
+            });
+        if should_delete {
+            rules.remove(index);
+            break;
+        }
+        ui.add_space(3.0);
+    }
+
+    // insert the copy right after the original, so it can be tweaked in place
+    if let Some(number) = duplicated_rule {
+        let index = number - first_number;
+        let copy = rules[index].clone();
+        rules.insert(index + 1, copy);
+    }
+
+    // take the rule out of this phase, and let the caller add it to the other one
+    if let Some((number, destination)) = rule_to_move {
+        let rule = rules.remove(number - first_number);
+        *moved_out = Some((rule, destination));
+    }
+
+    if mode.is_edit() {
+        if !rules.is_empty() {
+            // draw space before 'add rule' button, which doubles as the drop zone for dragging a rule to the end
+            // we can't just call ui.add_space() because we need to check the space for hovers
+            let response = ui.allocate_rect(
+                egui::Rect::from_min_size(
+                    ui.cursor().left_top(),
+                    egui::Vec2::new(ui.available_width(), 10.0),
+                ),
+                egui::Sense::hover(),
+            );
+            util::draw_reorder_drop_area(ui, rules.len(), &mut moved_rule, &response);
+
+            // if any rules were dragged and released, move them now. ignore drags that came from
+            // some other phase
+            if let Some(reordering) = moved_rule.filter(|_| dragging_rule) {
+                reordering.apply(rules)
+            }
+        }
+
+        if ui.button("Add Rule").clicked() {
+            rules.push(Default::default());
+        }
+    }
+}
+
+/// Render a field for sample input, and show what the enabled rules rewrite it to.
+fn draw_grammar_preview(ui: &mut egui::Ui, data: &mut GrammarTab, lexicon: &Lexicon) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Test All Rules:");
+        ui.add(
+            egui::TextEdit::singleline(&mut data.sample)
+                .hint_text("e.g. the/Det dog/Noun#pl")
+                .desired_width(200.0),
+        )
+        .on_hover_text("Words are written as text/Type#tag, where the type and tags are optional");
+        if data.sample.trim().is_empty() {
+            return;
+        }
+        let words = data.sample.split_whitespace().map(Word::parse);
+        match words.collect::<Result<Vec<_>, _>>() {
+            Ok(words) => {
+                let mut hit_limit = false;
+                let output = apply_grammar(&data.phases, &words, lexicon, &mut hit_limit);
+                let output: Vec<_> = output.iter().map(Word::display).collect();
+                ui.label("->");
+                ui.monospace(output.join(" "));
+                if hit_limit {
+                    draw_repeat_limit_warning(ui);
+                }
+            }
+            Err(error) => {
+                ui.colored_label(egui::Color32::RED, error);
             }
         }
-        label_response
     });
-    (response.response, response.inner)
 }
 
-/// Render the "find" portion of a grammar rule.
+/// Warn that rules were still changing the words when they stopped being applied.
+fn draw_repeat_limit_warning(ui: &mut egui::Ui) {
+    ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+        .on_hover_text(
+            "The rules were still changing the words when the repetition limit was reached. Some \
+        rules may be undoing each other, or a repeating rule may be rewriting its own output \
+        forever.",
+        );
+}
+
+/// Show the rule rewriting a made-up example of the words it matches, if there is one.
+fn draw_rule_example(ui: &mut egui::Ui, rule: &GrammarRule, lexicon: &Lexicon) {
+    let mut words = vec![];
+    for pattern in &rule.find_patterns {
+        push_example_words(pattern, lexicon, &mut words);
+    }
+    if find_match(rule, &words).is_none() {
+        return;
+    }
+    let mut hit_limit = false;
+    let output = apply_rule(rule, &words, lexicon, &mut hit_limit);
+    let display = |words: &[Word]| {
+        words
+            .iter()
+            .map(Word::display)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    ui.horizontal_wrapped(|ui| {
+        ui.weak("Example:")
+            .on_hover_text("Words in brackets have no entry of that type in the lexicon");
+        ui.monospace(display(&words));
+        ui.label("->");
+        ui.monospace(display(&output));
+        if hit_limit {
+            draw_repeat_limit_warning(ui);
+        }
+    });
+}
+
+/// Add words that a find pattern matches to an example. Words are native words from the lexicon
+/// with the right type, or placeholders in brackets if there are none. Optional patterns are
+/// included, and group matches are given a single word.
+fn push_example_words(pattern: &FindPatternRef, lexicon: &Lexicon, words: &mut Vec<Word>) {
+    let node = pattern.borrow();
+    match &node.pattern {
+        PatternType::Phrase(_) => {
+            for child in &node.children {
+                push_example_words(child, lexicon, words);
+            }
+        }
+        PatternType::Word(ty) => {
+            // pick the same word every frame, so the example doesn't flicker
+            let text = lexicon
+                .iter()
+                .filter(|(_, entry)| entry.word_type.as_ref() == Some(ty))
+                .map(|(native, _)| native)
+                .min()
+                .map_or_else(|| format!("[{}]", ty.name().to_lowercase()), String::clone);
+            words.push(Word {
+                text,
+                word_type: Some(ty.clone()),
+                attributes: node.attribute.into_iter().collect(),
+            });
+        }
+        PatternType::Literal(text) => words.push(Word {
+            text: text.clone(),
+            word_type: None,
+            attributes: vec![],
+        }),
+    }
+}
+
+/// Render a field for sample input, and show where the rule matches it and what it rewrites it to.
+fn draw_rule_preview(ui: &mut egui::Ui, rule: &mut GrammarRule, lexicon: &Lexicon) {
+    ui.horizontal_wrapped(|ui| {
+        ui.weak("Test:");
+        ui.add(
+            egui::TextEdit::singleline(&mut rule.sample)
+                .hint_text("e.g. the/Det dog/Noun#pl")
+                .desired_width(160.0),
+        )
+        .on_hover_text("Words are written as text/Type#tag, where the type and tags are optional");
+        if rule.sample.trim().is_empty() {
+            return;
+        }
+        let words: Vec<Word> = match rule.sample.split_whitespace().map(Word::parse).collect() {
+            Ok(words) => words,
+            Err(error) => {
+                ui.colored_label(egui::Color32::RED, error);
+                return;
+            }
+        };
+        match find_match(rule, &words) {
+            Some(found) => {
+                let highlight = ui.visuals().selection.bg_fill;
+                for (i, word) in words.iter().enumerate() {
+                    let text = egui::RichText::new(word.display()).monospace();
+                    if found.span.contains(&i) {
+                        ui.label(text.background_color(highlight));
+                    } else {
+                        ui.label(text);
+                    }
+                }
+                ui.label("->");
+                let mut hit_limit = false;
+                let output = apply_rule(rule, &words, lexicon, &mut hit_limit);
+                let output: Vec<_> = output.iter().map(Word::display).collect();
+                ui.monospace(output.join(" "));
+                if hit_limit {
+                    draw_repeat_limit_warning(ui);
+                }
+            }
+            None => {
+                ui.weak("(no match)");
+            }
+        }
+    });
+}
+
+/// Summarize a rule's top-level find patterns on one line, for display when the rule is collapsed.
+fn summarize_find_patterns(rule: &GrammarRule) -> String {
+    if rule.find_patterns.is_empty() {
+        return "(not set)".to_owned();
+    }
+    let labels: Vec<_> = rule
+        .find_patterns
+        .iter()
+        .map(|pattern| pattern.borrow().label.clone())
+        .collect();
+    labels.join(" ")
+}
+
+/// Render the "find" portion of a grammar rule, or the deep match patterns of a "find" pattern.
+/// `depth` is 0 for the top-level patterns of a rule, and increases by 1 for each level of deep
+/// matching.
 fn draw_find_patterns(
     ui: &mut egui::Ui,
     patterns: &mut Vec<FindPatternRef>,
     rule_modified: &mut bool,
     mode: EditMode,
+    depth: usize,
 ) {
     match mode {
         EditMode::View => {
             for pattern in patterns {
-                draw_find_node(ui, &mut pattern.borrow_mut(), rule_modified, mode);
+                draw_find_node(ui, &mut pattern.borrow_mut(), rule_modified, mode, depth);
             }
         }
         EditMode::Edit => {
+            let mut moved_pattern = None;
+            let mut dragging_pattern = false; // ignore drags that came from some other list
             for i in 0..patterns.len() {
                 *rule_modified |= draw_find_pattern_menu(ui, "+", |new| patterns.insert(i, new));
-                draw_find_node(ui, &mut patterns[i].borrow_mut(), rule_modified, mode);
+                let pattern = Rc::clone(&patterns[i]);
+                let id = egui::Id::new(("find pattern", Rc::as_ptr(&pattern)));
+                dragging_pattern |= ui.memory(|mem| mem.is_being_dragged(id));
+                util::draw_reorderable(mode, ui, id, i, &mut moved_pattern, |ui| {
+                    let element = ui.horizontal_wrapped(|ui| {
+                        let handle = util::draw_drag_handle(ui);
+                        draw_find_node(ui, &mut pattern.borrow_mut(), rule_modified, mode, depth);
+                        handle
+                    });
+                    (element.response, element.inner)
+                });
+            }
+
+            // the last "+" button doubles as the drop zone for dragging an element to the end
+            let end = ui.scope(|ui| draw_find_pattern_menu(ui, "+", |new| patterns.push(new)));
+            *rule_modified |= end.inner;
+            util::draw_reorder_drop_area(ui, patterns.len(), &mut moved_pattern, &end.response);
+
+            // if any elements were dragged and released, move them now
+            if let Some(reordering) = moved_pattern.filter(|_| dragging_pattern) {
+                reordering.apply(patterns);
+                *rule_modified = true;
             }
-            *rule_modified |= draw_find_pattern_menu(ui, "+", |new| patterns.push(new));
         }
         EditMode::Delete => {
             patterns.retain(|pattern| {
                 let should_delete =
-                    draw_find_node(ui, &mut pattern.borrow_mut(), rule_modified, mode);
+                    draw_find_node(ui, &mut pattern.borrow_mut(), rule_modified, mode, depth);
                 *rule_modified |= should_delete;
                 !should_delete
             });
@@ -369,43 +1513,78 @@ fn draw_replace_patterns(ui: &mut egui::Ui, rule: &mut GrammarRule, mode: EditMo
     match mode {
         EditMode::View => {
             for pattern in &mut rule.replace_patterns {
-                draw_replace_node(ui, pattern, mode);
+                draw_replace_node(ui, pattern, &rule.find_patterns, mode);
             }
         }
         EditMode::Edit => {
+            let mut moved_pattern = None;
+            let mut dragging_pattern = false; // ignore drags that came from some other list
             for i in 0..rule.replace_patterns.len() {
                 draw_replace_pattern_menu(ui, "+", &rule.find_patterns, |new| {
                     rule.replace_patterns.insert(i, new)
                 });
-                draw_replace_node(ui, &mut rule.replace_patterns[i], mode);
+                let id = ui.id().with(("replace pattern", i));
+                dragging_pattern |= ui.memory(|mem| mem.is_being_dragged(id));
+                let pattern = &mut rule.replace_patterns[i];
+                util::draw_reorderable(mode, ui, id, i, &mut moved_pattern, |ui| {
+                    let element = ui.horizontal(|ui| {
+                        let handle = util::draw_drag_handle(ui);
+                        draw_replace_node(ui, pattern, &rule.find_patterns, mode);
+                        handle
+                    });
+                    (element.response, element.inner)
+                });
             }
-            draw_replace_pattern_menu(ui, "+", &rule.find_patterns, |new: ReplacePattern| {
-                rule.replace_patterns.push(new)
+
+            // the last "+" button doubles as the drop zone for dragging an element to the end
+            let end = ui.scope(|ui| {
+                draw_replace_pattern_menu(ui, "+", &rule.find_patterns, |new: ReplacePattern| {
+                    rule.replace_patterns.push(new)
+                })
             });
+            let len = rule.replace_patterns.len();
+            util::draw_reorder_drop_area(ui, len, &mut moved_pattern, &end.response);
+
+            // if any elements were dragged and released, move them now
+            if let Some(reordering) = moved_pattern.filter(|_| dragging_pattern) {
+                reordering.apply(&mut rule.replace_patterns);
+            }
         }
         EditMode::Delete => {
-            rule.replace_patterns.retain_mut(|pattern| {
-                let should_delete = draw_replace_node(ui, pattern, mode);
-                !should_delete && pattern.is_valid()
-            });
+            let find_patterns = &rule.find_patterns;
+            rule.replace_patterns
+                .retain_mut(|pattern| !draw_replace_node(ui, pattern, find_patterns, mode));
         }
     }
 }
 
+/// The hover text for an exact word pattern that's still set to the placeholder text.
+const PLACEHOLDER_HOVER_TEXT: &str =
+    "This is a placeholder. Click it in edit mode to enter a word.";
+
 /// Render one element in a "find" pattern. Return true if the element should be deleted.
 fn draw_find_node(
     ui: &mut egui::Ui,
     node: &mut FindPattern,
     rule_modified: &mut bool,
     mode: EditMode,
+    depth: usize,
 ) -> bool {
-    let text = egui::RichText::new(&node.label).monospace();
+    let mut text = egui::RichText::new(&node.label).monospace();
+    let placeholder =
+        matches!(&node.pattern, PatternType::Literal(word) if word == PLACEHOLDER_WORD);
+    if placeholder {
+        text = text.color(ui.visuals().warn_fg_color);
+    }
     match mode {
         EditMode::View => {
-            let _ = ui.button(text);
+            let response = ui.button(text);
+            if placeholder {
+                response.on_hover_text(PLACEHOLDER_HOVER_TEXT);
+            }
         }
         EditMode::Edit => {
-            ui.menu_button(text, |ui| {
+            let response = ui.menu_button(text, |ui| {
                 egui::Frame::none()
                     .inner_margin(egui::Vec2::splat(6.0))
                     .show(ui, |ui| {
@@ -429,15 +1608,29 @@ fn draw_find_node(
                             .checkbox(&mut node.optional, "Optional Matching")
                             .on_hover_text("Match this rule even if this element is not present")
                             .changed();
+                        if matches!(node.pattern, PatternType::Word(_)) {
+                            *rule_modified |= draw_attribute_menu(ui, &mut node.attribute);
+                        }
                         if !matches!(node.pattern, PatternType::Literal(_)) {
                             ui.separator();
-                            *rule_modified |=
-                                draw_find_pattern_menu(ui, "Add Deep Match...", |new| {
-                                    node.children.push(new)
-                                });
+                            if depth + 1 < MAX_MATCH_DEPTH {
+                                *rule_modified |=
+                                    draw_find_pattern_menu(ui, "Add Deep Match...", |new| {
+                                        node.children.push(new)
+                                    });
+                            } else {
+                                ui.add_enabled(false, egui::Button::new("Add Deep Match..."))
+                                    .on_disabled_hover_text(format!(
+                                        "Deep matches can only be nested {} levels deep",
+                                        MAX_MATCH_DEPTH - 1
+                                    ));
+                            }
                         }
                     });
             });
+            if placeholder {
+                response.response.on_hover_text(PLACEHOLDER_HOVER_TEXT);
+            }
         }
         EditMode::Delete => {
             let node = ui.button(text);
@@ -449,17 +1642,126 @@ fn draw_find_node(
     }
     if !node.children.is_empty() {
         ui.label("{");
-        draw_find_patterns(ui, &mut node.children, rule_modified, mode);
+        draw_find_patterns(ui, &mut node.children, rule_modified, mode, depth + 1);
         ui.label("}");
     }
     false
 }
 
-/// Render one element in a "replace" pattern. Return true if the element should be deleted.
-fn draw_replace_node(ui: &mut egui::Ui, node: &mut ReplacePattern, mode: EditMode) -> bool {
-    let text = egui::RichText::new(node.as_dbg_text()).monospace();
-    let node = ui.button(text);
-    util::draw_deletion_overlay(mode, ui, &node)
+/// Render a submenu for choosing an optional word attribute. Return true if the choice changed.
+fn draw_attribute_menu(ui: &mut egui::Ui, attribute: &mut Option<WordAttribute>) -> bool {
+    let selected = attribute.map_or("(any)", |attribute| attribute.name());
+    ui.menu_button(format!("Required Attribute: {selected}"), |ui| {
+        let mut changed = ui.selectable_value(attribute, None, "(any)").changed();
+        for choice in WordAttribute::iter() {
+            changed |= ui
+                .selectable_value(attribute, Some(choice), choice.name())
+                .changed();
+        }
+        if changed {
+            ui.close_menu();
+        }
+        changed
+    })
+    .inner
+    .unwrap_or(false)
+}
+
+/// Render one element in a "replace" pattern. Elements that refer to a deleted find pattern are
+/// shown in red, and a capture like that can be pointed at one of `find_patterns` instead. Return
+/// true if the element should be deleted.
+fn draw_replace_node(
+    ui: &mut egui::Ui,
+    node: &mut ReplacePattern,
+    find_patterns: &[FindPatternRef],
+    mode: EditMode,
+) -> bool {
+    let placeholder = node.is_placeholder();
+    let valid = node.is_valid(find_patterns);
+    let mut text = egui::RichText::new(node.as_dbg_text()).monospace();
+    if placeholder {
+        text = text.color(ui.visuals().warn_fg_color);
+    } else if !valid {
+        text = text.color(egui::Color32::RED);
+    }
+    let is_word = matches!(
+        node,
+        ReplacePattern::Literal(_)
+            | ReplacePattern::Translated(_)
+            | ReplacePattern::Placeholder { .. }
+    );
+    let is_dangling_capture = !valid && matches!(node, ReplacePattern::Capture { .. });
+    let mut response = if is_word && mode.is_edit() {
+        ui.menu_button(text, |ui| {
+            egui::Frame::none()
+                .inner_margin(egui::Vec2::splat(6.0))
+                .show(ui, |ui| draw_word_editor(ui, node));
+        })
+        .response
+    } else if is_dangling_capture && mode.is_edit() {
+        ui.menu_button(text, |ui| {
+            for choice in find_patterns {
+                for_each_in_subtree(choice, |pattern| {
+                    if ui.button(&pattern.borrow().label).clicked() {
+                        ui.close_menu();
+                        if let ReplacePattern::Capture { capture, .. } = node {
+                            *capture = Rc::downgrade(pattern);
+                        }
+                    }
+                });
+            }
+        })
+        .response
+    } else {
+        ui.button(text)
+    };
+    if placeholder {
+        response = response.on_hover_text(PLACEHOLDER_HOVER_TEXT);
+    } else if is_dangling_capture {
+        response = response.on_hover_text(
+            "Capture target deleted. Click this in edit mode to capture something else, or \
+            delete it in delete mode.",
+        );
+    } else if !valid {
+        response = response.on_hover_text(
+            "Agreement source or target deleted. Delete this agreement in delete mode, then \
+            add it again.",
+        );
+    }
+    util::draw_deletion_overlay(mode, ui, &response)
+}
+
+/// Render the contents of the popup for editing a word in a "replace" pattern: its text, and
+/// whether it's inserted as-is or translated through the lexicon.
+fn draw_word_editor(ui: &mut egui::Ui, node: &mut ReplacePattern) {
+    let placeholder = node.is_placeholder();
+    let (mut word, was_translated) = match node {
+        ReplacePattern::Literal(word) => (std::mem::take(word), false),
+        ReplacePattern::Translated(word) => (std::mem::take(word), true),
+        ReplacePattern::Placeholder { translated } => (String::new(), *translated),
+        _ => return,
+    };
+    let mut edited = false;
+    ui.horizontal(|ui| {
+        ui.label("Word: ");
+        let field = egui::TextEdit::singleline(&mut word).hint_text(PLACEHOLDER_WORD);
+        edited = ui.add(field).changed();
+    });
+    ui.separator();
+    let mut translated = was_translated;
+    ui.radio_value(&mut translated, false, "Exact Word")
+        .on_hover_text("Insert this conlang text as-is");
+    ui.radio_value(&mut translated, true, "Translated Word")
+        .on_hover_text("Insert the lexicon's translation of this native word");
+
+    // a placeholder becomes a real word as soon as it's edited, even if it's left blank
+    *node = if placeholder && !edited {
+        ReplacePattern::Placeholder { translated }
+    } else if translated {
+        ReplacePattern::Translated(word)
+    } else {
+        ReplacePattern::Literal(word)
+    };
 }
 
 /// Render the "find" pattern dropdown for a new rule. If an item is selected, the provided `on_select`
@@ -517,7 +1819,7 @@ fn draw_find_pattern_menu(
             ui.separator();
             if ui.button("Exact Word").clicked() {
                 ui.close_menu();
-                return Some(PatternType::Literal("word".to_owned()));
+                return Some(PatternType::Literal(PLACEHOLDER_WORD.to_owned()));
             }
             None
         })
@@ -547,7 +1849,8 @@ fn draw_replace_pattern_menu(
                     ui.close_menu();
                     selected = Some(ReplacePattern::Capture {
                         capture: Rc::downgrade(node),
-                        serde_label: String::new(),
+                        serde_uid: 0,
+                        legacy_label: String::new(),
                     });
                 }
             });
@@ -556,24 +1859,104 @@ fn draw_replace_pattern_menu(
             }
         }
         ui.separator();
-        if ui.button("Exact Word").clicked() {
+        if ui
+            .button("Exact Word")
+            .on_hover_text("Insert this conlang text as-is")
+            .clicked()
+        {
+            ui.close_menu();
+            return Some(ReplacePattern::Placeholder { translated: false });
+        }
+        if ui
+            .button("Translated Word")
+            .on_hover_text("Insert the lexicon's translation of a native word")
+            .clicked()
+        {
             ui.close_menu();
-            return Some(ReplacePattern::Literal("word".to_owned()));
+            return Some(ReplacePattern::Placeholder { translated: true });
         }
-        None
+        let mut nodes = vec![];
+        for choice in choices {
+            for_each_in_subtree(choice, |node| nodes.push(Rc::clone(node)));
+        }
+        ui.add_enabled_ui(nodes.len() > 1, |ui| {
+            ui.menu_button("Agreement", |ui| draw_agreement_menu(ui, &nodes))
+                .inner
+                .flatten()
+        })
+        .inner
     });
     if let Some(new) = response.inner.flatten() {
         action(new);
     }
 }
 
+/// Render the submenus for choosing the feature, source, and target of a new agreement. Return the
+/// agreement if one was chosen.
+fn draw_agreement_menu(ui: &mut egui::Ui, nodes: &[FindPatternRef]) -> Option<ReplacePattern> {
+    for feature in Feature::iter() {
+        let selected = ui
+            .menu_button(feature.name(), |ui| {
+                for source in nodes {
+                    let text = format!("From {}", source.borrow().label);
+                    let selected = ui
+                        .menu_button(text, |ui| {
+                            for target in nodes.iter().filter(|node| !Rc::ptr_eq(node, source)) {
+                                if ui.button(format!("To {}", target.borrow().label)).clicked() {
+                                    ui.close_menu();
+                                    return Some(ReplacePattern::Agree {
+                                        source: Rc::downgrade(source),
+                                        target: Rc::downgrade(target),
+                                        serde_source_uid: 0,
+                                        serde_target_uid: 0,
+                                        feature,
+                                    });
+                                }
+                            }
+                            None
+                        })
+                        .inner
+                        .flatten();
+                    if selected.is_some() {
+                        return selected;
+                    }
+                }
+                None
+            })
+            .inner
+            .flatten();
+        if selected.is_some() {
+            return selected;
+        }
+    }
+    None
+}
+
 /// Apply a function to each "find" pattern that is part of this pattern, including the root pattern
 /// itself and any deep match patterns.
 fn for_each_in_subtree(root: &FindPatternRef, mut function: impl FnMut(&FindPatternRef)) {
-    function(root);
-    for sub_pattern in &root.borrow().children {
-        function(sub_pattern);
+    fn visit(pattern: &FindPatternRef, function: &mut dyn FnMut(&FindPatternRef)) {
+        function(pattern);
+        for sub_pattern in &pattern.borrow().children {
+            visit(sub_pattern, function);
+        }
+    }
+    visit(root, &mut function);
+}
+
+/// Get the find pattern that a replace pattern refers to, but only if it's one of the given find
+/// patterns or their deep matches. Replace patterns can only refer to their own rule's find
+/// patterns, so anything else is treated as deleted.
+fn resolve_in_rule(
+    pattern: &FindPatternWeakRef,
+    find_patterns: &[FindPatternRef],
+) -> Option<FindPatternRef> {
+    let pattern = pattern.upgrade()?;
+    let mut in_rule = false;
+    for root in find_patterns {
+        for_each_in_subtree(root, |node| in_rule |= Rc::ptr_eq(node, &pattern));
     }
+    in_rule.then_some(pattern)
 }
 
 /// Recompute the text labels for all the pattern nodes in this rule. This should be
@@ -592,50 +1975,487 @@ fn recompute_pattern_labels(rule: &mut GrammarRule) {
     for node in &mut rule.find_patterns {
         node.borrow_mut().compute_label(&mut counter);
     }
+
+    // the labels above can still collide (e.g. a literal whose text looks like a numbered
+    // label), so disambiguate any repeats in the order they appear
+    let mut seen = HashSet::new();
+    for pattern in &rule.find_patterns {
+        for_each_in_subtree(pattern, |pattern| {
+            let mut pattern = pattern.borrow_mut();
+            let base_label = pattern.label.clone();
+            let mut suffix = 2;
+            while !seen.insert(pattern.label.clone()) {
+                pattern.label = format!("{base_label} ({suffix})");
+                suffix += 1;
+            }
+        });
+    }
+}
+
+/// The words matched by each "find" pattern, as (pattern, range of word indices) pairs.
+type Captures = Vec<(FindPatternRef, Range<usize>)>;
+
+/// A place where a rule's "find" patterns match a sequence of words.
+pub struct RuleMatch {
+    span: Range<usize>,
+    captures: Captures,
+}
+
+impl FindPattern {
+    /// Return true if a single word satisfies this pattern. Phrase patterns never match a single
+    /// word directly; they match through their deep match patterns instead.
+    fn matches_word(&self, word: &Word) -> bool {
+        let has_attribute = self
+            .attribute
+            .map_or(true, |attribute| word.attributes.contains(&attribute));
+        match &self.pattern {
+            PatternType::Word(ty) => word.word_type.as_ref() == Some(ty) && has_attribute,
+            PatternType::Literal(text) => word.text.to_lowercase() == text.to_lowercase(),
+            PatternType::Phrase(_) => false,
+        }
+    }
+}
+
+/// Apply each phase's rules to the words in turn. Within a phase, the rules are applied repeatedly
+/// until a pass through them leaves the words unchanged. If a phase or rule is still changing the
+/// words when its limit is reached, `hit_limit` is set to true.
+fn apply_grammar(
+    phases: &[Phase],
+    words: &[Word],
+    lexicon: &Lexicon,
+    hit_limit: &mut bool,
+) -> Vec<Word> {
+    let mut words = words.to_vec();
+    for phase in phases {
+        let mut changed = true;
+        for _ in 0..MAX_PHASE_PASSES {
+            let output = apply_rules(&phase.rules, &words, lexicon, hit_limit);
+            changed = output != words;
+            if !changed {
+                break;
+            }
+            words = output;
+        }
+        *hit_limit |= changed;
+    }
+    words
+}
+
+/// Apply each enabled rule in order to the words. Rules whose find or replace patterns haven't
+/// been set are skipped.
+fn apply_rules(
+    rules: &[GrammarRule],
+    words: &[Word],
+    lexicon: &Lexicon,
+    hit_limit: &mut bool,
+) -> Vec<Word> {
+    let mut words = words.to_vec();
+    let active_rules = rules.iter().filter(|rule| {
+        rule.enabled && !rule.find_patterns.is_empty() && !rule.replace_patterns.is_empty()
+    });
+    for rule in active_rules {
+        words = apply_rule(rule, &words, lexicon, hit_limit);
+    }
+    words
+}
+
+/// Rewrite the first place where the rule matches the words. A repeating rule is applied again to
+/// its own output until it no longer changes the words, and `hit_limit` is set to true if it's
+/// still changing them when its limit is reached.
+fn apply_rule(
+    rule: &GrammarRule,
+    words: &[Word],
+    lexicon: &Lexicon,
+    hit_limit: &mut bool,
+) -> Vec<Word> {
+    let max_times = if rule.repeat { MAX_RULE_REPEATS } else { 1 };
+    let mut words = words.to_vec();
+    for _ in 0..max_times {
+        let Some(found) = find_match(rule, &words) else {
+            return words;
+        };
+        let output = apply_match(rule, &words, &found, lexicon);
+        if output == words {
+            return words;
+        }
+        words = output;
+    }
+    *hit_limit |= rule.repeat
+        && find_match(rule, &words)
+            .is_some_and(|found| apply_match(rule, &words, &found, lexicon) != words);
+    words
+}
+
+/// Find the first place in `words` where the rule's "find" patterns match. Longer matches are
+/// preferred for group matching. Matches must contain at least one word.
+pub fn find_match(rule: &GrammarRule, words: &[Word]) -> Option<RuleMatch> {
+    (0..words.len()).find_map(|start| {
+        match_sequence(&rule.find_patterns, words, start)
+            .into_iter()
+            .find(|(end, _)| *end > start)
+            .map(|(end, captures)| RuleMatch {
+                span: start..end,
+                captures,
+            })
+    })
+}
+
+/// Return every way a sequence of patterns can match the words beginning at `start`, as the end of
+/// the match and the captured words. The most preferred matches come first.
+fn match_sequence(
+    patterns: &[FindPatternRef],
+    words: &[Word],
+    start: usize,
+) -> Vec<(usize, Captures)> {
+    let Some((first, rest)) = patterns.split_first() else {
+        return vec![(start, vec![])];
+    };
+    let mut matches = vec![];
+    for (end, captures) in match_pattern(first, words, start) {
+        for (rest_end, rest_captures) in match_sequence(rest, words, end) {
+            let mut all_captures = captures.clone();
+            all_captures.extend(rest_captures);
+            matches.push((rest_end, all_captures));
+        }
+    }
+    matches
+}
+
+/// Return every way a single pattern can match the words beginning at `start`, in the same form as
+/// `match_sequence()`. Phrase patterns match whatever their deep match patterns match.
+fn match_pattern(pattern: &FindPatternRef, words: &[Word], start: usize) -> Vec<(usize, Captures)> {
+    let node = pattern.borrow();
+    let mut matches: Vec<(usize, Captures)> = match &node.pattern {
+        PatternType::Phrase(_) if node.children.is_empty() => vec![],
+        PatternType::Phrase(_) => match_sequence(&node.children, words, start)
+            .into_iter()
+            .filter(|(end, _)| *end > start)
+            .collect(),
+        _ => {
+            let count = words[start..]
+                .iter()
+                .take_while(|word| node.matches_word(word))
+                .count();
+            let max_len = if node.multimatch { count } else { count.min(1) };
+            (1..=max_len)
+                .rev()
+                .map(|len| (start + len, vec![]))
+                .collect()
+        }
+    };
+    if node.optional {
+        matches.push((start, vec![]));
+    }
+    for (end, captures) in &mut matches {
+        captures.insert(0, (Rc::clone(pattern), start..*end));
+    }
+    matches
+}
+
+/// Rewrite the words of a match using the rule's "replace" patterns, and return the entire
+/// sequence of words with the rewrite in place.
+pub fn apply_match(
+    rule: &GrammarRule,
+    words: &[Word],
+    found: &RuleMatch,
+    lexicon: &Lexicon,
+) -> Vec<Word> {
+    let captured = |pattern: &FindPatternWeakRef| {
+        pattern.upgrade().and_then(|pattern| {
+            found
+                .captures
+                .iter()
+                .find(|(capture, _)| Rc::ptr_eq(capture, &pattern))
+                .map(|(_, range)| range.clone())
+        })
+    };
+
+    // copy agreeing features onto the matched words before they are rearranged
+    let mut matched = words.to_vec();
+    for replace_pattern in &rule.replace_patterns {
+        if let ReplacePattern::Agree {
+            source,
+            target,
+            feature,
+            ..
+        } = replace_pattern
+        {
+            let (Some(source), Some(target)) = (captured(source), captured(target)) else {
+                continue;
+            };
+            let value = matched[source].iter().find_map(|word| {
+                word.attributes
+                    .iter()
+                    .copied()
+                    .find(|attribute| attribute.feature() == *feature)
+            });
+            for word in &mut matched[target] {
+                word.attributes
+                    .retain(|attribute| attribute.feature() != *feature);
+                word.attributes.extend(value);
+            }
+        }
+    }
+
+    let mut output = words[..found.span.start].to_vec();
+    for replace_pattern in &rule.replace_patterns {
+        match replace_pattern {
+            ReplacePattern::Capture { capture, .. } => {
+                if let Some(range) = captured(capture) {
+                    output.extend_from_slice(&matched[range]);
+                }
+            }
+            ReplacePattern::Literal(literal) => output.push(Word {
+                text: literal.clone(),
+                word_type: None,
+                attributes: vec![],
+            }),
+            ReplacePattern::Translated(native) => output.push(translate_literal(native, lexicon)),
+            ReplacePattern::Placeholder { translated: false } => output.push(Word {
+                text: PLACEHOLDER_WORD.to_owned(),
+                word_type: None,
+                attributes: vec![],
+            }),
+            ReplacePattern::Placeholder { translated: true } => {
+                output.push(translate_literal(PLACEHOLDER_WORD, lexicon))
+            }
+            ReplacePattern::Agree { .. } => {}
+        }
+    }
+    output.extend_from_slice(&words[found.span.end..]);
+    output
+}
+
+/// Look up the conlang word for a native word inserted by a rule, taking its type from the lexicon
+/// too. Words with no translation yet are marked instead.
+fn translate_literal(native: &str, lexicon: &Lexicon) -> Word {
+    match lexicon
+        .get(&native.to_lowercase())
+        .filter(|entry| !entry.word.is_empty())
+    {
+        Some(entry) => Word {
+            text: entry.word.clone(),
+            word_type: entry.word_type.clone(),
+            attributes: vec![],
+        },
+        None => Word {
+            text: format!("[{native}?]"),
+            word_type: None,
+            attributes: vec![],
+        },
+    }
 }
 
 /// Because `ReplacePattern::Capture` contains a `Weak` reference to the captured `FindPattern`,
-/// it can't be serialized directly. So we also serialize the `FindPattern`'s current label, and
-/// during deserialization we use the label to associate with the correct `FindPattern`.
-pub fn save_grammar_serde_metadata(rules: &mut Vec<GrammarRule>) {
-    for rule in rules {
+/// it can't be serialized directly. So we also serialize the `FindPattern`'s uid, and during
+/// deserialization we use the uid to associate with the correct `FindPattern`.
+pub fn save_grammar_serde_metadata(grammar: &mut GrammarTab) {
+    for rule in grammar.rules_mut() {
+        // a reference outside the rule is saved as deleted, since it would be looked up among the
+        // wrong rule's find patterns on load
+        let uid = |pattern: &FindPatternWeakRef| {
+            resolve_in_rule(pattern, &rule.find_patterns)
+                .map(|find_pattern| find_pattern.borrow().uid)
+                .unwrap_or_default()
+        };
         for replace_pattern in &mut rule.replace_patterns {
             if let ReplacePattern::Capture {
-                capture,
-                serde_label,
+                capture, serde_uid, ..
+            } = replace_pattern
+            {
+                *serde_uid = uid(capture);
+            } else if let ReplacePattern::Agree {
+                source,
+                target,
+                serde_source_uid,
+                serde_target_uid,
+                ..
             } = replace_pattern
             {
-                *serde_label = capture
-                    .upgrade()
-                    .map(|find_pattern| find_pattern.borrow().label.clone())
-                    .unwrap_or_default();
+                *serde_source_uid = uid(source);
+                *serde_target_uid = uid(target);
             }
         }
     }
 }
 
-/// See `save_grammar_serde_metadata()` for why this function exists.
-pub fn load_grammar_serde_metadata(rules: &mut Vec<GrammarRule>) {
-    for rule in rules {
-        // map this rule's labels to their corresponding find patterns
-        let find_pattern_labels: HashMap<String, FindPatternRef> = rule
-            .find_patterns
-            .iter()
-            .map(|find_pattern| (find_pattern.borrow().label.clone(), Rc::clone(find_pattern)))
-            .collect();
+/// See `save_grammar_serde_metadata()` for why this function exists. Rules from saves made before
+/// phases existed are also moved into a phase of their own.
+pub fn load_grammar_serde_metadata(grammar: &mut GrammarTab) {
+    if !grammar.grammar_rules.is_empty() {
+        let rules = std::mem::take(&mut grammar.grammar_rules);
+        grammar.phases.insert(
+            0,
+            Phase {
+                name: String::new(),
+                rules,
+            },
+        );
+    }
+    for rule in grammar.rules_mut() {
+        // map this rule's uids (and labels, for older saves) to their corresponding find
+        // patterns, including deep matches. only this rule's patterns are considered, so
+        // identical uids or labels in other rules can't be mixed up with them. a uid or label
+        // that's shared within the rule is ambiguous, so it maps to nothing
+        let mut find_pattern_uids: HashMap<u64, Option<FindPatternRef>> = HashMap::new();
+        let mut find_pattern_labels: HashMap<String, Option<FindPatternRef>> = HashMap::new();
+        for root in &rule.find_patterns {
+            for_each_in_subtree(root, |find_pattern| {
+                let (uid, label) = {
+                    let find_pattern = find_pattern.borrow();
+                    (find_pattern.uid, find_pattern.label.clone())
+                };
+                find_pattern_uids
+                    .entry(uid)
+                    .and_modify(|entry| *entry = None)
+                    .or_insert_with(|| Some(Rc::clone(find_pattern)));
+                find_pattern_labels
+                    .entry(label)
+                    .and_modify(|entry| *entry = None)
+                    .or_insert_with(|| Some(Rc::clone(find_pattern)));
+            });
+        }
 
-        // look up each replace pattern's deserialized label to get a reference to the captured find pattern
+        // look up each replace pattern's deserialized uid to get a reference to the captured find pattern
         for replace_pattern in &mut rule.replace_patterns {
             if let ReplacePattern::Capture {
                 capture,
-                serde_label,
+                serde_uid,
+                legacy_label,
             } = replace_pattern
             {
-                match find_pattern_labels.get(serde_label) {
+                let find_pattern = if *serde_uid != 0 {
+                    find_pattern_uids.get(serde_uid)
+                } else {
+                    find_pattern_labels.get(legacy_label)
+                };
+                let find_pattern = find_pattern.and_then(Option::as_ref);
+                match find_pattern {
                     Some(find_pattern) => *capture = Rc::downgrade(find_pattern),
                     None => *capture = Weak::new(),
                 }
+                legacy_label.clear();
+            } else if let ReplacePattern::Agree {
+                source,
+                target,
+                serde_source_uid,
+                serde_target_uid,
+                ..
+            } = replace_pattern
+            {
+                let find = |uid: &u64| {
+                    find_pattern_uids
+                        .get(uid)
+                        .and_then(Option::as_ref)
+                        .map_or_else(Weak::new, Rc::downgrade)
+                };
+                *source = find(serde_source_uid);
+                *target = find(serde_target_uid);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_pattern(pattern: PatternType, children: Vec<FindPatternRef>) -> FindPatternRef {
+        Rc::new(RefCell::new(FindPattern {
+            children,
+            ..FindPattern::new(pattern)
+        }))
+    }
+
+    #[test]
+    fn recomputed_labels_are_unique() {
+        let noun = || PatternType::Word(WordType::Noun);
+        let literal = || PatternType::Literal("ka".to_owned());
+        let mut rule = GrammarRule {
+            find_patterns: vec![
+                find_pattern(noun(), vec![]),
+                find_pattern(
+                    PatternType::Phrase(PhraseType::Argument),
+                    vec![
+                        find_pattern(noun(), vec![]),
+                        find_pattern(literal(), vec![]),
+                    ],
+                ),
+                find_pattern(noun(), vec![]),
+                find_pattern(literal(), vec![]),
+                find_pattern(PatternType::Literal("ka\" 1".to_owned()), vec![]),
+            ],
+            ..GrammarRule::default()
+        };
+        recompute_pattern_labels(&mut rule);
+
+        let mut labels = Vec::new();
+        for root in &rule.find_patterns {
+            for_each_in_subtree(root, |pattern| labels.push(pattern.borrow().label.clone()));
+        }
+        assert_eq!(labels.len(), 7);
+        let unique: HashSet<_> = labels.iter().collect();
+        assert_eq!(unique.len(), labels.len(), "duplicate labels in {labels:?}");
+    }
+
+    /// Build a rule that captures its only find pattern, a noun with the given uid.
+    fn capturing_rule(uid: u64) -> GrammarRule {
+        let noun = find_pattern(PatternType::Word(WordType::Noun), vec![]);
+        noun.borrow_mut().uid = uid;
+        let mut rule = GrammarRule {
+            replace_patterns: vec![ReplacePattern::Capture {
+                capture: Rc::downgrade(&noun),
+                serde_uid: 0,
+                legacy_label: String::new(),
+            }],
+            find_patterns: vec![noun],
+            ..GrammarRule::default()
+        };
+        recompute_pattern_labels(&mut rule);
+        rule
+    }
+
+    /// Save two rules whose find patterns share a label and a uid, load them back, and check that
+    /// each rule's capture points at its own find pattern. `edit_save` can rewrite the saved JSON
+    /// before it's loaded.
+    fn assert_captures_survive_reload(uid: u64, edit_save: impl Fn(String) -> String) {
+        let mut grammar = GrammarTab {
+            phases: vec![Phase {
+                name: String::new(),
+                rules: vec![capturing_rule(uid), capturing_rule(uid)],
+            }],
+            ..GrammarTab::default()
+        };
+        save_grammar_serde_metadata(&mut grammar);
+        let json = edit_save(serde_json::to_string(&grammar).unwrap());
+        let mut loaded: GrammarTab = serde_json::from_str(&json).unwrap();
+        load_grammar_serde_metadata(&mut loaded);
+
+        assert_eq!(loaded.num_rules(), 2);
+        for rule in loaded.rules() {
+            let ReplacePattern::Capture { capture, .. } = &rule.replace_patterns[0] else {
+                panic!("replace pattern should be a capture");
+            };
+            let resolved = resolve_in_rule(capture, &rule.find_patterns);
+            assert!(resolved.is_some_and(|pattern| Rc::ptr_eq(&pattern, &rule.find_patterns[0])));
+        }
+    }
+
+    #[test]
+    fn identical_uids_resolve_within_their_rule() {
+        assert_captures_survive_reload(7, |json| json);
+    }
+
+    #[test]
+    fn identical_labels_resolve_within_their_rule() {
+        // make the save look like an older one, which identified captures by label instead of uid
+        let label = capturing_rule(0).find_patterns[0].borrow().label.clone();
+        let label = serde_json::to_string(&label).unwrap();
+        assert_captures_survive_reload(0, |json| {
+            json.replace(",\"uid\":0", "").replace(
+                "\"serde_uid\":0",
+                &format!("\"serde_uid\":0,\"serde_label\":{label}"),
+            )
+        });
+    }
+}
@@ -1,18 +1,41 @@
 use eframe::egui;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::BTreeSet;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 
+use crate::util;
+
+/// How long to wait for a second click before removing a clicked grapheme, in seconds. A double
+/// click edits the grapheme instead.
+const DOUBLE_CLICK_DELAY: f64 = 0.3;
+
 /// A grapheme or multigraph.
-#[derive(Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[derive(Clone, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct Grapheme(String);
 
 impl Grapheme {
+    /// Create a new grapheme from a string.
+    pub fn new(grapheme: impl Into<String>) -> Self {
+        Self(grapheme.into())
+    }
+
     /// Get a reference to the grapheme as a string slice.
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// Return true if the grapheme is written with more than one character, like <ch>. Combining
+    /// diacritics are counted as part of the character before them, so <é> written with a
+    /// combining accent is still a single character.
+    pub fn is_multigraph(&self) -> bool {
+        self.0
+            .chars()
+            .filter(|&chr| !is_combining_mark(chr))
+            .count()
+            > 1
+    }
 }
 
 impl Display for Grapheme {
@@ -21,6 +44,36 @@ impl Display for Grapheme {
     }
 }
 
+/// Return true if the character is a combining diacritic, which attaches to the character before
+/// it instead of standing on its own.
+fn is_combining_mark(chr: char) -> bool {
+    matches!(
+        chr,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// A grapheme that was clicked in a GraphemeInputField and will be removed unless the click turns
+/// out to be a double click.
+#[derive(Clone)]
+struct PendingRemoval {
+    index: usize, // position in the field, so duplicates in a sequence aren't all removed
+    grapheme: Grapheme,
+    since: f64,
+}
+
+/// A grapheme that was double-clicked in a GraphemeInputField and is being edited in the input
+/// buffer. It stays in the field until the edit is done, and is then replaced in place.
+#[derive(Clone)]
+struct EditedGrapheme {
+    index: usize, // position in the field, so the replacement goes in the same place
+    grapheme: Grapheme,
+}
+
 /// A container that can hold graphemes. The container can set its own policies on
 /// ordering and duplicate permissability.
 pub trait GraphemeStorage {
@@ -35,6 +88,23 @@ pub trait GraphemeStorage {
 
     /// Apply the given function to each grapheme, removing it if it returns false.
     fn update(&mut self, f: impl FnMut(&Grapheme) -> bool);
+
+    /// Replace a grapheme with another one, keeping its place if the container is ordered. `index`
+    /// is the grapheme's position in the order `update()` visits them, which tells duplicates apart.
+    /// If the grapheme is no longer there, the new one is added instead.
+    fn replace(&mut self, index: usize, old: &Grapheme, new: Grapheme);
+}
+
+/// Replace an item of a list in place, preferring the one at `index`. If no item matches, the new
+/// item is added to the end.
+fn replace_in_list<T>(list: &mut Vec<T>, index: usize, matches: impl Fn(&T) -> bool, new: T) {
+    let position = Some(index)
+        .filter(|&index| list.get(index).is_some_and(&matches))
+        .or_else(|| list.iter().position(&matches));
+    match position {
+        Some(position) => list[position] = new,
+        None => list.push(new),
+    }
 }
 
 impl GraphemeStorage for Vec<Grapheme> {
@@ -54,6 +124,38 @@ impl GraphemeStorage for Vec<Grapheme> {
     fn update(&mut self, f: impl FnMut(&Grapheme) -> bool) {
         self.retain(f);
     }
+
+    fn replace(&mut self, index: usize, old: &Grapheme, new: Grapheme) {
+        replace_in_list(self, index, |grapheme| grapheme == old, new);
+    }
+}
+
+/// Sequence elements are shown and typed in their text form.
+impl GraphemeStorage for Vec<SequenceElement> {
+    fn add(&mut self, grapheme: Grapheme) {
+        self.extend(SequenceElement::parse(grapheme.as_str()));
+    }
+
+    fn contains(&self, grapheme: &Grapheme) -> bool {
+        self.iter()
+            .any(|element| element.to_string() == grapheme.as_str())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn update(&mut self, mut f: impl FnMut(&Grapheme) -> bool) {
+        self.retain(|element| f(&Grapheme(element.to_string())));
+    }
+
+    fn replace(&mut self, index: usize, old: &Grapheme, new: Grapheme) {
+        let matches = |element: &SequenceElement| element.to_string() == old.as_str();
+        match SequenceElement::parse(new.as_str()) {
+            Some(new) => replace_in_list(self, index, matches, new),
+            None => self.retain(|element| !matches(element)),
+        }
+    }
 }
 
 impl GraphemeStorage for BTreeSet<Grapheme> {
@@ -72,19 +174,331 @@ impl GraphemeStorage for BTreeSet<Grapheme> {
     fn update(&mut self, f: impl FnMut(&Grapheme) -> bool) {
         self.retain(f);
     }
+
+    fn replace(&mut self, _index: usize, old: &Grapheme, new: Grapheme) {
+        self.remove(old);
+        self.insert(new);
+    }
+}
+
+/// The master grapheme inventory, which other grapheme fields may be linked to. It never holds
+/// duplicates, and its order is the language's alphabetical order, which the user can change.
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct MasterGraphemeStorage(Vec<Grapheme>);
+
+impl MasterGraphemeStorage {
+    /// Return an iterator over the graphemes in alphabetical order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Grapheme> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, grapheme: &Grapheme) -> bool {
+        self.0.contains(grapheme)
+    }
+
+    /// Add a grapheme to the end of the alphabet. Return false if it was already in the inventory.
+    pub fn insert(&mut self, grapheme: Grapheme) -> bool {
+        let is_new = !self.contains(&grapheme);
+        if is_new {
+            self.0.push(grapheme);
+        }
+        is_new
+    }
+
+    /// Replace a grapheme with another one at the same place in the alphabet. If the new grapheme is
+    /// already in the inventory, the old one is just removed, and if the old one isn't in the
+    /// inventory, the new one is added to the end.
+    pub fn rename(&mut self, old: &Grapheme, new: Grapheme) {
+        if self.contains(&new) {
+            self.0.retain(|grapheme| grapheme != old);
+        } else {
+            replace_in_list(&mut self.0, usize::MAX, |grapheme| grapheme == old, new);
+        }
+    }
+
+    /// Return the position of a grapheme in the alphabet, or None if it isn't in the inventory.
+    pub fn position(&self, grapheme: &str) -> Option<usize> {
+        self.0.iter().position(|other| other.as_str() == grapheme)
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&Grapheme) -> bool) {
+        self.0.retain(f);
+    }
+
+    /// Move a grapheme to a new position in the alphabet.
+    pub fn reorder(&mut self, reordering: &util::Reordering) {
+        reordering.apply(&mut self.0);
+    }
+
+    /// Put the alphabet in Unicode order.
+    pub fn sort(&mut self) {
+        self.0.sort();
+    }
+}
+
+impl Extend<Grapheme> for MasterGraphemeStorage {
+    fn extend<T: IntoIterator<Item = Grapheme>>(&mut self, iter: T) {
+        for grapheme in iter {
+            self.insert(grapheme);
+        }
+    }
+}
+
+impl IntoIterator for MasterGraphemeStorage {
+    type Item = Grapheme;
+    type IntoIter = std::vec::IntoIter<Grapheme>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
 }
 
-/// The type of the master grapheme inventory, which other grapheme fields may be linked to.
-pub type MasterGraphemeStorage = BTreeSet<Grapheme>;
+impl<'a> IntoIterator for &'a MasterGraphemeStorage {
+    type Item = &'a Grapheme;
+    type IntoIter = std::slice::Iter<'a, Grapheme>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl GraphemeStorage for MasterGraphemeStorage {
+    fn add(&mut self, grapheme: Grapheme) {
+        self.insert(grapheme);
+    }
+
+    fn contains(&self, grapheme: &Grapheme) -> bool {
+        self.contains(grapheme)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.is_empty()
+    }
+
+    fn update(&mut self, f: impl FnMut(&Grapheme) -> bool) {
+        self.retain(f);
+    }
+
+    fn replace(&mut self, _index: usize, old: &Grapheme, new: Grapheme) {
+        self.rename(old, new);
+    }
+}
+
+/// Split a word into graphemes from the inventory. At each position the longest matching grapheme
+/// is chosen, so that multigraphs like <ch> aren't split into <c> and <h>. Characters that don't
+/// begin any grapheme in the inventory become segments of their own.
+pub fn segment<'a>(word: &'a str, inventory: &MasterGraphemeStorage) -> Vec<&'a str> {
+    let mut segments = Vec::new();
+    let mut rest = word;
+    while let Some(first) = rest.chars().next() {
+        let len = inventory
+            .iter()
+            .map(Grapheme::as_str)
+            .filter(|grapheme| !grapheme.is_empty() && rest.starts_with(grapheme))
+            .map(str::len)
+            .max()
+            .unwrap_or(first.len_utf8());
+        segments.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    segments
+}
+
+/// The alphabetical order used to sort words in the conlang.
+#[derive(Clone, Copy, Default, Deserialize, Hash, PartialEq, Serialize)]
+pub enum Collation {
+    #[default]
+    Unicode,
+    Alphabet,
+}
+
+impl Collation {
+    pub fn iter() -> impl Iterator<Item = Self> {
+        [Self::Unicode, Self::Alphabet].into_iter()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Unicode => "Unicode",
+            Self::Alphabet => "Alphabet",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Unicode => "Sort words letter by letter in Unicode order",
+            Self::Alphabet => {
+                "Sort words grapheme by grapheme in the alphabetical order set in the graphemic \
+                inventory, with graphemes outside the inventory last"
+            }
+        }
+    }
+
+    /// Compare two words in this order. Case is ignored, except to break ties.
+    pub fn compare(&self, a: &str, b: &str, inventory: &MasterGraphemeStorage) -> Ordering {
+        let (a_lower, b_lower) = (a.to_lowercase(), b.to_lowercase());
+        let order = match self {
+            Self::Unicode => a_lower.cmp(&b_lower),
+            Self::Alphabet => {
+                alphabet_key(&a_lower, inventory).cmp(&alphabet_key(&b_lower, inventory))
+            }
+        };
+        order.then_with(|| a.cmp(b))
+    }
+}
+
+/// Segment a word and pair each segment with its position in the inventory, for sorting. Segments
+/// outside the inventory are placed after every grapheme in it.
+fn alphabet_key<'a>(word: &'a str, inventory: &MasterGraphemeStorage) -> Vec<(usize, &'a str)> {
+    segment(word, inventory)
+        .into_iter()
+        .map(|segment| {
+            let index = inventory.position(segment);
+            (index.unwrap_or(usize::MAX), segment)
+        })
+        .collect()
+}
+
+/// Find the multigraphs in the inventory that can also be spelled with shorter graphemes from the
+/// inventory, like <ch> when <c> and <h> also exist. Words containing these can be segmented in
+/// more than one way. Return each such multigraph along with one way of spelling it.
+pub fn find_ambiguous_multigraphs(inventory: &MasterGraphemeStorage) -> Vec<(&str, Vec<&str>)> {
+    inventory
+        .iter()
+        .map(Grapheme::as_str)
+        .filter(|multigraph| multigraph.chars().nth(1).is_some())
+        .filter_map(|multigraph| {
+            let parts = spell_with_parts(multigraph, multigraph, inventory)?;
+            Some((multigraph, parts))
+        })
+        .collect()
+}
+
+/// Spell `text` as a sequence of graphemes from the inventory, not counting `whole` itself. Return
+/// None if this isn't possible.
+fn spell_with_parts<'a>(
+    text: &str,
+    whole: &str,
+    inventory: &'a MasterGraphemeStorage,
+) -> Option<Vec<&'a str>> {
+    if text.is_empty() {
+        return Some(Vec::new());
+    }
+    inventory
+        .iter()
+        .map(Grapheme::as_str)
+        .filter(|&part| !part.is_empty() && part != whole && text.starts_with(part))
+        .find_map(|part| {
+            let mut rest = spell_with_parts(&text[part.len()..], whole, inventory)?;
+            rest.insert(0, part);
+            Some(rest)
+        })
+}
+
+/// One element of a grapheme sequence, which produces one of its alternative graphemes. An optional
+/// element may also produce nothing. As text, "(n)" marks an optional element and "a/e" separates
+/// alternatives.
+#[derive(Clone, Deserialize, PartialEq, Serialize)]
+#[serde(from = "SavedSequenceElement")]
+pub struct SequenceElement {
+    pub alternatives: Vec<Grapheme>, // never empty
+    pub optional: bool,
+}
+
+impl SequenceElement {
+    /// Parse the text form of a sequence element. Return None if it names no graphemes.
+    pub fn parse(text: &str) -> Option<Self> {
+        let (inner, optional) = match text
+            .strip_prefix('(')
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            Some(inner) if !inner.is_empty() => (inner, true),
+            _ => (text, false),
+        };
+        let alternatives: Vec<_> = inner
+            .split('/')
+            .filter(|alternative| !alternative.is_empty())
+            .map(Grapheme::new)
+            .collect();
+        (!alternatives.is_empty()).then_some(Self {
+            alternatives,
+            optional,
+        })
+    }
+
+    /// Return true if the element is a single grapheme that is always produced.
+    pub fn is_plain(&self) -> bool {
+        !self.optional && self.alternatives.len() == 1
+    }
+}
+
+impl Display for SequenceElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let joined = self
+            .alternatives
+            .iter()
+            .map(Grapheme::as_str)
+            .collect::<Vec<_>>()
+            .join("/");
+        if self.optional {
+            write!(f, "({joined})")
+        } else {
+            f.write_str(&joined)
+        }
+    }
+}
+
+/// The saved form of a sequence element. Older saves stored each element as a grapheme, using the
+/// text form for optional elements and alternatives.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedSequenceElement {
+    Text(String),
+    Element {
+        alternatives: Vec<Grapheme>,
+        optional: bool,
+    },
+}
+
+impl From<SavedSequenceElement> for SequenceElement {
+    fn from(saved: SavedSequenceElement) -> Self {
+        match saved {
+            SavedSequenceElement::Text(text) => Self::parse(&text).unwrap_or(Self {
+                alternatives: vec![Grapheme(text)],
+                optional: false,
+            }),
+            SavedSequenceElement::Element {
+                alternatives,
+                optional,
+            } => Self {
+                alternatives,
+                optional,
+            },
+        }
+    }
+}
 
 /// A TextField-like widget for storing graphemes.
 pub struct GraphemeInputField<'data, 'buffer, 'master, Storage: GraphemeStorage> {
     graphemes: &'data mut Storage,
     input: &'buffer mut String,
     master: Option<&'master MasterGraphemeStorage>,
+    context_menu: Option<Box<dyn FnMut(&mut egui::Ui, &Grapheme) + 'master>>,
+    on_edit: Option<Box<dyn FnMut(&Grapheme, &Grapheme) + 'master>>,
     small: bool,
     allow_editing: bool,
     interactable: bool,
+    sequence_notation: bool,
+    comma_separated: bool,
     id: egui::Id,
 }
 
@@ -99,9 +513,13 @@ impl<'data, 'buffer, 'master, Storage: GraphemeStorage>
             graphemes,
             input,
             master: None,
+            context_menu: None,
+            on_edit: None,
             small: false,
             allow_editing: true,
             interactable: true,
+            sequence_notation: false,
+            comma_separated: false,
             id: egui::Id::new(id),
         }
     }
@@ -113,6 +531,20 @@ impl<'data, 'buffer, 'master, Storage: GraphemeStorage>
         self
     }
 
+    /// Show a menu when a grapheme is right-clicked. The menu's contents are drawn by the given
+    /// function, which is passed the grapheme that was clicked.
+    pub fn context_menu(mut self, menu: impl FnMut(&mut egui::Ui, &Grapheme) + 'master) -> Self {
+        self.context_menu = Some(Box::new(menu));
+        self
+    }
+
+    /// Call the given function with the old and new grapheme whenever the user changes a grapheme by
+    /// double-clicking it, so that anything referring to the old grapheme can be updated.
+    pub fn on_edit(mut self, on_edit: impl FnMut(&Grapheme, &Grapheme) + 'master) -> Self {
+        self.on_edit = Some(Box::new(on_edit));
+        self
+    }
+
     /// Make the input field much lower profile. The frame border and hint text will
     /// disappear once some graphemes have been added.
     pub fn small(mut self, small: bool) -> Self {
@@ -132,23 +564,108 @@ impl<'data, 'buffer, 'master, Storage: GraphemeStorage>
         self
     }
 
+    /// Allow sequence notation, where "(n)" marks an optional grapheme and "a/e" picks one of
+    /// several. Each grapheme named this way is checked against the master list separately.
+    pub fn sequence_notation(mut self, allow: bool) -> Self {
+        self.sequence_notation = allow;
+        self
+    }
+
+    /// Let commas separate graphemes, in addition to whitespace.
+    pub fn comma_separated(mut self, allow: bool) -> Self {
+        self.comma_separated = allow;
+        self
+    }
+
+    /// Return true if the character separates one grapheme from the next in the input buffer.
+    fn is_separator(&self, chr: char) -> bool {
+        chr.is_whitespace() || self.comma_separated && chr == ','
+    }
+
+    /// Split text into graphemes and add them all. Duplicates are handled by the storage's policy,
+    /// so a set ignores graphemes it already contains.
+    fn add_all(&mut self, ui: &egui::Ui, text: &str) {
+        let graphemes: Vec<&str> = text.split(|chr| self.is_separator(chr)).collect();
+        for grapheme in graphemes {
+            if !grapheme.is_empty() {
+                self.add_typed(ui, grapheme.to_owned());
+            }
+        }
+    }
+
+    /// Add a grapheme typed in the input buffer. If a grapheme is being edited, the typed one takes
+    /// its place instead.
+    fn add_typed(&mut self, ui: &egui::Ui, text: String) {
+        let edited_id = self.id.with("edited grapheme");
+        let edited: Option<EditedGrapheme> = ui.data(|data| data.get_temp(edited_id));
+        let Some(edited) = edited else {
+            self.graphemes.add(Grapheme(text));
+            return;
+        };
+        ui.data_mut(|data| data.remove::<EditedGrapheme>(edited_id));
+        let new = Grapheme(text);
+        if new != edited.grapheme {
+            if let Some(on_edit) = &mut self.on_edit {
+                on_edit(&edited.grapheme, &new);
+            }
+        }
+        self.graphemes.replace(edited.index, &edited.grapheme, new);
+    }
+
     /// Draw the contents of the GraphemeInputField.
     fn show_contents(&mut self, ui: &mut egui::Ui) -> egui::Response {
         ui.horizontal_wrapped(|ui| {
             // add extra space between graphemes
             ui.spacing_mut().item_spacing.x += if self.small { -3.0 } else { 4.0 };
 
-            // draw graphemes, and remove them if clicked
+            // a clicked grapheme is removed once it's clear the click wasn't a double click
+            let pending_id = self.id.with("pending removal");
+            let pending: Option<PendingRemoval> = ui.data(|data| data.get_temp(pending_id));
+            let now = ui.input(|input| input.time);
+            let mut new_pending = None;
+            let mut edited = None;
+            let edited_id = self.id.with("edited grapheme");
+            let editing: Option<EditedGrapheme> = ui.data(|data| data.get_temp(edited_id));
+
+            // draw graphemes, remove them if clicked, and edit them if double clicked
+            let mut index = 0;
             self.graphemes.update(|grapheme| {
+                index += 1;
+                let is_edited = editing.as_ref().is_some_and(|editing| {
+                    editing.index == index - 1 && &editing.grapheme == grapheme
+                });
+                let pending_since = pending
+                    .as_ref()
+                    .filter(|pending| pending.index == index && &pending.grapheme == grapheme)
+                    .map(|pending| pending.since);
+                if pending_since.is_some_and(|since| now - since > DOUBLE_CLICK_DELAY) {
+                    return false;
+                }
+
                 // invalid if there is a master list and the grapheme isn't in it
-                let invalid = self
-                    .master
-                    .map_or(false, |master| !master.contains(grapheme));
+                let invalid = self.master.map_or(false, |master| {
+                    if self.sequence_notation {
+                        SequenceElement::parse(grapheme.as_str()).map_or(true, |element| {
+                            element
+                                .alternatives
+                                .iter()
+                                .any(|alternative| !master.contains(alternative))
+                        })
+                    } else {
+                        !master.contains(grapheme)
+                    }
+                });
 
                 let mut text = egui::RichText::new(grapheme.as_str());
                 if invalid {
                     text = text.color(egui::Color32::RED);
                 }
+                if pending_since.is_some() {
+                    text = text.strikethrough();
+                }
+                if is_edited {
+                    text = text.underline();
+                }
                 let mut button = egui::Button::new(text);
                 if self.small {
                     button = button.small();
@@ -159,11 +676,62 @@ impl<'data, 'buffer, 'master, Storage: GraphemeStorage>
                         ui.colored_label(egui::Color32::RED, "Not in graphemic inventory");
                     });
                 };
+                if let Some(menu) = &mut self.context_menu {
+                    response = response.context_menu(|ui| menu(ui, grapheme));
+                }
 
-                // true to keep in list, false to remove
-                !self.allow_editing || !response.on_hover_text("Click to remove").clicked()
+                if !self.allow_editing {
+                    return true;
+                }
+                if is_edited {
+                    return true;
+                }
+                let response = response.on_hover_text("Click to remove, or double-click to edit");
+                if response.double_clicked() {
+                    edited = Some(EditedGrapheme {
+                        index: index - 1,
+                        grapheme: grapheme.clone(),
+                    });
+                    return true;
+                }
+                if response.clicked() {
+                    new_pending = Some(PendingRemoval {
+                        index,
+                        grapheme: grapheme.clone(),
+                        since: now,
+                    });
+                }
+                true
             });
 
+            // copy an edited grapheme into the input buffer, and replace it once the user is done
+            // typing
+            if let Some(edited) = edited {
+                if !self.input.is_empty() {
+                    let text = std::mem::take(self.input);
+                    self.add_typed(ui, text);
+                }
+                ui.data_mut(|data| data.remove::<EditedGrapheme>(edited_id));
+                *self.input = edited.grapheme.to_string();
+                ui.data_mut(|data| data.insert_temp(edited_id, edited));
+                ui.memory_mut(|mem| mem.request_focus(self.id));
+                let mut state = egui::TextEdit::load_state(ui.ctx(), self.id).unwrap_or_default();
+                let end = egui::text::CCursor::new(self.input.chars().count());
+                state.set_ccursor_range(Some(egui::text::CCursorRange::one(end)));
+                state.store(ui.ctx(), self.id);
+                ui.data_mut(|data| data.remove::<PendingRemoval>(pending_id));
+            } else if let Some(new_pending) = new_pending {
+                ui.data_mut(|data| data.insert_temp(pending_id, new_pending));
+                ui.ctx()
+                    .request_repaint_after(std::time::Duration::from_secs_f64(DOUBLE_CLICK_DELAY));
+            } else if let Some(pending) = pending {
+                if now - pending.since > DOUBLE_CLICK_DELAY {
+                    ui.data_mut(|data| data.remove::<PendingRemoval>(pending_id));
+                } else {
+                    ui.ctx().request_repaint();
+                }
+            }
+
             if self.allow_editing {
                 // show input field if in edit mode
                 self.show_input(ui);
@@ -177,6 +745,26 @@ impl<'data, 'buffer, 'master, Storage: GraphemeStorage>
 
     /// Draw the text input field at the end of the widget.
     fn show_input(&mut self, ui: &mut egui::Ui) {
+        // add a pasted list of graphemes all at once, along with anything already typed, instead
+        // of letting the text edit insert it into the buffer
+        if ui.memory(|mem| mem.has_focus(self.id)) {
+            let pasted: Vec<String> = ui.input_mut(|input| {
+                let mut pasted = Vec::new();
+                input.events.retain(|event| match event {
+                    egui::Event::Paste(text) if text.contains(|chr| self.is_separator(chr)) => {
+                        pasted.push(text.clone());
+                        false
+                    }
+                    _ => true,
+                });
+                pasted
+            });
+            for text in pasted {
+                let text = std::mem::take(self.input) + &text;
+                self.add_all(ui, &text);
+            }
+        }
+
         let input_buffer = ui.add({
             let text_edit = egui::TextEdit::singleline(self.input)
                 .frame(false)
@@ -194,19 +782,27 @@ impl<'data, 'buffer, 'master, Storage: GraphemeStorage>
 
         // add grapheme on space or enter...
         if input_buffer.changed() {
-            while let Some(space_pos) = self.input.find(char::is_whitespace) {
+            while let Some((space_pos, space)) = self
+                .input
+                .char_indices()
+                .find(|&(_, chr)| self.is_separator(chr))
+            {
                 if space_pos > 0 {
-                    self.graphemes
-                        .add(Grapheme(self.input[..space_pos].to_owned()));
+                    let text = self.input[..space_pos].to_owned();
+                    self.add_typed(ui, text);
                 }
-                self.input.replace_range(..=space_pos, "");
+                self.input.replace_range(..space_pos + space.len_utf8(), "");
             }
         }
 
-        // ...or on loss of focus
-        if input_buffer.lost_focus() && !self.input.is_empty() {
-            self.graphemes.add(Grapheme(self.input.clone()));
-            self.input.clear();
+        // ...or on loss of focus. an edit that was erased leaves the grapheme as it was
+        if input_buffer.lost_focus() {
+            if self.input.is_empty() {
+                ui.data_mut(|data| data.remove::<EditedGrapheme>(self.id.with("edited grapheme")));
+            } else {
+                let text = std::mem::take(self.input);
+                self.add_typed(ui, text);
+            }
         }
     }
 }
@@ -1,30 +1,203 @@
 use eframe::egui;
+use itertools::Itertools;
+use rand::seq::IteratorRandom;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 
-#[derive(Default, Deserialize, Serialize)]
+use crate::{export, grammar, grapheme, swadesh, synthesis};
+
+/// The default number of times to try generating a word that is spelled differently from every
+/// other lexicon entry before falling back to longer words.
+const DEFAULT_UNIQUE_ATTEMPTS: u16 = 100;
+
+/// The fraction of the possible words that the lexicon can fill before warning that new words will
+/// struggle to be unique.
+const WORD_SPACE_WARNING_RATIO: f64 = 0.8;
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct LexiconTab {
     pub lexicon: Lexicon,
     allow_homonyms: bool,
-    num_homonyms: u32,
+    #[serde(default = "default_unique_attempts")]
+    unique_attempts: u16,
+    #[serde(default)]
+    collation: grapheme::Collation,
     #[serde(skip)]
     lexicon_search: String,
     #[serde(skip)]
     lexicon_search_mode: LexiconSearchMode,
+    #[serde(skip)]
+    lexicon_match_type: LexiconMatchType,
+    #[serde(skip)]
+    tag_filter: Option<String>, // only entries with this tag are shown
+    #[serde(skip)]
+    bulk_edit: bool,
+    #[serde(skip)]
+    selected: HashSet<String>, // native words selected in bulk edit mode
+    #[serde(skip)]
+    export_error: Option<String>,
+    #[serde(skip)]
+    sorted_natives: Option<(u64, Vec<String>)>, // table order, keyed on a hash of what it sorts by
 }
 
-pub type Lexicon = HashMap<String, String>;
+impl Default for LexiconTab {
+    fn default() -> Self {
+        Self {
+            lexicon: Default::default(),
+            allow_homonyms: false,
+            unique_attempts: default_unique_attempts(),
+            collation: Default::default(),
+            lexicon_search: Default::default(),
+            lexicon_search_mode: Default::default(),
+            lexicon_match_type: Default::default(),
+            tag_filter: None,
+            bulk_edit: false,
+            selected: Default::default(),
+            export_error: None,
+            sorted_natives: None,
+        }
+    }
+}
+
+impl LexiconTab {
+    /// Return how many times to try generating a word that isn't a homonym, or None if homonyms
+    /// are allowed.
+    pub fn unique_attempts(&self) -> Option<usize> {
+        (!self.allow_homonyms).then_some(self.unique_attempts as usize)
+    }
+
+    /// Sort the native phrases in the lexicon by collation order, unless the lexicon, the collation,
+    /// and the inventory are unchanged since they were last sorted.
+    fn update_sorted_natives(&mut self, inventory: &grapheme::MasterGraphemeStorage) {
+        let mut hasher = DefaultHasher::new();
+        self.collation.hash(&mut hasher);
+        inventory.iter().for_each(|g| g.as_str().hash(&mut hasher));
+        for (native, entry) in &self.lexicon {
+            (native, &entry.word).hash(&mut hasher);
+        }
+        let key = hasher.finish();
+        if self.sorted_natives.as_ref().map(|(k, _)| *k) != Some(key) {
+            let mut entries: Vec<_> = self.lexicon.iter().collect();
+            entries.sort_by(|(native_a, a), (native_b, b)| {
+                self.collation
+                    .compare(&a.word, &b.word, inventory)
+                    .then_with(|| native_a.cmp(native_b))
+            });
+            let natives = entries.into_iter().map(|(native, _)| native.clone());
+            self.sorted_natives = Some((key, natives.collect()));
+        }
+    }
+}
 
-/// The popup window for updating the lexicon.
+fn default_unique_attempts() -> u16 {
+    DEFAULT_UNIQUE_ATTEMPTS
+}
+
+pub type Lexicon = HashMap<String, LexiconEntry>;
+
+/// The conlang word for a lexicon entry, whether it was generated automatically during
+/// translation or entered manually, its word type if known, and any tags given by the user.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(from = "SavedLexiconEntry")]
+pub struct LexiconEntry {
+    pub word: String,
+    pub generated: bool,
+    pub word_type: Option<grammar::WordType>, // used to pick which affixes apply
+    pub tags: Vec<String>,                    // semantic domains like "kinship" or "color"
+}
+
+impl LexiconEntry {
+    /// Create an entry for a word entered by the user.
+    pub fn manual(word: impl Into<String>) -> Self {
+        Self {
+            word: word.into(),
+            generated: false,
+            word_type: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Create an entry for a word generated by the synthesizer.
+    pub fn generated(word: impl Into<String>) -> Self {
+        Self {
+            word: word.into(),
+            generated: true,
+            word_type: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// The saved form of a lexicon entry. Older saves only stored the conlang word, so those entries
+/// are loaded as manual entries.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedLexiconEntry {
+    Word(String),
+    Entry {
+        word: String,
+        generated: bool,
+        #[serde(default)]
+        word_type: Option<grammar::WordType>,
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+}
+
+impl From<SavedLexiconEntry> for LexiconEntry {
+    fn from(saved: SavedLexiconEntry) -> Self {
+        match saved {
+            SavedLexiconEntry::Word(word) => Self::manual(word),
+            SavedLexiconEntry::Entry {
+                word,
+                generated,
+                word_type,
+                tags,
+            } => Self {
+                word,
+                generated,
+                word_type,
+                tags,
+            },
+        }
+    }
+}
+
+/// Build a map from each conlang word (in lowercase) to the native words that translate to it,
+/// in alphabetical order. There is more than one native word when the conlang word is a homonym.
+pub fn reverse_lexicon(lexicon: &Lexicon) -> HashMap<String, Vec<&str>> {
+    let mut reversed: HashMap<String, Vec<&str>> = HashMap::new();
+    for (native, entry) in lexicon {
+        reversed
+            .entry(entry.word.to_lowercase())
+            .or_default()
+            .push(native);
+    }
+    for natives in reversed.values_mut() {
+        natives.sort_unstable();
+    }
+    reversed
+}
+
+/// The popup window for updating the lexicon. It's saved with the app state, so an unfinished edit
+/// survives an autosave or a crash.
+#[derive(Deserialize, Serialize)]
 pub struct LexiconEditWindow {
-    original_native_phrase: Option<String>, // todo change to Option<&String>
+    original_native_phrase: Option<String>,
     native_phrase: String,
     conlang_phrase: String,
+    word_type: Option<grammar::WordType>,
+    tags: String, // comma-separated
     overwrite_warning: Option<String>,
+    #[serde(skip)]
+    focus_native: bool, // whether to focus the native phrase field when the window is next drawn
 }
 
 /// The toggleable mode for the lexicon search field.
-#[derive(Default, PartialEq)]
+#[derive(Clone, Default, PartialEq)]
 pub enum LexiconSearchMode {
     #[default]
     Native,
@@ -32,31 +205,194 @@ pub enum LexiconSearchMode {
 }
 
 impl LexiconSearchMode {
-    fn matches(&self, native: &str, conlang: &str, search: &str) -> bool {
+    fn matches(&self, native: &str, conlang: &str, search: &LexiconSearch) -> bool {
+        match self {
+            LexiconSearchMode::Native => search.matches(native),
+            LexiconSearchMode::Conlang => search.matches(conlang),
+        }
+    }
+
+    /// Return the byte ranges of the search matches in the native and conlang words.
+    fn match_ranges(
+        &self,
+        native: &str,
+        conlang: &str,
+        search: &LexiconSearch,
+    ) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+        match self {
+            LexiconSearchMode::Native => (search.match_ranges(native), Vec::new()),
+            LexiconSearchMode::Conlang => (Vec::new(), search.match_ranges(conlang)),
+        }
+    }
+}
+
+/// How the lexicon search text is compared against entries.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum LexiconMatchType {
+    #[default]
+    Substring,
+    Regex,
+    Fuzzy,
+}
+
+impl LexiconMatchType {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Substring, Self::Regex, Self::Fuzzy].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Substring => "Substring",
+            Self::Regex => "Regex",
+            Self::Fuzzy => "Fuzzy",
+        }
+    }
+
+    fn description(&self) -> &'static str {
         match self {
-            LexiconSearchMode::Native => native.contains(search),
-            LexiconSearchMode::Conlang => conlang.contains(search),
+            Self::Substring => "Find entries containing the search text",
+            Self::Regex => "Find entries matching a regular expression",
+            Self::Fuzzy => "Find entries containing the search letters in order, with gaps allowed",
         }
     }
 }
 
+/// The lexicon search text, prepared for matching against entries.
+enum LexiconSearch {
+    Substring(String),
+    Regex(regex::Regex),
+    Fuzzy(String), // lowercase
+}
+
+impl LexiconSearch {
+    fn new(search: &str, match_type: LexiconMatchType) -> Result<Self, regex::Error> {
+        Ok(match match_type {
+            LexiconMatchType::Substring => Self::Substring(search.to_owned()),
+            LexiconMatchType::Regex => Self::Regex(regex::Regex::new(search)?),
+            LexiconMatchType::Fuzzy => Self::Fuzzy(search.to_lowercase()),
+        })
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Self::Substring(search) => text.contains(search.as_str()),
+            Self::Regex(regex) => regex.is_match(text),
+            Self::Fuzzy(search) => fuzzy_match(text, search).is_some(),
+        }
+    }
+
+    /// Return the byte ranges of the parts of a string that match the search, for highlighting.
+    fn match_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        match self {
+            Self::Substring(search) if search.is_empty() => Vec::new(),
+            Self::Substring(search) => text
+                .match_indices(search.as_str())
+                .map(|(start, found)| start..start + found.len())
+                .collect(),
+            Self::Regex(regex) => regex
+                .find_iter(text)
+                .map(|found| found.range())
+                .filter(|range| !range.is_empty())
+                .collect(),
+            Self::Fuzzy(search) => fuzzy_match(text, search).unwrap_or_default(),
+        }
+    }
+}
+
+/// Find the characters of a lowercase search string in a piece of text, in order but possibly
+/// with gaps between them. Return the byte range of each character found, or None if they
+/// weren't all found.
+fn fuzzy_match(text: &str, search: &str) -> Option<Vec<Range<usize>>> {
+    let mut search_chars = search.chars().peekable();
+    let mut ranges = Vec::new();
+    for (i, chr) in text.char_indices() {
+        let Some(&search_chr) = search_chars.peek() else {
+            break;
+        };
+        if chr.to_lowercase().any(|lower| lower == search_chr) {
+            ranges.push(i..i + chr.len_utf8());
+            search_chars.next();
+        }
+    }
+    search_chars.peek().is_none().then_some(ranges)
+}
+
+/// Lay out a table cell's text with the given byte ranges highlighted.
+fn highlighted_text(
+    ui: &egui::Ui,
+    text: &str,
+    ranges: &[Range<usize>],
+    italics: bool,
+) -> egui::text::LayoutJob {
+    let format = egui::TextFormat {
+        font_id: egui::TextStyle::Body.resolve(ui.style()),
+        color: ui.visuals().text_color(),
+        italics,
+        ..Default::default()
+    };
+    let highlight = egui::TextFormat {
+        color: ui.visuals().selection.stroke.color,
+        background: ui.visuals().selection.bg_fill,
+        ..format.clone()
+    };
+    let mut job = egui::text::LayoutJob::default();
+    let mut start = 0;
+    for range in ranges {
+        job.append(&text[start..range.start], 0.0, format.clone());
+        job.append(&text[range.clone()], 0.0, highlight.clone());
+        start = range.end;
+    }
+    job.append(&text[start..], 0.0, format);
+    job
+}
+
 /// Render contents of the 'lexicon' tab.
 pub fn draw_lexicon_tab(
     ui: &mut egui::Ui,
     data: &mut LexiconTab,
     lang_name: &str,
+    native_name: &str,
+    synthesis_tab: &synthesis::SynthesisTab,
     lexicon_edit_win: &mut Option<LexiconEditWindow>,
 ) {
     // add +10 pts vertical spacing between rows in this tab
     ui.spacing_mut().item_spacing += (0.0, 10.0).into();
 
-    let label = format!("Allow homonyms ({} currently)", data.num_homonyms);
+    let num_homonyms = reverse_lexicon(&data.lexicon)
+        .iter()
+        .filter(|(word, natives)| !word.is_empty() && natives.len() > 1)
+        .count();
+    let label = format!("Allow homonyms ({num_homonyms} currently)");
     let tooltip = "Homonyms are words with the same spelling or pronunciation, but different \
         meanings. Natural languages often have many homonyms, but constructed languages rarely do \
         to avoid confusion.";
-    ui.add_enabled(false, egui::Checkbox::new(&mut data.allow_homonyms, label))
-        .on_hover_text(tooltip)
-        .on_disabled_hover_text("Not yet implemented");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut data.allow_homonyms, label)
+            .on_hover_text(tooltip);
+        ui.separator();
+        ui.label("Attempts per unique word:");
+        let attempts = egui::DragValue::new(&mut data.unique_attempts).clamp_range(1..=10_000);
+        ui.add_enabled(!data.allow_homonyms, attempts)
+            .on_hover_text(
+                "How many words to generate while looking for one that isn't already in the \
+                lexicon. If they're all taken, longer words are tried instead.",
+            );
+        ui.separator();
+        ui.label("Alphabetical order:");
+        egui::ComboBox::from_id_source("lexicon collation")
+            .selected_text(data.collation.name())
+            .show_ui(ui, |ui| {
+                for collation in grapheme::Collation::iter() {
+                    ui.selectable_value(&mut data.collation, collation, collation.name())
+                        .on_hover_text(collation.description());
+                }
+            })
+            .response
+            .on_hover_text("How the lexicon and exported dictionary are sorted");
+    });
+    if !data.allow_homonyms {
+        draw_word_space_warning(ui, data.lexicon.len(), synthesis_tab);
+    }
 
     ui.separator();
 
@@ -71,15 +407,92 @@ pub fn draw_lexicon_tab(
         ui.selectable_value(
             &mut data.lexicon_search_mode,
             LexiconSearchMode::Native,
-            "English",
+            native_name,
         );
         ui.selectable_value(
             &mut data.lexicon_search_mode,
             LexiconSearchMode::Conlang,
             lang_name,
         );
+        ui.separator();
+        egui::ComboBox::from_id_source("lexicon match type")
+            .selected_text(data.lexicon_match_type.name())
+            .show_ui(ui, |ui| {
+                for match_type in LexiconMatchType::iter() {
+                    ui.selectable_value(
+                        &mut data.lexicon_match_type,
+                        match_type,
+                        match_type.name(),
+                    )
+                    .on_hover_text(match_type.description());
+                }
+            });
+        ui.separator();
+        let tags: BTreeSet<&str> = data
+            .lexicon
+            .values()
+            .flat_map(|entry| entry.tags.iter().map(String::as_str))
+            .collect();
+        ui.label("Tag:");
+        egui::ComboBox::from_id_source("lexicon tag filter")
+            .selected_text(data.tag_filter.as_deref().unwrap_or("(any)"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut data.tag_filter, None, "(any)");
+                for tag in tags {
+                    ui.selectable_value(&mut data.tag_filter, Some(tag.to_owned()), tag);
+                }
+            });
+        ui.separator();
+        let bulk_toggle = ui
+            .toggle_value(&mut data.bulk_edit, "Bulk Edit")
+            .on_hover_text("Select several entries at once");
+        if bulk_toggle.changed() {
+            data.selected.clear();
+        }
     });
 
+    // an invalid search shows every entry, along with the error
+    let search = match LexiconSearch::new(&data.lexicon_search, data.lexicon_match_type) {
+        Ok(search) => search,
+        Err(err) => {
+            ui.colored_label(egui::Color32::RED, "Invalid regular expression")
+                .on_hover_text(err.to_string());
+            LexiconSearch::Substring(String::new())
+        }
+    };
+
+    // bulk edit controls
+    if data.bulk_edit {
+        ui.horizontal(|ui| {
+            if ui.button("Select All Filtered").clicked() {
+                let filtered = data
+                    .lexicon
+                    .iter()
+                    .filter(|(native, entry)| {
+                        data.lexicon_search_mode
+                            .matches(native, &entry.word, &search)
+                            && data.tag_filter.iter().all(|tag| entry.tags.contains(tag))
+                    })
+                    .map(|(native, _)| native.clone());
+                data.selected.extend(filtered);
+            }
+            if ui.button("Clear Selection").clicked() {
+                data.selected.clear();
+            }
+            let delete_text = format!("Delete Selected ({})", data.selected.len());
+            let delete_btn = egui::Button::new(delete_text);
+            if ui
+                .add_enabled(!data.selected.is_empty(), delete_btn)
+                .clicked()
+            {
+                data.lexicon
+                    .retain(|native, _| !data.selected.contains(native));
+                data.selected.clear();
+                *lexicon_edit_win = None;
+            }
+        });
+    }
+
     // draw the lexicon table
     egui::ScrollArea::vertical().show(ui, |ui| {
         ui.group(|ui| {
@@ -87,7 +500,7 @@ pub fn draw_lexicon_tab(
             ui.spacing_mut().item_spacing.y -= 10.0;
 
             // draw the table header
-            ui.heading(format!("{} to {} Lexicon", lang_name, "English"));
+            ui.heading(format!("{} to {} Lexicon", lang_name, native_name));
             ui.separator();
 
             // draw the table body
@@ -95,17 +508,53 @@ pub fn draw_lexicon_tab(
                 .striped(true)
                 .min_col_width(100.0)
                 .show(ui, |ui| {
-                    for (native, conlang) in data.lexicon.iter() {
+                    data.update_sorted_natives(&synthesis_tab.graphemes);
+                    let natives = data.sorted_natives.iter().flat_map(|(_, natives)| natives);
+                    for native in natives {
+                        let entry = &data.lexicon[native];
                         if data
                             .lexicon_search_mode
-                            .matches(native, conlang, &data.lexicon_search)
+                            .matches(native, &entry.word, &search)
+                            && data.tag_filter.iter().all(|tag| entry.tags.contains(tag))
                         {
+                            if data.bulk_edit {
+                                let mut checked = data.selected.contains(native);
+                                if ui.checkbox(&mut checked, "").changed() {
+                                    if checked {
+                                        data.selected.insert(native.clone());
+                                    } else {
+                                        data.selected.remove(native);
+                                    }
+                                }
+                            }
+                            let (native_ranges, conlang_ranges) = data
+                                .lexicon_search_mode
+                                .match_ranges(native, &entry.word, &search);
+                            let conlang_text =
+                                highlighted_text(ui, &entry.word, &conlang_ranges, entry.generated);
                             let conlang_lbl = ui
-                                .selectable_label(false, conlang)
-                                .on_hover_text("Click to modify");
+                                .selectable_label(false, conlang_text)
+                                .on_hover_text(if entry.generated {
+                                    "Generated automatically. Click to modify"
+                                } else {
+                                    "Click to modify"
+                                });
                             let native_lbl = ui
-                                .selectable_label(false, native)
+                                .selectable_label(
+                                    false,
+                                    highlighted_text(ui, native, &native_ranges, false),
+                                )
                                 .on_hover_text("Click to modify");
+                            ui.horizontal(|ui| {
+                                for tag in &entry.tags {
+                                    let tag_btn = ui
+                                        .small_button(tag)
+                                        .on_hover_text("Show only entries with this tag");
+                                    if tag_btn.clicked() {
+                                        data.tag_filter = Some(tag.clone());
+                                    }
+                                }
+                            });
                             if conlang_lbl.clicked() || native_lbl.clicked() {
                                 *lexicon_edit_win =
                                     Some(LexiconEditWindow::edit_entry(native, &data.lexicon));
@@ -117,27 +566,247 @@ pub fn draw_lexicon_tab(
         });
     });
 
-    if ui.button("Add Manual Lexicon Entry").clicked() {
-        *lexicon_edit_win = Some(LexiconEditWindow::new_entry());
+    ui.horizontal(|ui| {
+        if ui.button("Add Manual Lexicon Entry").clicked() {
+            *lexicon_edit_win = Some(LexiconEditWindow::new_entry());
+        }
+        let current_entry = lexicon_edit_win
+            .as_ref()
+            .and_then(|win| win.original_native_phrase.as_deref());
+        let random_text = if current_entry.is_some() {
+            "Next Random Entry"
+        } else {
+            "Random Entry"
+        };
+        let random_btn = ui
+            .add_enabled(!data.lexicon.is_empty(), egui::Button::new(random_text))
+            .on_hover_text("Open a random entry, to review the vocabulary");
+        if random_btn.clicked() {
+            if let Some(native) = random_entry(&data.lexicon, current_entry) {
+                *lexicon_edit_win = Some(LexiconEditWindow::edit_entry(native, &data.lexicon));
+            }
+        }
+        let num_generated = data
+            .lexicon
+            .values()
+            .filter(|entry| entry.generated)
+            .count();
+        let clear_btn = ui
+            .add_enabled(
+                num_generated > 0,
+                egui::Button::new(format!("Clear Generated Words ({})", num_generated)),
+            )
+            .on_hover_text("Delete every entry generated during translation, keeping manual ones");
+        if clear_btn.clicked() {
+            data.lexicon.retain(|_, entry| !entry.generated);
+            data.selected.clear();
+            *lexicon_edit_win = None;
+        }
+        let regenerate_btn = ui
+            .add_enabled(
+                num_generated > 0 && synthesis::is_config_valid(synthesis_tab),
+                egui::Button::new("Regenerate Generated Words"),
+            )
+            .on_hover_text("Replace every generated word using the current synthesis settings")
+            .on_disabled_hover_text(
+                "There are no generated words, or this language's configuration contains errors.",
+            );
+        if regenerate_btn.clicked() {
+            regenerate_words(data, synthesis_tab);
+            *lexicon_edit_win = None;
+        }
+        ui.separator();
+        if ui
+            .button("Load Swadesh List")
+            .on_hover_text("Add 100 basic concepts that nearly every language has a word for")
+            .clicked()
+        {
+            load_swadesh_list(&mut data.lexicon);
+        }
+        let num_blank = data
+            .lexicon
+            .values()
+            .filter(|entry| entry.word.is_empty())
+            .count();
+        let fill_btn = ui
+            .add_enabled(
+                num_blank > 0 && synthesis::is_config_valid(synthesis_tab),
+                egui::Button::new(format!("Generate Blank Words ({})", num_blank)),
+            )
+            .on_hover_text("Generate a word for every entry that doesn't have one yet")
+            .on_disabled_hover_text(
+                "There are no blank words, or this language's configuration contains errors.",
+            );
+        if fill_btn.clicked() {
+            fill_blank_words(data, synthesis_tab);
+            *lexicon_edit_win = None;
+        }
+        ui.separator();
+        let export_btn = ui
+            .add_enabled(
+                !data.lexicon.is_empty(),
+                egui::Button::new("Export Dictionary"),
+            )
+            .on_hover_text("Save the lexicon as a printable HTML dictionary");
+        if export_btn.clicked() {
+            let html = export::dictionary_html(
+                &data.lexicon,
+                lang_name,
+                native_name,
+                &synthesis_tab.graphemes,
+                data.collation,
+            );
+            let file_name = format!("{lang_name} Dictionary.html");
+            data.export_error = export::save_with_dialog(&file_name, "html", &html)
+                .err()
+                .map(|error| format!("Couldn't export: {error}"));
+        }
+        let anki_btn = ui
+            .add_enabled(
+                !data.lexicon.is_empty(),
+                egui::Button::new("Export Flashcards"),
+            )
+            .on_hover_text("Save the lexicon as a tab-separated file that Anki can import");
+        if anki_btn.clicked() {
+            let tsv = export::anki_tsv(&data.lexicon, synthesis_tab);
+            let file_name = format!("{lang_name} Flashcards.txt");
+            data.export_error = export::save_with_dialog(&file_name, "txt", &tsv)
+                .err()
+                .map(|error| format!("Couldn't export: {error}"));
+        }
+    });
+    if let Some(error) = &data.export_error {
+        ui.colored_label(egui::Color32::RED, error);
     }
 
     // draw lexicon edit popup
     if let Some(edit_win) = lexicon_edit_win {
-        let request_close = edit_win.show(ui, lang_name, &mut data.lexicon);
+        let request_close = edit_win.show(ui, lang_name, native_name, &mut data.lexicon);
         if request_close {
             *lexicon_edit_win = None;
         }
     }
 }
 
+/// Warn the user if the lexicon is close to using up every word the syllable rules can generate,
+/// since new words would then have trouble being spelled differently from existing ones.
+fn draw_word_space_warning(
+    ui: &mut egui::Ui,
+    lexicon_size: usize,
+    synthesis_tab: &synthesis::SynthesisTab,
+) {
+    let weights = &synthesis_tab.syllable_wgts.1;
+    let Some(space) = synthesis::count_word_space(synthesis_tab, weights) else {
+        return; // unbounded
+    };
+    if lexicon_size as f64 > WORD_SPACE_WARNING_RATIO * space {
+        let text = format!(
+            "The lexicon has {lexicon_size} entries, but the syllable rules can only produce about \
+            {space:.0} different words. Consider expanding the syllable rules or the grapheme \
+            inventory so new words can be unique."
+        );
+        ui.colored_label(ui.visuals().warn_fg_color, text);
+    }
+}
+
+/// Pick a random native word from the lexicon, avoiding `skip` unless it's the only entry.
+fn random_entry<'a>(lexicon: &'a Lexicon, skip: Option<&str>) -> Option<&'a str> {
+    let mut rng = rand::thread_rng();
+    lexicon
+        .keys()
+        .map(String::as_str)
+        .filter(|native| Some(*native) != skip)
+        .choose(&mut rng)
+        .or_else(|| lexicon.keys().next().map(String::as_str))
+}
+
+/// Add an entry with a blank conlang word for each concept in the Swadesh list that isn't already
+/// in the lexicon. The blank words are filled in by `fill_blank_words`.
+fn load_swadesh_list(lexicon: &mut Lexicon) {
+    for (native, word_type) in swadesh::SWADESH_LIST {
+        lexicon
+            .entry(native.to_owned())
+            .or_insert_with(|| LexiconEntry {
+                word_type: Some(word_type),
+                ..LexiconEntry::generated("")
+            });
+    }
+}
+
+/// Replace every generated word in the lexicon with a newly generated one, leaving manual entries
+/// alone. Unless homonyms are allowed, new words are spelled differently from every other entry
+/// when possible.
+fn regenerate_words(data: &mut LexiconTab, synthesis_tab: &synthesis::SynthesisTab) {
+    generate_words(data, synthesis_tab, |entry| entry.generated);
+}
+
+/// Generate a word for every entry with a blank conlang word, such as those from the Swadesh list.
+fn fill_blank_words(data: &mut LexiconTab, synthesis_tab: &synthesis::SynthesisTab) {
+    generate_words(data, synthesis_tab, |entry| entry.word.is_empty());
+}
+
+/// Replace the word of every entry selected by `replace` with a newly generated one. Unless
+/// homonyms are allowed, new words are spelled differently from every other entry when possible.
+fn generate_words(
+    data: &mut LexiconTab,
+    synthesis_tab: &synthesis::SynthesisTab,
+    replace: impl Fn(&LexiconEntry) -> bool,
+) {
+    let unique_attempts = data.unique_attempts();
+    let mut taken: HashSet<String> = data
+        .lexicon
+        .values()
+        .filter(|entry| !replace(entry))
+        .map(|entry| entry.word.to_lowercase())
+        .collect();
+    for entry in data.lexicon.values_mut().filter(|entry| replace(entry)) {
+        let word = generate_word(synthesis_tab, &taken, unique_attempts);
+        taken.insert(word.to_lowercase());
+        entry.word = word;
+    }
+}
+
+/// Generate a new word. If `unique_attempts` is given, up to that many words are generated while
+/// looking for one that isn't in `taken` (which must be lowercase). If every attempt is taken, the
+/// word space may be exhausted, so the same number of two-morpheme words is tried before giving up
+/// and returning a homonym.
+pub fn generate_word(
+    synthesis_tab: &synthesis::SynthesisTab,
+    taken: &HashSet<String>,
+    unique_attempts: Option<usize>,
+) -> String {
+    let generate = || {
+        synthesis::synthesize_morpheme(synthesis_tab, synthesis::WordClass::Content)
+        // todo distinguish content and function weights
+    };
+    let Some(attempts) = unique_attempts else {
+        return generate();
+    };
+    let is_free = |word: &String| !taken.contains(&word.to_lowercase());
+    if let Some(word) = std::iter::repeat_with(generate)
+        .take(attempts)
+        .find(is_free)
+    {
+        return word;
+    }
+    let compound = || generate() + &generate();
+    std::iter::repeat_with(compound)
+        .take(attempts)
+        .find(is_free)
+        .unwrap_or_else(generate)
+}
+
 impl LexiconEditWindow {
     /// Create an instance of the edit window for modifying an existing entry.
     pub fn edit_entry(curr_native_phrase: &str, lexicon: &Lexicon) -> LexiconEditWindow {
         LexiconEditWindow {
             original_native_phrase: Some(curr_native_phrase.to_owned()),
             native_phrase: curr_native_phrase.to_owned(),
-            conlang_phrase: lexicon.get(curr_native_phrase).unwrap().to_owned(),
+            conlang_phrase: lexicon.get(curr_native_phrase).unwrap().word.clone(),
+            word_type: lexicon.get(curr_native_phrase).unwrap().word_type.clone(),
+            tags: lexicon.get(curr_native_phrase).unwrap().tags.join(", "),
             overwrite_warning: None,
+            focus_native: false,
         }
     }
 
@@ -147,13 +816,32 @@ impl LexiconEditWindow {
             original_native_phrase: None,
             native_phrase: String::new(),
             conlang_phrase: String::new(),
+            word_type: None,
+            tags: String::new(),
             overwrite_warning: None,
+            focus_native: false,
+        }
+    }
+
+    /// Create an instance of the edit window for adding a new entry with the given conlang word.
+    /// The native phrase field is focused, so the user can type the word's meaning right away.
+    pub fn new_entry_for(conlang_phrase: impl Into<String>) -> LexiconEditWindow {
+        LexiconEditWindow {
+            conlang_phrase: conlang_phrase.into(),
+            focus_native: true,
+            ..Self::new_entry()
         }
     }
 
     /// Render the lexicon entry edit window.
     /// Return true if the window should be closed, or false otherwise.
-    pub fn show(&mut self, ui: &mut egui::Ui, conlang_name: &str, lexicon: &mut Lexicon) -> bool {
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        conlang_name: &str,
+        native_name: &str,
+        lexicon: &mut Lexicon,
+    ) -> bool {
         let mut not_manual_close = true; // negative semantics required to pass to Window::open()
         let mut auto_close = false;
         egui::Window::new("Edit Lexicon")
@@ -165,7 +853,10 @@ impl LexiconEditWindow {
                 egui::Grid::new("edit lexicon")
                     .min_row_height(25.0)
                     .min_col_width(100.0)
-                    .show(ui, self.draw_edit_fields(conlang_name, lexicon));
+                    .show(
+                        ui,
+                        self.draw_edit_fields(conlang_name, native_name, lexicon),
+                    );
                 ui.separator();
                 ui.horizontal(|ui| match &self.original_native_phrase {
                     Some(original) => {
@@ -175,7 +866,7 @@ impl LexiconEditWindow {
                             lexicon,
                             original,
                             &self.native_phrase,
-                            &self.conlang_phrase,
+                            self.entry(),
                             self.can_edit_lexicon(),
                         );
                     }
@@ -184,7 +875,7 @@ impl LexiconEditWindow {
                             ui,
                             lexicon,
                             &self.native_phrase,
-                            &self.conlang_phrase,
+                            self.entry(),
                             self.can_edit_lexicon(),
                         );
                     }
@@ -197,6 +888,7 @@ impl LexiconEditWindow {
     fn draw_edit_fields<'a>(
         &'a mut self,
         conlang_name: &'a str,
+        native_name: &'a str,
         lexicon: &'a mut Lexicon,
     ) -> impl FnOnce(&mut egui::Ui) + 'a {
         move |ui| {
@@ -207,15 +899,40 @@ impl LexiconEditWindow {
             ui.end_row();
 
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                ui.label("English:");
+                ui.label(format!("{}:", native_name));
             });
             let native_input = ui.text_edit_singleline(&mut self.native_phrase);
+            if self.focus_native {
+                native_input.request_focus();
+                self.focus_native = false;
+            }
+            ui.end_row();
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label("Word Type:");
+            });
+            let selected = self.word_type.as_ref().map_or("(unknown)", |ty| ty.name());
+            egui::ComboBox::from_id_source("lexicon word type")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.word_type, None, "(unknown)");
+                    for choice in grammar::WordType::iter() {
+                        let name = choice.name();
+                        ui.selectable_value(&mut self.word_type, Some(choice), name);
+                    }
+                });
+            ui.end_row();
+
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label("Tags:");
+            });
+            ui.add(egui::TextEdit::singleline(&mut self.tags).hint_text("e.g. kinship, color"));
             ui.end_row();
 
             if native_input.changed() {
                 self.overwrite_warning = lexicon
                     .get(&self.native_phrase)
-                    .map(|curr_word| format!("Already mapped to <{}>", curr_word));
+                    .map(|curr_entry| format!("Already mapped to <{}>", curr_entry.word));
                 if self.overwrite_warning.is_none() {
                     ui.memory_mut(|mem| mem.close_popup());
                 }
@@ -231,12 +948,49 @@ impl LexiconEditWindow {
         }
     }
 
+    /// Return true if the edit window holds changes that haven't been committed to the lexicon.
+    pub fn has_unsaved_changes(&self, lexicon: &Lexicon) -> bool {
+        match &self.original_native_phrase {
+            Some(original) => lexicon.get(original).map_or(true, |entry| {
+                self.native_phrase != *original
+                    || self.conlang_phrase != entry.word
+                    || self.word_type != entry.word_type
+                    || parse_tags(&self.tags) != entry.tags
+            }),
+            None => {
+                !self.native_phrase.is_empty()
+                    || !self.conlang_phrase.is_empty()
+                    || self.word_type.is_some()
+                    || !self.tags.trim().is_empty()
+            }
+        }
+    }
+
+    /// Build a manual lexicon entry from the contents of the edit window.
+    fn entry(&self) -> LexiconEntry {
+        LexiconEntry {
+            word_type: self.word_type.clone(),
+            tags: parse_tags(&self.tags),
+            ..LexiconEntry::manual(self.conlang_phrase.clone())
+        }
+    }
+
     /// Return whether the contents of the edit window can be safely committed to the lexicon.
     fn can_edit_lexicon(&self) -> bool {
         self.overwrite_warning.is_none() && !self.native_phrase.is_empty()
     }
 }
 
+/// Split comma-separated tags, dropping blank and repeated ones.
+fn parse_tags(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .unique()
+        .map(str::to_owned)
+        .collect()
+}
+
 /// Draw a button that deletes the active lexicon entry.
 fn draw_delete_btn(ui: &mut egui::Ui, lexicon: &mut Lexicon, orig_native_phrase: &str) -> bool {
     let clicked = ui.button("Delete Entry").clicked();
@@ -252,13 +1006,13 @@ fn draw_apply_btn(
     lexicon: &mut Lexicon,
     orig_native_phrase: &str,
     native_phrase: &str,
-    conlang_phrase: &str,
+    entry: LexiconEntry,
     can_edit: bool,
 ) -> bool {
     let button = egui::Button::new("Apply Changes");
     let clicked = ui.add_enabled(can_edit, button).clicked();
     if clicked {
-        lexicon.insert(native_phrase.to_string(), conlang_phrase.to_string());
+        lexicon.insert(native_phrase.to_string(), entry);
         if orig_native_phrase != native_phrase {
             lexicon.remove(orig_native_phrase);
         }
@@ -271,13 +1025,13 @@ fn draw_new_btn(
     ui: &mut egui::Ui,
     lexicon: &mut Lexicon,
     native_phrase: &str,
-    conlang_phrase: &str,
+    entry: LexiconEntry,
     can_edit: bool,
 ) -> bool {
     let button = egui::Button::new("Add Entry");
     let clicked = ui.add_enabled(can_edit, button).clicked();
     if clicked {
-        lexicon.insert(native_phrase.to_string(), conlang_phrase.to_string());
+        lexicon.insert(native_phrase.to_string(), entry);
     }
     clicked
 }
@@ -1,14 +1,24 @@
 use eframe::egui;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Debug, Display, Formatter};
+use std::path::{Path, PathBuf};
 
+mod audio;
+mod evolution;
+mod export;
 mod grammar;
 mod grapheme;
 mod lexicon;
+mod palette;
+mod project;
+mod swadesh;
 mod synthesis;
 mod translate;
 mod util;
 
+/// The width of the language list before the user resizes it.
+const DEFAULT_SIDE_PANEL_WIDTH: f32 = 120.0;
+
 fn main() -> eframe::Result<()> {
     eframe::run_native(
         "Language Alchemist",
@@ -18,14 +28,17 @@ fn main() -> eframe::Result<()> {
 }
 
 /// A constructed language.
-#[derive(Default, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Language {
     name: String,
+    #[serde(default = "default_native_name")]
+    native_name: String, // the language being translated from
     translate_tab: translate::TranslateTab,
     lexicon_tab: lexicon::LexiconTab,
     synthesis_tab: synthesis::SynthesisTab,
     grammar_tab: grammar::GrammarTab,
+    evolution_tab: evolution::EvolutionTab,
 }
 
 impl Language {
@@ -33,9 +46,30 @@ impl Language {
     fn new() -> Self {
         Self {
             name: "New Language".to_owned(),
+            native_name: default_native_name(),
             ..Default::default()
         }
     }
+
+    /// Create a language with a random but usable phonology, named with one of its own words.
+    fn random() -> Self {
+        let synthesis_tab = synthesis::random_config(&mut rand::thread_rng());
+        let word = synthesis::synthesize_morpheme(&synthesis_tab, synthesis::WordClass::Content);
+        let mut chars = word.chars();
+        let name = match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => "Random Language".to_owned(),
+        };
+        Self {
+            name,
+            synthesis_tab,
+            ..Self::new()
+        }
+    }
+}
+
+fn default_native_name() -> String {
+    "English".to_owned()
 }
 
 /// An instance of the application. Maintains the list of the languages as well as UI data.
@@ -43,12 +77,44 @@ impl Language {
 struct Application {
     curr_lang_idx: Option<usize>,
     languages: Vec<Language>,
+    #[serde(default)]
+    confirm_deletions: bool,
+    #[serde(default)]
+    theme: Theme,
+    #[serde(default)]
+    side_panel_width: Option<f32>, // None until the panel is first drawn
+    #[serde(default)]
+    autosave_path: Option<PathBuf>, // a file that the app state is also saved to
+    #[serde(skip)]
+    autosave_error: Option<String>,
+    #[serde(skip)]
+    restore_path: Option<PathBuf>, // an autosave file the user is being offered to restore from
+    #[serde(default)]
+    project_path: Option<PathBuf>, // the file the languages were last opened from or saved to
+    #[serde(default)]
+    unsaved_changes: bool, // whether the project had unsaved changes when the app last saved
+    #[serde(skip)]
+    saved_project: Option<String>, // the project file contents when last opened or saved
+    #[serde(skip)]
+    pending_action: Option<project::ProjectAction>, // waiting on the unsaved changes prompt
+    #[serde(skip)]
+    project_error: Option<String>,
     #[serde(skip)]
     curr_tab: Tab,
     #[serde(skip)]
     editing_name: bool,
     #[serde(skip)]
-    lexicon_edit_win: Option<lexicon::LexiconEditWindow>,
+    renaming_lang_idx: Option<usize>,
+    #[serde(skip)]
+    deleting_lang_idx: Option<usize>,
+    #[serde(default)]
+    lexicon_edit_win: Option<lexicon::LexiconEditWindow>, // kept so autosaves include unsaved edits
+    #[serde(skip)]
+    switching_lang: Option<LanguageSwitch>, // waiting on the discard lexicon edit prompt
+    #[serde(skip)]
+    palette: palette::CharacterPalette,
+    #[serde(skip)]
+    show_issues: bool, // whether the configuration problems window is open
 }
 
 impl Application {
@@ -57,16 +123,309 @@ impl Application {
             let mut loaded_app: Self =
                 eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
             for language in &mut loaded_app.languages {
-                grammar::load_grammar_serde_metadata(&mut language.grammar_tab.grammar_rules);
+                grammar::load_grammar_serde_metadata(&mut language.grammar_tab);
+            }
+            loaded_app.restore_path = loaded_app
+                .autosave_path
+                .clone()
+                .filter(|path| path.exists());
+            if loaded_app.project_path.is_some() && !loaded_app.unsaved_changes {
+                loaded_app.saved_project = project::project_json(&mut loaded_app.languages).ok();
             }
             loaded_app
         } else {
             Default::default()
         }
     }
+
+    /// Replace the languages with the ones saved in an autosave file.
+    fn restore_from(&mut self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let mut restored: Self = serde_json::from_str(&text).map_err(|err| err.to_string())?;
+        for language in &mut restored.languages {
+            grammar::load_grammar_serde_metadata(&mut language.grammar_tab);
+        }
+        self.replace_languages(restored.languages);
+        self.curr_lang_idx = restored.curr_lang_idx;
+        self.lexicon_edit_win = restored.lexicon_edit_win;
+        Ok(())
+    }
+
+    /// Replace every language, selecting the first one and resetting any UI state that refers to
+    /// the old languages.
+    fn replace_languages(&mut self, languages: Vec<Language>) {
+        self.curr_lang_idx = (!languages.is_empty()).then_some(0);
+        self.languages = languages;
+        self.renaming_lang_idx = None;
+        self.deleting_lang_idx = None;
+        self.lexicon_edit_win = None;
+        self.switching_lang = None;
+    }
+
+    /// Return true if the languages have changed since the project was last opened or saved.
+    fn has_unsaved_changes(&mut self) -> bool {
+        match &self.saved_project {
+            Some(saved) => project::project_json(&mut self.languages).ok().as_ref() != Some(saved),
+            None => !self.languages.is_empty(),
+        }
+    }
+
+    /// Perform an action from the "File" menu. Actions that would discard unsaved changes wait for
+    /// the user to answer the unsaved changes prompt first.
+    fn request_project_action(&mut self, action: project::ProjectAction) {
+        if action.replaces_project() && self.has_unsaved_changes() {
+            self.pending_action = Some(action);
+        } else {
+            self.run_project_action(action);
+        }
+    }
+
+    /// Perform an action from the "File" menu immediately.
+    fn run_project_action(&mut self, action: project::ProjectAction) {
+        match action {
+            project::ProjectAction::New => {
+                self.replace_languages(Vec::new());
+                self.project_path = None;
+                self.saved_project = None;
+                self.project_error = None;
+            }
+            project::ProjectAction::Open => {
+                let Some(path) = project::choose_project_to_open() else {
+                    return;
+                };
+                match project::read_project(&path) {
+                    Ok(opened) => {
+                        self.replace_languages(opened.languages);
+                        self.saved_project = project::project_json(&mut self.languages).ok();
+                        self.project_path = Some(path);
+                        self.project_error = None;
+                    }
+                    Err(err) => self.project_error = Some(format!("Couldn't open project: {err}")),
+                }
+            }
+            project::ProjectAction::Save => {
+                self.save_project(false);
+            }
+            project::ProjectAction::SaveAs => {
+                self.save_project(true);
+            }
+            project::ProjectAction::Restore => {
+                let Some(path) = project::choose_autosave_to_restore() else {
+                    return;
+                };
+                match self.restore_from(&path) {
+                    Ok(()) => self.project_error = None,
+                    Err(err) => self.project_error = Some(format!("Couldn't restore: {err}")),
+                }
+            }
+        }
+    }
+
+    /// Save the languages to the project file, asking the user where to save them if there is no
+    /// project file yet or `choose_path` is true. Return true if the project was saved.
+    fn save_project(&mut self, choose_path: bool) -> bool {
+        let path = match &self.project_path {
+            Some(path) if !choose_path => path.clone(),
+            _ => match project::choose_project_to_save() {
+                Some(path) => path,
+                None => return false,
+            },
+        };
+        let saved = project::project_json(&mut self.languages).and_then(|json| {
+            std::fs::write(&path, &json)
+                .map(|_| json)
+                .map_err(|err| err.to_string())
+        });
+        match saved {
+            Ok(json) => {
+                self.saved_project = Some(json);
+                self.project_path = Some(path);
+                self.project_error = None;
+                true
+            }
+            Err(err) => {
+                self.project_error = Some(format!("Couldn't save project: {err}"));
+                false
+            }
+        }
+    }
+
+    /// Draw the prompt asking whether to save unsaved changes before replacing the project.
+    fn draw_unsaved_changes_prompt(&mut self, ctx: &egui::Context) {
+        let Some(action) = self.pending_action else {
+            return;
+        };
+        egui::Window::new("Unsaved Changes")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("The current project has unsaved changes. Save them first?");
+                ui.horizontal(|ui| {
+                    if ui.button("Save").clicked() {
+                        if self.save_project(false) {
+                            self.run_project_action(action);
+                        }
+                        self.pending_action = None;
+                    }
+                    if ui.button("Don't Save").clicked() {
+                        self.run_project_action(action);
+                        self.pending_action = None;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.pending_action = None;
+                    }
+                });
+            });
+    }
+
+    /// Draw a prompt asking whether to discard the unsaved lexicon edit before switching to another
+    /// language.
+    fn draw_switch_language_prompt(&mut self, ctx: &egui::Context) {
+        if self.switching_lang.is_none() {
+            return;
+        }
+        egui::Window::new("Unsaved Lexicon Edit")
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("The lexicon entry being edited has unsaved changes. Discard them?");
+                ui.horizontal(|ui| {
+                    if ui.button("Discard").clicked() {
+                        if let Some(switch) = self.switching_lang.take() {
+                            let other_idxs =
+                                [&mut self.renaming_lang_idx, &mut self.deleting_lang_idx];
+                            self.curr_lang_idx =
+                                Some(switch.apply(&mut self.languages, other_idxs));
+                            self.lexicon_edit_win = None;
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.switching_lang = None;
+                    }
+                });
+            });
+    }
 }
 
-/// One of the four UI tabs at the top of the window.
+/// Draw the menu bar at the top of the window. Return the "File" menu action that was clicked, if
+/// any.
+fn draw_menu_bar(
+    ui: &mut egui::Ui,
+    project_path: Option<&Path>,
+    project_error: Option<&str>,
+    theme: &mut Theme,
+) -> Option<project::ProjectAction> {
+    egui::menu::bar(ui, |ui| {
+        let action = ui
+            .menu_button("File", |ui| {
+                let mut clicked = None;
+                for action in project::ProjectAction::iter() {
+                    if ui.button(action.name()).clicked() {
+                        clicked = Some(action);
+                        ui.close_menu();
+                    }
+                }
+                clicked
+            })
+            .inner
+            .flatten();
+        ui.menu_button("View", |ui| {
+            for choice in Theme::iter() {
+                if ui.selectable_value(theme, choice, choice.name()).clicked() {
+                    ui.close_menu();
+                }
+            }
+        });
+        ui.separator();
+        match project_path {
+            Some(path) => {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                ui.label(file_name.into_owned())
+                    .on_hover_text(path.display().to_string());
+            }
+            None => {
+                ui.weak("Unsaved Project");
+            }
+        }
+        if let Some(error) = project_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+        action
+    })
+    .inner
+}
+
+/// Write the app state to an autosave file as JSON.
+fn write_autosave(path: &Path, app: &Application) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(app).map_err(|err| err.to_string())?;
+    std::fs::write(path, json).map_err(|err| err.to_string())
+}
+
+/// A change of the selected language, which waits on the discard lexicon edit prompt if the entry
+/// being edited has unsaved changes.
+enum LanguageSwitch {
+    Select(usize),                // an existing language
+    Insert(Box<Language>, usize), // a new language, added at the index
+}
+
+impl LanguageSwitch {
+    /// Add the new language, if there is one, and return the index of the language to select. The
+    /// indices of the languages being renamed and deleted are shifted past a new language, so they
+    /// still point to the same languages.
+    fn apply(self, languages: &mut Vec<Language>, other_idxs: [&mut Option<usize>; 2]) -> usize {
+        match self {
+            Self::Select(idx) => idx,
+            Self::Insert(language, idx) => {
+                languages.insert(idx, *language);
+                for other in other_idxs.into_iter().flatten() {
+                    if *other >= idx {
+                        *other += 1;
+                    }
+                }
+                idx
+            }
+        }
+    }
+}
+
+/// Switch to another language, closing the lexicon edit window since it belongs to the current
+/// language's lexicon. If the entry being edited has unsaved changes, the switch is put in
+/// `switching_lang` instead, to wait for the user to discard them. Return true if the language was
+/// switched right away. `other_idxs` are the languages being renamed and deleted.
+fn switch_language(
+    switch: LanguageSwitch,
+    languages: &mut Vec<Language>,
+    curr_lang_idx: &mut Option<usize>,
+    other_idxs: [&mut Option<usize>; 2],
+    lexicon_edit_win: &mut Option<lexicon::LexiconEditWindow>,
+    switching_lang: &mut Option<LanguageSwitch>,
+) -> bool {
+    let unsaved_edit = match (lexicon_edit_win.as_ref(), *curr_lang_idx) {
+        (Some(win), Some(idx)) => win.has_unsaved_changes(&languages[idx].lexicon_tab.lexicon),
+        _ => false,
+    };
+    if unsaved_edit {
+        *switching_lang = Some(switch);
+        false
+    } else {
+        *curr_lang_idx = Some(switch.apply(languages, other_idxs));
+        *lexicon_edit_win = None;
+        true
+    }
+}
+
+/// Remove the language at `idx`. The current language index is updated so that it still points
+/// to the same language, or to a neighbor if the current language was removed.
+fn delete_language(languages: &mut Vec<Language>, curr_lang_idx: &mut Option<usize>, idx: usize) {
+    languages.remove(idx);
+    *curr_lang_idx = match *curr_lang_idx {
+        _ if languages.is_empty() => None,
+        Some(curr) if curr > idx || curr == languages.len() => Some(curr - 1),
+        other => other,
+    };
+}
+
+/// One of the UI tabs at the top of the window.
 #[derive(Clone, Debug, Default, PartialEq)]
 enum Tab {
     #[default]
@@ -74,6 +433,124 @@ enum Tab {
     Lexicon,
     Synthesis,
     Grammar,
+    Evolution,
+}
+
+impl Tab {
+    fn iter() -> impl Iterator<Item = Self> {
+        [
+            Self::Translate,
+            Self::Lexicon,
+            Self::Synthesis,
+            Self::Grammar,
+            Self::Evolution,
+        ]
+        .into_iter()
+    }
+
+    /// Describe the configuration errors shown in this tab for a language.
+    fn config_errors(&self, lang: &Language) -> Vec<String> {
+        match self {
+            Self::Synthesis => synthesis::config_errors(&lang.synthesis_tab),
+            Self::Grammar => grammar::config_errors(&lang.grammar_tab),
+            Self::Translate | Self::Lexicon | Self::Evolution => Vec::new(),
+        }
+    }
+
+    /// The key that switches to this tab when pressed along with Ctrl (or Cmd on Mac).
+    fn shortcut_key(&self) -> egui::Key {
+        match self {
+            Self::Translate => egui::Key::Num1,
+            Self::Lexicon => egui::Key::Num2,
+            Self::Synthesis => egui::Key::Num3,
+            Self::Grammar => egui::Key::Num4,
+            Self::Evolution => egui::Key::Num5,
+        }
+    }
+}
+
+/// The color scheme of the app.
+#[derive(Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Dark, Self::Light].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Dark => "🌙 Dark",
+            Self::Light => "☀ Light",
+        }
+    }
+
+    fn visuals(&self) -> egui::Visuals {
+        match self {
+            Self::Dark => egui::Visuals::dark(),
+            Self::Light => egui::Visuals::light(),
+        }
+    }
+}
+
+/// Draw a button for each theme, in place of egui's own buttons, so the choice is saved.
+fn draw_theme_buttons(ui: &mut egui::Ui, theme: &mut Theme) {
+    for choice in Theme::iter() {
+        ui.selectable_value(theme, choice, choice.name());
+    }
+}
+
+/// A problem with a language's configuration, and the tab where it can be fixed.
+struct ValidationIssue {
+    tab: Tab,
+    message: String,
+}
+
+/// Find every problem with a language's configuration, in tab order.
+fn validation_issues(lang: &Language) -> Vec<ValidationIssue> {
+    Tab::iter()
+        .flat_map(|tab| {
+            tab.config_errors(lang)
+                .into_iter()
+                .map(move |message| ValidationIssue {
+                    tab: tab.clone(),
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// Draw a window listing a language's configuration problems. Clicking one switches to its tab.
+fn draw_validation_report(
+    ctx: &egui::Context,
+    issues: &[ValidationIssue],
+    open: &mut bool,
+    curr_tab: &mut Tab,
+) {
+    egui::Window::new("Configuration Problems")
+        .open(open)
+        .default_width(300.0)
+        .show(ctx, |ui| {
+            if issues.is_empty() {
+                ui.label("No problems found.");
+            }
+            for issue in issues {
+                ui.horizontal_wrapped(|ui| {
+                    ui.weak(format!("{}:", issue.tab));
+                    if ui
+                        .link(&issue.message)
+                        .on_hover_text(format!("Go to the {} tab", issue.tab))
+                        .clicked()
+                    {
+                        *curr_tab = issue.tab.clone();
+                    }
+                });
+            }
+        });
 }
 
 // implement to_string() so we don't have to repeat the tab names
@@ -83,29 +560,169 @@ impl Display for Tab {
     }
 }
 
+/// Lay out text on a single line, cut off with an ellipsis if it's too wide for the UI.
+fn elided_text(ui: &mut egui::Ui, text: &str) -> egui::text::LayoutJob {
+    ui.style_mut().wrap = Some(true); // otherwise the text isn't limited to the available width
+    let format = egui::TextFormat {
+        font_id: egui::TextStyle::Button.resolve(ui.style()),
+        color: egui::Color32::PLACEHOLDER, // let the widget choose the color
+        ..Default::default()
+    };
+    let mut job = egui::text::LayoutJob::single_section(text.to_owned(), format);
+    job.wrap = egui::text::TextWrapping {
+        max_rows: 1,
+        break_anywhere: true,
+        overflow_character: Some('…'),
+        ..Default::default()
+    };
+    job
+}
+
+/// Draw the controls for choosing a file that the app state is autosaved to.
+fn draw_autosave_settings(
+    ui: &mut egui::Ui,
+    autosave_path: &mut Option<PathBuf>,
+    autosave_error: &mut Option<String>,
+) {
+    ui.vertical_centered(|ui| {
+        match autosave_path {
+            Some(path) => {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                ui.label(format!("Autosaving to {file_name}"))
+                    .on_hover_text(path.display().to_string());
+                if ui.button("Stop Autosaving").clicked() {
+                    *autosave_path = None;
+                    *autosave_error = None;
+                }
+            }
+            None => {
+                let autosave_btn = ui.button("Autosave to File").on_hover_text(
+                    "Also save your languages to a file of your choice every 30 seconds",
+                );
+                if autosave_btn.clicked() {
+                    let path = rfd::FileDialog::new()
+                        .set_file_name("languages.json")
+                        .add_filter("JSON", &["json"])
+                        .save_file();
+                    if path.is_some() {
+                        *autosave_path = path;
+                    }
+                }
+            }
+        }
+        if let Some(error) = autosave_error {
+            ui.colored_label(egui::Color32::RED, format!("Couldn't autosave: {error}"));
+        }
+    });
+}
+
 impl eframe::App for Application {
     /// Called on exit to save any state not marked with `#[serde(skip)]`.
     /// Also automatically called every 30 seconds (as defined by `epi:App::auto_save_interval`).
+    /// If an autosave file was chosen, the state is written there too.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         for language in &mut self.languages {
-            grammar::save_grammar_serde_metadata(&mut language.grammar_tab.grammar_rules);
+            grammar::save_grammar_serde_metadata(&mut language.grammar_tab);
         }
+        self.unsaved_changes = self.has_unsaved_changes();
         eframe::set_value(storage, eframe::APP_KEY, self);
+        if let Some(path) = &self.autosave_path {
+            self.autosave_error = write_autosave(path, self).err();
+        }
+    }
+
+    /// Called before each frame to edit the input, so the character palette can type text.
+    fn raw_input_hook(&mut self, _ctx: &egui::Context, raw_input: &mut egui::RawInput) {
+        self.palette.feed_input(raw_input);
     }
 
     /// Called each frame to render the UI.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // offer to restore from the autosave file on startup
+        if let Some(path) = self.restore_path.clone() {
+            egui::Window::new("Restore Autosave")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "Restore your languages from the autosave file \"{}\"? Any changes not \
+                        in that file will be lost.",
+                        path.display()
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Restore").clicked() {
+                            self.autosave_error = self.restore_from(&path).err();
+                            self.restore_path = None;
+                        }
+                        if ui.button("Ignore").clicked() {
+                            self.restore_path = None;
+                        }
+                    });
+                });
+        }
+
+        // apply the saved theme, which egui doesn't persist on its own
+        if ctx.style().visuals.dark_mode != (self.theme == Theme::Dark) {
+            ctx.set_visuals(self.theme.visuals());
+        }
+
+        // draw menu bar
+        let action = egui::TopBottomPanel::top("menu bar")
+            .show(ctx, |ui| {
+                draw_menu_bar(
+                    ui,
+                    self.project_path.as_deref(),
+                    self.project_error.as_deref(),
+                    &mut self.theme,
+                )
+            })
+            .inner;
+        if let Some(action) = action {
+            self.request_project_action(action);
+        }
+        self.draw_unsaved_changes_prompt(ctx);
+        self.draw_switch_language_prompt(ctx);
+
         let Self {
             languages,
             curr_lang_idx,
+            confirm_deletions,
+            theme,
+            side_panel_width,
             curr_tab,
             editing_name,
+            renaming_lang_idx,
+            deleting_lang_idx,
             lexicon_edit_win,
+            switching_lang,
+            palette,
+            show_issues,
+            autosave_path,
+            autosave_error,
+            restore_path: _,
+            project_path: _,
+            unsaved_changes: _,
+            saved_project: _,
+            pending_action: _,
+            project_error: _,
         } = self;
 
+        util::set_confirm_deletions(ctx, *confirm_deletions);
+
+        // switch tabs with keyboard shortcuts
+        if curr_lang_idx.is_some() {
+            for tab in Tab::iter() {
+                let key = tab.shortcut_key();
+                if ctx.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, key)) {
+                    *curr_tab = tab;
+                }
+            }
+        }
+
         // draw left panel
-        egui::SidePanel::left("language list")
-            .default_width(120.0)
+        let side_panel = egui::SidePanel::left("language list")
+            .resizable(true)
+            .default_width(side_panel_width.unwrap_or(DEFAULT_SIDE_PANEL_WIDTH))
             .show(ctx, |ui| {
                 ui.vertical_centered(|ui| {
                     ui.add_space(4.0); // align with tab list to our right
@@ -115,9 +732,91 @@ impl eframe::App for Application {
 
                 // draw language list
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    if let Some(curr_lang_idx) = curr_lang_idx {
-                        for (idx, lang) in languages.iter().enumerate() {
-                            ui.selectable_value(curr_lang_idx, idx, &lang.name);
+                    if let Some(curr_idx) = *curr_lang_idx {
+                        let mut moved_lang = None;
+                        let mut clicked_lang = None;
+                        for (idx, lang) in languages.iter_mut().enumerate() {
+                            if *renaming_lang_idx == Some(idx) {
+                                let response = ui.text_edit_singleline(&mut lang.name);
+                                response.request_focus();
+                                if response.lost_focus()
+                                    || response.clicked_elsewhere()
+                                    || ui.ctx().input(|i| i.key_pressed(egui::Key::Enter))
+                                {
+                                    *renaming_lang_idx = None;
+                                }
+                            } else {
+                                let lang_id = egui::Id::new(format!("language {idx}"));
+                                let mode = util::EditMode::Edit; // always allow dragging
+                                util::draw_reorderable(
+                                    mode,
+                                    ui,
+                                    lang_id,
+                                    idx,
+                                    &mut moved_lang,
+                                    |ui| {
+                                        let name = elided_text(ui, &lang.name);
+                                        let response = ui
+                                            .selectable_label(curr_idx == idx, name)
+                                            .on_hover_text(&lang.name)
+                                            .context_menu(|ui| {
+                                                if ui.button("Rename").clicked() {
+                                                    *renaming_lang_idx = Some(idx);
+                                                    ui.close_menu();
+                                                }
+                                                if ui.button("Delete").clicked() {
+                                                    *deleting_lang_idx = Some(idx);
+                                                    ui.close_menu();
+                                                }
+                                            });
+                                        if response.clicked() && curr_idx != idx {
+                                            clicked_lang = Some(idx);
+                                        }
+                                        (response.clone(), response)
+                                    },
+                                );
+                            }
+                        }
+
+                        // draw a drop zone for dragging a language to the end of the list
+                        let response = ui.allocate_rect(
+                            egui::Rect::from_min_size(
+                                ui.cursor().left_top(),
+                                egui::Vec2::new(ui.available_width(), 10.0),
+                            ),
+                            egui::Sense::hover(),
+                        );
+                        util::draw_reorder_drop_area(
+                            ui,
+                            languages.len(),
+                            &mut moved_lang,
+                            &response,
+                        );
+
+                        if let Some(idx) = clicked_lang {
+                            let switch = LanguageSwitch::Select(idx);
+                            switch_language(
+                                switch,
+                                languages,
+                                curr_lang_idx,
+                                [&mut *renaming_lang_idx, &mut *deleting_lang_idx],
+                                lexicon_edit_win,
+                                switching_lang,
+                            );
+                        }
+
+                        // if a language was dragged and released, move it now and keep the same
+                        // languages selected, renamed, and up for deletion
+                        if let Some(reordering) = moved_lang {
+                            reordering.apply(languages);
+                            for lang_idx in [
+                                &mut *curr_lang_idx,
+                                &mut *renaming_lang_idx,
+                                &mut *deleting_lang_idx,
+                            ] {
+                                *lang_idx = lang_idx.map(|idx| reordering.remap_index(idx));
+                            }
+                            *switching_lang = None;
                         }
                     } else {
                         ui.vertical_centered(|ui| {
@@ -131,53 +830,193 @@ impl eframe::App for Application {
 
                 // draw 'new language' button
                 ui.vertical_centered(|ui| {
+                    let mut new_lang = None;
                     if ui.button("New Language").clicked() {
-                        languages.push(Language::new());
-                        *curr_lang_idx = Some(languages.len() - 1);
-                        *curr_tab = Tab::Translate;
+                        new_lang = Some(Language::new());
+                    }
+                    let random_btn = ui.button("Random Language").on_hover_text(
+                        "Create a language with randomly chosen sounds and syllables",
+                    );
+                    if random_btn.clicked() {
+                        new_lang = Some(Language::random());
+                    }
+                    if let Some(language) = new_lang {
+                        let switch = LanguageSwitch::Insert(Box::new(language), languages.len());
+                        if switch_language(
+                            switch,
+                            languages,
+                            curr_lang_idx,
+                            [&mut *renaming_lang_idx, &mut *deleting_lang_idx],
+                            lexicon_edit_win,
+                            switching_lang,
+                        ) {
+                            *curr_tab = Tab::Translate;
+                        }
+                    }
+                    let duplicate_btn = ui
+                        .add_enabled(curr_lang_idx.is_some(), egui::Button::new("Duplicate"))
+                        .on_hover_text("Create a copy of the selected language");
+                    if let (true, Some(idx)) = (duplicate_btn.clicked(), *curr_lang_idx) {
+                        let mut copy = languages[idx].clone();
+                        copy.name.push_str(" (copy)");
+                        let switch = LanguageSwitch::Insert(Box::new(copy), idx + 1);
+                        switch_language(
+                            switch,
+                            languages,
+                            curr_lang_idx,
+                            [&mut *renaming_lang_idx, &mut *deleting_lang_idx],
+                            lexicon_edit_win,
+                            switching_lang,
+                        );
                     }
                 });
+
+                // draw autosave file controls
+                ui.separator();
+                draw_autosave_settings(ui, autosave_path, autosave_error);
             });
+        *side_panel_width = Some(side_panel.response.rect.width());
+
+        // draw deletion confirmation popup
+        if let Some(idx) = *deleting_lang_idx {
+            egui::Window::new("Delete Language")
+                .collapsible(false)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.label(format!(
+                        "Delete \"{}\"? This can't be undone.",
+                        languages[idx].name
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Delete").clicked() {
+                            // the edit window only belongs to the deleted language if it was the
+                            // current one
+                            if *curr_lang_idx == Some(idx) {
+                                *lexicon_edit_win = None;
+                            }
+                            delete_language(languages, curr_lang_idx, idx);
+                            *deleting_lang_idx = None;
+                            *renaming_lang_idx = None;
+                            *switching_lang = None;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            *deleting_lang_idx = None;
+                        }
+                    });
+                });
+        }
 
         // draw main panel
+        let mut new_daughter = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             let curr_lang = curr_lang_idx.map(|idx| &mut languages[idx]);
             if let Some(curr_lang) = curr_lang {
                 // draw top tabs
+                let issues = validation_issues(curr_lang);
                 ui.horizontal(|ui| {
-                    for tab in [Tab::Translate, Tab::Lexicon, Tab::Synthesis, Tab::Grammar] {
-                        ui.selectable_value(curr_tab, tab.clone(), tab.to_string());
+                    for tab in Tab::iter() {
+                        let shortcut = egui::KeyboardShortcut::new(
+                            egui::Modifiers::COMMAND,
+                            tab.shortcut_key(),
+                        );
+                        let errors = issues.iter().filter(|issue| issue.tab == tab).count();
+                        let (text, hover) = if errors > 0 {
+                            let text = egui::RichText::new(format!("{tab} ⚠ {errors}"))
+                                .color(ui.visuals().error_fg_color);
+                            let plural = if errors == 1 { "" } else { "s" };
+                            let hover = format!(
+                                "{}\n{errors} configuration error{plural}",
+                                ctx.format_shortcut(&shortcut)
+                            );
+                            (text, hover)
+                        } else {
+                            let text = egui::RichText::new(tab.to_string());
+                            (text, ctx.format_shortcut(&shortcut))
+                        };
+                        ui.selectable_value(curr_tab, tab.clone(), text)
+                            .on_hover_text(hover);
                         ui.separator();
                     }
 
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        egui::global_dark_light_mode_buttons(ui);
+                        draw_theme_buttons(ui, theme);
+                        ui.separator();
+                        ui.toggle_value(show_issues, format!("Problems ({})", issues.len()))
+                            .on_hover_text("List every problem with this language's configuration");
+                        ui.separator();
+                        ui.toggle_value(&mut palette.open, "Characters")
+                            .on_hover_text("Show buttons for typing special characters");
+                        ui.separator();
+                        ui.checkbox(confirm_deletions, "Confirm Deletions")
+                            .on_hover_text("Ask before deleting anything in Delete Mode");
                     });
                 });
 
+                draw_validation_report(ctx, &issues, show_issues, curr_tab);
+
                 ui.separator();
                 ui.add_space(5.0);
 
                 // draw contents of active tab
+                let mut added_word = None;
                 match curr_tab {
                     Tab::Translate => translate::draw_translate_tab(ui, curr_lang, editing_name),
                     Tab::Lexicon => lexicon::draw_lexicon_tab(
                         ui,
                         &mut curr_lang.lexicon_tab,
                         &curr_lang.name,
+                        &curr_lang.native_name,
+                        &curr_lang.synthesis_tab,
                         lexicon_edit_win,
                     ),
                     Tab::Synthesis => {
-                        synthesis::draw_synthesis_tab(ui, &mut curr_lang.synthesis_tab)
+                        added_word = synthesis::draw_synthesis_tab(
+                            ui,
+                            &mut curr_lang.synthesis_tab,
+                            &curr_lang.lexicon_tab.lexicon,
+                        );
+                    }
+                    Tab::Grammar => grammar::draw_grammar_tab(
+                        ui,
+                        &mut curr_lang.grammar_tab,
+                        &curr_lang.lexicon_tab.lexicon,
+                    ),
+                    Tab::Evolution => {
+                        new_daughter = evolution::draw_evolution_tab(ui, curr_lang);
                     }
-                    Tab::Grammar => grammar::draw_grammar_tab(ui, &mut curr_lang.grammar_tab),
+                }
+
+                // open a sample word from the synthesis tab in the lexicon, for the user to gloss
+                if let Some(word) = added_word {
+                    *lexicon_edit_win = Some(lexicon::LexiconEditWindow::new_entry_for(word));
+                    *curr_tab = Tab::Lexicon;
                 }
             } else {
                 ui.add_space(10.0);
                 ui.label("Select a language on the left, or create a new one.");
-                egui::global_dark_light_mode_buttons(ui);
+                ui.horizontal(|ui| draw_theme_buttons(ui, theme));
                 egui::warn_if_debug_build(ui);
             }
         });
+
+        // draw character palette
+        palette.track_focus(ctx);
+        if palette.open {
+            let inventory = curr_lang_idx.map(|idx| &languages[idx].synthesis_tab.graphemes);
+            palette.show(ctx, inventory);
+        }
+
+        // select a daughter language created in the evolution tab, placing it after its parent
+        if let (Some(daughter), Some(idx)) = (new_daughter, *curr_lang_idx) {
+            let switch = LanguageSwitch::Insert(Box::new(daughter), idx + 1);
+            switch_language(
+                switch,
+                languages,
+                curr_lang_idx,
+                [renaming_lang_idx, deleting_lang_idx],
+                lexicon_edit_win,
+                switching_lang,
+            );
+        }
     }
 }
@@ -0,0 +1,86 @@
+use eframe::egui;
+
+use crate::grapheme;
+
+/// Symbols that are hard to type on most keyboards but common in conlang orthographies.
+const COMMON_SYMBOLS: [&str; 40] = [
+    "ə", "ɛ", "ɔ", "æ", "ɑ", "ɪ", "ʊ", "ʌ", "ɨ", "ʉ", "ø", "œ", "ɯ", "ɤ", "ŋ", "ɲ", "ʃ", "ʒ", "θ",
+    "ð", "χ", "ʁ", "ħ", "ʕ", "ʔ", "ɬ", "ɮ", "ɾ", "ʎ", "ɣ", "β", "ɸ", "ç", "ʝ", "ː", "ˈ", "ˌ", "ʰ",
+    "ʷ", "ʲ",
+];
+
+/// A floating window of clickable characters that are typed into the last focused text field.
+#[derive(Default)]
+pub struct CharacterPalette {
+    pub open: bool,
+    last_text_field: Option<egui::Id>,
+    pending_text: Option<String>, // typed into the text field at the start of the next frame
+}
+
+impl CharacterPalette {
+    /// Remember the focused widget if it's a text field, so that clicking a palette button (which
+    /// takes focus away from the field) still types into it.
+    pub fn track_focus(&mut self, ctx: &egui::Context) {
+        if let Some(id) = ctx.memory(|mem| mem.focused()) {
+            if egui::TextEdit::load_state(ctx, id).is_some() {
+                self.last_text_field = Some(id);
+            }
+        }
+    }
+
+    /// Type any character clicked on the last frame into the text field, at its cursor. The field
+    /// was given focus again when the character was clicked, so it receives the text as if it
+    /// were typed on the keyboard.
+    pub fn feed_input(&mut self, raw_input: &mut egui::RawInput) {
+        if let Some(text) = self.pending_text.take() {
+            raw_input.events.push(egui::Event::Text(text));
+        }
+    }
+
+    /// Draw the palette window, with the current language's graphemes followed by common symbols.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        inventory: Option<&grapheme::MasterGraphemeStorage>,
+    ) {
+        let mut clicked = None;
+        egui::Window::new("Character Palette")
+            .open(&mut self.open)
+            .default_width(240.0)
+            .show(ctx, |ui| {
+                if let Some(inventory) = inventory {
+                    ui.strong("Graphemes");
+                    let graphemes = inventory.iter().map(grapheme::Grapheme::as_str);
+                    draw_symbol_buttons(ui, graphemes, &mut clicked);
+                    ui.separator();
+                }
+                ui.strong("Common Symbols");
+                draw_symbol_buttons(ui, COMMON_SYMBOLS.into_iter(), &mut clicked);
+                if self.last_text_field.is_none() {
+                    ui.weak("Click a text field first to choose where characters go.");
+                }
+            });
+
+        if let (Some(text), Some(id)) = (clicked, self.last_text_field) {
+            ctx.memory_mut(|mem| mem.request_focus(id));
+            self.pending_text = Some(text);
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Draw a button for each symbol, storing the symbol in `clicked` if its button is clicked.
+fn draw_symbol_buttons<'a>(
+    ui: &mut egui::Ui,
+    symbols: impl Iterator<Item = &'a str>,
+    clicked: &mut Option<String>,
+) {
+    ui.horizontal_wrapped(|ui| {
+        for symbol in symbols {
+            let button = egui::Button::new(egui::RichText::new(symbol).monospace());
+            if ui.add(button).clicked() {
+                *clicked = Some(symbol.to_owned());
+            }
+        }
+    });
+}
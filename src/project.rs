@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{grammar, Language};
+
+/// The contents of a project file: a set of languages, without any of the app's UI state.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    pub languages: Vec<Language>,
+}
+
+/// A borrowed version of `Project`, so a project can be saved without copying its languages.
+#[derive(Serialize)]
+struct ProjectRef<'a> {
+    languages: &'a [Language],
+}
+
+/// An action from the "File" menu.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ProjectAction {
+    New,
+    Open,
+    Save,
+    SaveAs,
+    Restore,
+}
+
+impl ProjectAction {
+    pub fn iter() -> impl Iterator<Item = Self> {
+        [
+            Self::New,
+            Self::Open,
+            Self::Save,
+            Self::SaveAs,
+            Self::Restore,
+        ]
+        .into_iter()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::New => "New Project",
+            Self::Open => "Open Project...",
+            Self::Save => "Save Project",
+            Self::SaveAs => "Save Project As...",
+            Self::Restore => "Restore from File...",
+        }
+    }
+
+    /// Return true if this action replaces the current project, so unsaved changes would be lost.
+    pub fn replaces_project(&self) -> bool {
+        matches!(self, Self::New | Self::Open | Self::Restore)
+    }
+}
+
+/// Read a project from a file.
+pub fn read_project(path: &Path) -> Result<Project, String> {
+    let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let mut project: Project = serde_json::from_str(&text).map_err(|err| err.to_string())?;
+    for language in &mut project.languages {
+        grammar::load_grammar_serde_metadata(&mut language.grammar_tab);
+    }
+    Ok(project)
+}
+
+/// Serialize a set of languages as the contents of a project file.
+pub fn project_json(languages: &mut [Language]) -> Result<String, String> {
+    for language in languages.iter_mut() {
+        grammar::save_grammar_serde_metadata(&mut language.grammar_tab);
+    }
+    serde_json::to_string_pretty(&ProjectRef { languages }).map_err(|err| err.to_string())
+}
+
+/// Ask the user for a project file to open.
+pub fn choose_project_to_open() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("Language Project", &["json"])
+        .pick_file()
+}
+
+/// Ask the user where to save a project file.
+pub fn choose_project_to_save() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_file_name("project.json")
+        .add_filter("Language Project", &["json"])
+        .save_file()
+}
+
+/// Ask the user for an autosave file to restore languages from. This works even when the app has
+/// forgotten which autosave file it was using, such as after a reinstall.
+pub fn choose_autosave_to_restore() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("JSON", &["json"])
+        .pick_file()
+}
@@ -0,0 +1,106 @@
+use crate::grammar::WordType::{self, Determiner, Noun, NounModifier, Pronoun, Verb, VerbModifier};
+
+/// Morris Swadesh's list of 100 basic concepts, which nearly every language has a word for, along
+/// with the word type of each.
+pub const SWADESH_LIST: [(&str, WordType); 100] = [
+    ("i", Pronoun),
+    ("you", Pronoun),
+    ("we", Pronoun),
+    ("this", Determiner),
+    ("that", Determiner),
+    ("who", Pronoun),
+    ("what", Pronoun),
+    ("not", VerbModifier),
+    ("all", Determiner),
+    ("many", Determiner),
+    ("one", Determiner),
+    ("two", Determiner),
+    ("big", NounModifier),
+    ("long", NounModifier),
+    ("small", NounModifier),
+    ("woman", Noun),
+    ("man", Noun),
+    ("person", Noun),
+    ("fish", Noun),
+    ("bird", Noun),
+    ("dog", Noun),
+    ("louse", Noun),
+    ("tree", Noun),
+    ("seed", Noun),
+    ("leaf", Noun),
+    ("root", Noun),
+    ("bark", Noun),
+    ("skin", Noun),
+    ("flesh", Noun),
+    ("blood", Noun),
+    ("bone", Noun),
+    ("grease", Noun),
+    ("egg", Noun),
+    ("horn", Noun),
+    ("tail", Noun),
+    ("feather", Noun),
+    ("hair", Noun),
+    ("head", Noun),
+    ("ear", Noun),
+    ("eye", Noun),
+    ("nose", Noun),
+    ("mouth", Noun),
+    ("tooth", Noun),
+    ("tongue", Noun),
+    ("claw", Noun),
+    ("foot", Noun),
+    ("knee", Noun),
+    ("hand", Noun),
+    ("belly", Noun),
+    ("neck", Noun),
+    ("breast", Noun),
+    ("heart", Noun),
+    ("liver", Noun),
+    ("drink", Verb),
+    ("eat", Verb),
+    ("bite", Verb),
+    ("see", Verb),
+    ("hear", Verb),
+    ("know", Verb),
+    ("sleep", Verb),
+    ("die", Verb),
+    ("kill", Verb),
+    ("swim", Verb),
+    ("fly", Verb),
+    ("walk", Verb),
+    ("come", Verb),
+    ("lie", Verb),
+    ("sit", Verb),
+    ("stand", Verb),
+    ("give", Verb),
+    ("say", Verb),
+    ("sun", Noun),
+    ("moon", Noun),
+    ("star", Noun),
+    ("water", Noun),
+    ("rain", Noun),
+    ("stone", Noun),
+    ("sand", Noun),
+    ("earth", Noun),
+    ("cloud", Noun),
+    ("smoke", Noun),
+    ("fire", Noun),
+    ("ash", Noun),
+    ("burn", Verb),
+    ("path", Noun),
+    ("mountain", Noun),
+    ("red", NounModifier),
+    ("green", NounModifier),
+    ("yellow", NounModifier),
+    ("white", NounModifier),
+    ("black", NounModifier),
+    ("night", Noun),
+    ("hot", NounModifier),
+    ("cold", NounModifier),
+    ("full", NounModifier),
+    ("new", NounModifier),
+    ("good", NounModifier),
+    ("round", NounModifier),
+    ("dry", NounModifier),
+    ("name", Noun),
+];
@@ -1,31 +1,280 @@
+use crate::audio;
 use crate::grapheme;
+use crate::lexicon::Lexicon;
 use crate::util::{self, EditMode, NonEmptyList};
 use eframe::egui;
 use itertools::{EitherOrBoth, Itertools};
 use rand::{distributions::WeightedIndex, prelude::*};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
 
-#[derive(Default, Deserialize, Serialize)]
+/// The maximum depth of nested variables that will be expanded during synthesis.
+const MAX_VAR_DEPTH: usize = 64;
+
+/// The number of times a word is generated while looking for one that follows the word
+/// constraints and length limit. If none do, the last one is used anyway.
+const MAX_CONSTRAINT_ATTEMPTS: usize = 100;
+
+/// The symbol for the edge of a word in constraint and allophony patterns.
+const WORD_BOUNDARY: &str = "#";
+
+/// The maximum word length in graphemes when a limit is first turned on.
+const DEFAULT_MAX_GRAPHEMES: u8 = 8;
+
+/// The character inserted before a stressed syllable.
+const STRESS_MARK: char = 'ˈ';
+
+/// The syllable positions that can be weighted when stress is placed randomly, in the same order
+/// as `SynthesisTab::stress_weights`.
+const STRESS_WEIGHT_NAMES: [&str; 4] = ["Initial", "Penultimate", "Final", "Other"];
+
+/// The tooltip shown for variables that can never finish expanding.
+const RECURSIVE_VAR_ERROR: &str = "Recursive without a base case";
+
+/// The symbol that stands for a blank node in the text form of the syllable grammar.
+const BLANK_SYMBOL: &str = "ε";
+
+/// The default percent chance that a new Linker node inserts its graphemes.
+const DEFAULT_LINKER_CHANCE: u8 = 50;
+
+/// The default percent chance that a node is included when it is first made optional.
+const DEFAULT_OPTIONAL_CHANCE: u8 = 50;
+
+/// The prefix of the token that makes the preceding node optional in the text form of the
+/// syllable grammar, as in `{p t k} ?40%`.
+const OPTIONAL_SYMBOL: char = '?';
+
+/// The text form of a node that hasn't been set up yet, such as a new node or a variable node with
+/// no variable chosen.
+const UNSET_SYMBOL: &str = "?";
+
+/// How long to highlight a variable's rule after navigating to it.
+const NAVIGATION_HIGHLIGHT_SECS: f64 = 1.0;
+
+/// The number of words generated when computing sample statistics.
+const STATS_SAMPLE_SIZE: usize = 1000;
+
+/// The number of rows shown in each sample statistics histogram.
+const STATS_MAX_ROWS: usize = 8;
+
+/// Letters treated as vowels when classifying graphemes for sample statistics.
+const VOWEL_LETTERS: &str = "aeiouyáàâäãåéèêëíìîïóòôöõúùûüýæøœɑɒɐəɛɜɪɨɔʊʉʌɯɤ";
+
+/// The grapheme categories that are always offered, even before any grapheme is assigned to them.
+const DEFAULT_CATEGORIES: [&str; 2] = ["Vowel", "Consonant"];
+
+/// The colors given to grapheme categories when coloring words, starting with the default
+/// categories and cycling if there are more categories than colors.
+const CATEGORY_COLORS: [egui::Color32; 6] = [
+    egui::Color32::from_rgb(0xe0, 0x6c, 0x75),
+    egui::Color32::from_rgb(0x61, 0xaf, 0xef),
+    egui::Color32::from_rgb(0x98, 0xc3, 0x79),
+    egui::Color32::from_rgb(0xd1, 0x9a, 0x66),
+    egui::Color32::from_rgb(0xc6, 0x78, 0xdd),
+    egui::Color32::from_rgb(0x56, 0xb6, 0xc2),
+];
+
+/// Common graphemic inventories that can be merged into a language's inventory, in
+/// (name, graphemes) format.
+const INVENTORY_PRESETS: [(&str, &[&str]); 3] = [
+    (
+        "Basic (5 Vowels)",
+        &[
+            "a", "e", "i", "o", "u", "p", "t", "k", "b", "d", "g", "m", "n", "f", "s", "h", "l",
+            "r", "w", "y",
+        ],
+    ),
+    (
+        "Polynesian",
+        &[
+            "a", "e", "i", "o", "u", "p", "t", "k", "m", "n", "ng", "h", "w", "l", "ʻ",
+        ],
+    ),
+    (
+        "Extended (IPA)",
+        &[
+            "a", "e", "i", "o", "u", "ə", "ɛ", "ɔ", "p", "t", "k", "b", "d", "g", "m", "n", "ŋ",
+            "f", "v", "s", "z", "ʃ", "ʒ", "θ", "h", "l", "r", "w", "j",
+        ],
+    ),
+];
+
+/// Vowels for randomly generated languages. The first three are always included.
+const RANDOM_VOWELS: [&str; 10] = ["a", "i", "u", "e", "o", "ə", "ɛ", "ɔ", "y", "ɪ"];
+
+/// Consonants for randomly generated languages, roughly from most to least common across natural
+/// languages. The first six are always included.
+const RANDOM_CONSONANTS: [&str; 22] = [
+    "t", "k", "m", "n", "p", "s", "l", "j", "w", "h", "b", "d", "g", "r", "f", "ŋ", "v", "z", "ʃ",
+    "ts", "x", "ʔ",
+];
+
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct SynthesisTab {
     pub graphemes: grapheme::MasterGraphemeStorage,
+    #[serde(default)]
+    grapheme_categories: BTreeMap<String, String>, // grapheme -> category
+    #[serde(default)]
+    pronunciations: BTreeMap<String, String>, // grapheme -> IPA, if it differs from the grapheme
+    #[serde(default)]
+    comma_separators: bool, // let commas separate graphemes typed into the inventory
     pub syllable_vars: SyllableVars,
     pub max_syllables: (u8, u8), // (function words, content words)
     pub syllable_wgts: (Vec<u16>, Vec<u16>), // (function words, content words)
+    #[serde(default)]
+    max_graphemes: (Option<u8>, Option<u8>), // (function words, content words)
+    #[serde(default)]
+    stress_position: StressPosition,
+    #[serde(default)]
+    stress_mark: StressMark,
+    #[serde(default)]
+    stress_weights: [u16; 4], // (initial, penultimate, final, other), all zero for equal chances
+    #[serde(default)]
+    orphans_are_errors: bool,
+    #[serde(default)]
+    word_constraints: Vec<WordConstraint>,
+    #[serde(default)]
+    allophony: Vec<AllophonyRule>,
+    #[serde(default)]
+    reduplication: Reduplication,
+    #[serde(skip)]
+    test_words: Vec<Morpheme>,
+    #[serde(skip)]
+    show_syllables: bool,
+    #[serde(skip)]
+    color_graphemes: bool,
     #[serde(skip)]
-    test_words: Vec<String>,
+    show_ipa: bool,
+    #[serde(skip)]
+    sample_stats: Option<SampleStats>,
+    #[serde(skip)]
+    frequency_sort: (FrequencyColumn, bool), // (column, ascending)
+    #[serde(skip)]
+    pair_set: usize,
+    #[serde(skip)]
+    minimal_pairs: Vec<(String, String)>,
     #[serde(skip)]
     new_grapheme: String,
     #[serde(skip)]
+    new_multigraph: String, // input buffer for the multigraph field when multigraphs are grouped
+    #[serde(skip)]
+    group_multigraphs: bool,
+    #[serde(skip)]
+    new_category: String,
+    #[serde(skip)]
+    usage_grapheme: Option<String>,
+    #[serde(skip)]
+    removed_graphemes: Vec<String>, // removed from the inventory, but still used by syllable rules
+    #[serde(skip)]
+    space_syllables: u8, // the word length to count possible words for
+    #[serde(skip)]
     syllable_edit_mode: EditMode,
+    #[serde(skip)]
+    navigation: Option<VarNavigation>,
+    #[serde(skip)]
+    rules_import: Option<String>, // text being imported, if the import box is open
+    #[serde(skip)]
+    rules_import_error: Option<String>,
+}
+
+impl SynthesisTab {
+    /// Return the relative probabilities of each word length, in syllables, for a class of word.
+    fn syllable_weights(&self, class: WordClass) -> &[u16] {
+        match class {
+            WordClass::Function => &self.syllable_wgts.0,
+            WordClass::Content => &self.syllable_wgts.1,
+        }
+    }
+
+    /// Return the most graphemes a word of the given class may have, if there is a limit.
+    fn max_graphemes(&self, class: WordClass) -> Option<u8> {
+        match class {
+            WordClass::Function => self.max_graphemes.0,
+            WordClass::Content => self.max_graphemes.1,
+        }
+    }
+
+    /// Return the graphemes in the master inventory that belong to a category.
+    pub fn category_members<'a>(
+        &'a self,
+        category: &'a str,
+    ) -> impl Iterator<Item = &'a grapheme::Grapheme> {
+        self.graphemes.iter().filter(move |grapheme| {
+            self.grapheme_categories
+                .get(grapheme.as_str())
+                .map(String::as_str)
+                == Some(category)
+        })
+    }
+
+    /// Return the names of every category with at least one grapheme in the master inventory.
+    fn category_names(&self) -> BTreeSet<String> {
+        self.graphemes
+            .iter()
+            .filter_map(|grapheme| self.grapheme_categories.get(grapheme.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A class of words with its own word length settings. Function words (conjunctions, determiners,
+/// etc.) are often shorter than content words.
+#[derive(Clone, Copy)]
+pub enum WordClass {
+    Function,
+    Content,
+}
+
+/// Data updated by certain nodes as the syllable rules are drawn.
+#[derive(Default)]
+struct RuleVisit {
+    order: usize,                // incremented for each leaf node visited
+    new_var: Option<String>,     // set if a new variable is referenced
+    clicked_var: Option<String>, // set if a variable reference is clicked in view mode
+}
+
+/// A request to scroll to a variable's rule and highlight it.
+#[derive(Clone)]
+struct VarNavigation {
+    var: String,
+    scrolled: bool,
+    highlight_until: f64, // in seconds, on the egui clock
+}
+
+/// Names that variable and category nodes can refer to.
+struct DefinedNames {
+    vars: HashMap<String, String>, // variable name -> its rule in text form
+    categories: BTreeSet<String>,
+}
+
+/// Statistics about a large batch of generated words. Each histogram is a list of
+/// (label, fraction of total) pairs.
+#[derive(Clone)]
+struct SampleStats {
+    initial_clusters: Vec<(String, f32)>,
+    syllable_shapes: Vec<(String, f32)>,
+    word_lengths: Vec<(String, f32)>,
+    grapheme_counts: Vec<(String, usize)>, // every grapheme in the inventory, even if unused
+    total_graphemes: usize,
+}
+
+/// The column that the grapheme frequency table is sorted by.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum FrequencyColumn {
+    Grapheme,
+    #[default]
+    Count,
 }
 
 /// A mapping of syllable rule variable names to their values.
-#[derive(Default, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct SyllableVars {
     roots: SyllableRoots,
     vars: BTreeMap<String, OrRule>,
     reachable: HashSet<String>,
+    #[serde(skip)]
+    recursive: HashSet<String>,
 }
 
 impl SyllableVars {
@@ -39,11 +288,55 @@ impl SyllableVars {
             _ => self.vars.get(var),
         }
     }
+
+    /// Return the rule associated with a variable name if it exists, or otherwise None.
+    fn get_mut(&mut self, var: &str) -> Option<&mut OrRule> {
+        match var {
+            "InitialSyllable" => Some(&mut self.roots.initial),
+            "MiddleSyllable" => Some(&mut self.roots.middle),
+            "TerminalSyllable" => Some(&mut self.roots.terminal),
+            "SingleSyllable" => Some(&mut self.roots.single),
+            _ => self.vars.get_mut(var),
+        }
+    }
+
+    /// Return an iterator over the names and rules of every variable, starting with the roots.
+    fn iter(&self) -> impl Iterator<Item = (&str, &OrRule)> {
+        SyllableRoots::names()
+            .zip(self.roots.iter())
+            .chain(self.vars.iter().map(|(var, rule)| (var.as_str(), rule)))
+    }
+
+    /// Return the graphemes used anywhere in the rules.
+    fn graphemes(&self) -> BTreeSet<grapheme::Grapheme> {
+        self.iter()
+            .flat_map(|(_, rule)| rule.iter().flat_map(NonEmptyList::iter))
+            .flat_map(LeafRule::graphemes)
+            .collect()
+    }
+
+    /// Remove every use of a grapheme from the rules of every variable.
+    fn remove_grapheme(&mut self, grapheme: &str) {
+        self.roots
+            .iter_mut()
+            .chain(self.vars.values_mut())
+            .flat_map(|rule| rule.iter_mut().flat_map(NonEmptyList::iter_mut))
+            .for_each(|leaf| leaf.remove_grapheme(grapheme));
+    }
+
+    /// Replace every use of a grapheme in the rules of every variable with another grapheme.
+    fn rename_grapheme(&mut self, old: &str, new: &grapheme::Grapheme) {
+        self.roots
+            .iter_mut()
+            .chain(self.vars.values_mut())
+            .flat_map(|rule| rule.iter_mut().flat_map(NonEmptyList::iter_mut))
+            .for_each(|leaf| leaf.rename_grapheme(old, new));
+    }
 }
 
 /// The four root rules of the syllable synthesis grammar. Rules are stored in
 /// sum-of-products form.
-#[derive(Default, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 struct SyllableRoots {
     initial: OrRule,
     middle: OrRule,
@@ -53,7 +346,7 @@ struct SyllableRoots {
 
 impl SyllableRoots {
     /// Return an iterator over the root rule names.
-    fn names() -> impl Iterator<Item = &'static str> {
+    fn names<'a>() -> impl Iterator<Item = &'a str> {
         [
             "InitialSyllable",
             "MiddleSyllable",
@@ -87,20 +380,32 @@ type AndRule = NonEmptyList<LeafRule>;
 type OrRule = NonEmptyList<AndRule>;
 
 /// A leaf node in the syllable synthesis grammar.
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Deserialize, Serialize)]
 enum LeafRule {
     Uninitialized,
-    Sequence(Vec<grapheme::Grapheme>, String),
+    Sequence(Vec<grapheme::SequenceElement>, String),
     Set(BTreeSet<grapheme::Grapheme>, String),
     Variable(String),
     Blank,
+    Category(String),
+    Linker(Vec<grapheme::Grapheme>, String, u8), // emitted with a percent chance, else nothing
+    Optional(Box<LeafRule>, u8), // the wrapped node is included with a percent chance
 }
 
 impl LeafRule {
     /// Return an iterator over a "menu" of leaf node types in a (name, constructor) format.
     fn choices() -> impl Iterator<Item = (&'static str, fn() -> Self)> {
-        let names = ["String", "Random", "Variable", "Blank"];
-        let funcs = [Self::sequence, Self::set, Self::variable, Self::blank];
+        let names = [
+            "String", "Random", "Category", "Variable", "Blank", "Linker",
+        ];
+        let funcs = [
+            Self::sequence,
+            Self::set,
+            Self::category,
+            Self::variable,
+            Self::blank,
+            Self::linker,
+        ];
         names.into_iter().zip(funcs)
     }
 
@@ -117,6 +422,76 @@ impl LeafRule {
         .response
     }
 
+    /// Return the graphemes used by this node, if it is a Sequence, Set, or Linker node. Each
+    /// alternative in a Sequence element counts separately.
+    fn graphemes(&self) -> Vec<grapheme::Grapheme> {
+        match self {
+            Self::Optional(inner, _) => inner.graphemes(),
+            Self::Linker(list, ..) => list.clone(),
+            Self::Sequence(list, _) => list
+                .iter()
+                .flat_map(|element| element.alternatives.iter().cloned())
+                .collect(),
+            Self::Set(set, _) => set.iter().cloned().collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Remove every use of a grapheme from this node, if it is a Sequence, Set, or Linker node.
+    /// Sequence elements lose the grapheme as an alternative, and are removed if it was the only one.
+    fn remove_grapheme(&mut self, grapheme: &str) {
+        match self {
+            Self::Sequence(list, _) => list.retain_mut(|element| {
+                element.alternatives.retain(|g| g.as_str() != grapheme);
+                !element.alternatives.is_empty()
+            }),
+            Self::Set(set, _) => set.retain(|g| g.as_str() != grapheme),
+            Self::Linker(list, ..) => list.retain(|g| g.as_str() != grapheme),
+            Self::Optional(inner, _) => inner.remove_grapheme(grapheme),
+            _ => {}
+        }
+    }
+
+    /// Replace every use of a grapheme in this node with another grapheme, if it is a Sequence, Set,
+    /// or Linker node.
+    fn rename_grapheme(&mut self, old: &str, new: &grapheme::Grapheme) {
+        let rename = |grapheme: &mut grapheme::Grapheme| {
+            if grapheme.as_str() == old {
+                *grapheme = new.clone();
+            }
+        };
+        match self {
+            Self::Sequence(list, _) => list
+                .iter_mut()
+                .flat_map(|element| element.alternatives.iter_mut())
+                .for_each(rename),
+            Self::Set(set, _) => {
+                if set.remove(&grapheme::Grapheme::new(old)) {
+                    set.insert(new.clone());
+                }
+            }
+            Self::Linker(list, ..) => list.iter_mut().for_each(rename),
+            Self::Optional(inner, _) => inner.rename_grapheme(old, new),
+            _ => {}
+        }
+    }
+
+    /// Return the node wrapped by this node if it is an Optional node, or else this node itself.
+    fn required(&self) -> &Self {
+        match self {
+            Self::Optional(inner, _) => inner.required(),
+            _ => self,
+        }
+    }
+
+    /// Wrap this node in an Optional node, or unwrap it if it already is one.
+    fn toggle_optional(&mut self) {
+        *self = match std::mem::take(self) {
+            Self::Optional(inner, _) => *inner,
+            rule => Self::Optional(Box::new(rule), DEFAULT_OPTIONAL_CHANCE),
+        };
+    }
+
     /// Return true if this node is not Self::Uninitialized, otherwise return false.
     fn initialized(&self) -> bool {
         !matches!(self, Self::Uninitialized)
@@ -132,6 +507,11 @@ impl LeafRule {
         Self::Set(BTreeSet::new(), String::new())
     }
 
+    /// Construct a default Category node.
+    fn category() -> Self {
+        Self::Category(String::new())
+    }
+
     /// Construct a default Variable node.
     fn variable() -> Self {
         Self::Variable(String::new())
@@ -141,6 +521,11 @@ impl LeafRule {
     fn blank() -> Self {
         Self::Blank
     }
+
+    /// Construct a default Linker node.
+    fn linker() -> Self {
+        Self::Linker(Vec::new(), String::new(), DEFAULT_LINKER_CHANCE)
+    }
 }
 
 impl Default for LeafRule {
@@ -149,25 +534,405 @@ impl Default for LeafRule {
     }
 }
 
-/// Render contents of the 'synthesis' tab.
-pub fn draw_synthesis_tab(ui: &mut egui::Ui, data: &mut SynthesisTab) {
-    egui::ScrollArea::vertical().show(ui, |ui| {
-        draw_test_generator(ui, data);
-        ui.add_space(10.0);
-        draw_graphemic_inventory(ui, data);
-        ui.add_space(10.0);
-        draw_syllable_rules(ui, data);
-        ui.add_space(10.0);
-        draw_syllable_counter(ui, data);
-    });
+/// The syllable of a multi-syllable word that receives stress.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, Serialize)]
+enum StressPosition {
+    #[default]
+    None,
+    Initial,
+    Penultimate,
+    Final,
+    Random,
+}
+
+impl StressPosition {
+    fn iter() -> impl Iterator<Item = Self> {
+        [
+            Self::None,
+            Self::Initial,
+            Self::Penultimate,
+            Self::Final,
+            Self::Random,
+        ]
+        .into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Initial => "Initial",
+            Self::Penultimate => "Penultimate",
+            Self::Final => "Final",
+            Self::Random => "Random",
+        }
+    }
+
+    /// Return the index of the stressed syllable in a word with the given number of syllables,
+    /// or None if no syllable should be stressed. Single-syllable words are never stressed.
+    /// Random stress picks a syllable using the given weights; see `SynthesisTab::stress_weights`.
+    fn choose(
+        &self,
+        num_syllables: usize,
+        weights: &[u16; 4],
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        if num_syllables < 2 {
+            return None;
+        }
+        match self {
+            Self::None => None,
+            Self::Initial => Some(0),
+            Self::Penultimate => Some(num_syllables - 2),
+            Self::Final => Some(num_syllables - 1),
+            Self::Random => {
+                let syllable_weights = (0..num_syllables).map(|i| {
+                    let [initial, penultimate, last, other] = *weights;
+                    match i {
+                        0 if num_syllables == 2 => initial + penultimate,
+                        0 => initial,
+                        _ if i == num_syllables - 1 => last,
+                        _ if i == num_syllables - 2 => penultimate,
+                        _ => other,
+                    }
+                });
+                match WeightedIndex::new(syllable_weights) {
+                    Ok(dist) => Some(dist.sample(rng)),
+                    Err(_) => Some(rng.gen_range(0..num_syllables)), // every weight is zero
+                }
+            }
+        }
+    }
+}
+
+/// The way a stressed syllable is written.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, Serialize)]
+enum StressMark {
+    #[default]
+    Accent,
+    Capitalize,
+}
+
+impl StressMark {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Accent, Self::Capitalize].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Accent => "Stress Mark (ˈ)",
+            Self::Capitalize => "Capital Letters",
+        }
+    }
+
+    /// Mark the given syllable as stressed.
+    fn apply(&self, syllable: &mut String) {
+        match self {
+            Self::Accent => syllable.insert(0, STRESS_MARK),
+            Self::Capitalize => *syllable = syllable.to_uppercase(),
+        }
+    }
+}
+
+/// A sequence of graphemes that generated words may not contain, such as a voiced stop at the end
+/// of a word. Unlike the syllable rules, constraints can span syllable boundaries.
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct WordConstraint {
+    position: ConstraintPosition,
+    pattern: String, // space-separated graphemes, category names, and word boundaries
 }
 
-fn draw_test_generator(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+impl WordConstraint {
+    /// Return true if a word, split into graphemes, contains this constraint's pattern where the
+    /// constraint forbids it. Patterns with no graphemes or categories are never violated.
+    fn is_violated_by(&self, segments: &[&str], data: &SynthesisTab) -> bool {
+        let mut pattern: Vec<&str> = self.pattern.split_whitespace().collect();
+        if pattern.iter().all(|&element| element == WORD_BOUNDARY) {
+            return false;
+        }
+
+        // starting or ending a word is the same as following or preceding a word boundary
+        let starts_at_boundary = pattern.first() == Some(&WORD_BOUNDARY);
+        let ends_at_boundary = pattern.last() == Some(&WORD_BOUNDARY);
+        match self.position {
+            ConstraintPosition::Start if !starts_at_boundary => pattern.insert(0, WORD_BOUNDARY),
+            ConstraintPosition::End if !ends_at_boundary => pattern.push(WORD_BOUNDARY),
+            _ => {}
+        }
+        let bounded = with_boundaries(segments.iter().copied());
+        bounded
+            .windows(pattern.len())
+            .any(|window| sequence_matches(data, &pattern, window))
+    }
+}
+
+/// Return true if an element of a pattern, which is either a grapheme or a category name, matches
+/// a grapheme. A word boundary only matches the boundary markers added by `with_boundaries`.
+fn element_matches(data: &SynthesisTab, element: &str, grapheme: &str) -> bool {
+    element == grapheme
+        || data.grapheme_categories.get(grapheme).map(String::as_str) == Some(element)
+}
+
+/// Return true if each element of a pattern matches the grapheme in the same position.
+fn sequence_matches(data: &SynthesisTab, pattern: &[&str], graphemes: &[&str]) -> bool {
+    pattern.len() == graphemes.len()
+        && pattern
+            .iter()
+            .zip(graphemes)
+            .all(|(element, grapheme)| element_matches(data, element, grapheme))
+}
+
+/// Surround a word's graphemes with word boundary markers, so patterns can match the edges of the
+/// word.
+fn with_boundaries<'a>(graphemes: impl Iterator<Item = &'a str>) -> Vec<&'a str> {
+    std::iter::once(WORD_BOUNDARY)
+        .chain(graphemes)
+        .chain(std::iter::once(WORD_BOUNDARY))
+        .collect()
+}
+
+/// A rule that replaces a grapheme with another in a certain context, such as /n/ becoming [ŋ]
+/// before /k/. The target and replacement are each a grapheme or a category name. Each context is
+/// a space-separated sequence of graphemes, category names, and word boundaries, and a blank
+/// context matches anything.
+#[derive(Clone, Default, Deserialize, Serialize)]
+struct AllophonyRule {
+    target: String,
+    replacement: String,
+    preceding: String, // "#" matches the start of the word
+    following: String, // "#" matches the end of the word
+}
+
+impl AllophonyRule {
+    /// Apply the rule everywhere it matches in a word split into graphemes. Contexts are checked
+    /// against the word as it was before the rule was applied.
+    fn apply(&self, segments: &mut [String], data: &SynthesisTab) {
+        let target = self.target.trim();
+        if target.is_empty() {
+            return;
+        }
+        let preceding: Vec<&str> = self.preceding.split_whitespace().collect();
+        let following: Vec<&str> = self.following.split_whitespace().collect();
+        let bounded = with_boundaries(segments.iter().map(String::as_str));
+
+        // the word's graphemes are at positions 1 to len in the bounded word
+        let matched: Vec<usize> = (1..=segments.len())
+            .filter(|&i| {
+                let before = &bounded[i.saturating_sub(preceding.len())..i];
+                let after = &bounded[i + 1..(i + 1 + following.len()).min(bounded.len())];
+                element_matches(data, target, bounded[i])
+                    && sequence_matches(data, &preceding, before)
+                    && sequence_matches(data, &following, after)
+            })
+            .map(|i| i - 1)
+            .collect();
+        for i in matched {
+            segments[i] = self.replacement.trim().to_owned();
+        }
+    }
+}
+
+/// Apply each allophony rule in order to a word split into graphemes.
+fn apply_allophony(data: &SynthesisTab, segments: &mut [String]) {
+    for rule in &data.allophony {
+        rule.apply(segments, data);
+    }
+}
+
+/// Apply the allophony rules to a word split into syllables, keeping each grapheme in its
+/// syllable. Rules can look across syllable boundaries.
+fn apply_allophony_to_syllables(data: &SynthesisTab, syllables: &mut [String]) {
+    if data.allophony.is_empty() {
+        return;
+    }
+    let mut segments = Vec::new();
+    let mut lengths = Vec::with_capacity(syllables.len());
+    for syllable in syllables.iter() {
+        let syllable_segments = grapheme::segment(syllable, &data.graphemes);
+        lengths.push(syllable_segments.len());
+        segments.extend(syllable_segments.into_iter().map(str::to_owned));
+    }
+    apply_allophony(data, &mut segments);
+    let mut segments = segments.into_iter();
+    for (syllable, length) in syllables.iter_mut().zip(lengths) {
+        *syllable = segments.by_ref().take(length).collect();
+    }
+}
+
+/// Where in a word a constraint's pattern is forbidden.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, Serialize)]
+enum ConstraintPosition {
+    Start,
+    End,
+    #[default]
+    Anywhere,
+}
+
+impl ConstraintPosition {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::Start, Self::End, Self::Anywhere].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Start => "Start With",
+            Self::End => "End With",
+            Self::Anywhere => "Contain",
+        }
+    }
+}
+
+/// A setting for sometimes forming words by repeating one of their syllables, as many languages do
+/// for intensives or plurals.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+struct Reduplication {
+    copied: ReduplicatedSyllable,
+    chance: u8, // percent of words that are reduplicated
+}
+
+impl Default for Reduplication {
+    fn default() -> Self {
+        Self {
+            copied: ReduplicatedSyllable::None,
+            chance: 25,
+        }
+    }
+}
+
+impl Reduplication {
+    /// Possibly copy a syllable of a word, placing the copy next to the original.
+    fn apply(&self, syllables: &mut Vec<String>, rng: &mut impl Rng) {
+        if syllables.is_empty() || !rng.gen_ratio(self.chance.min(100) as u32, 100) {
+            return;
+        }
+        match self.copied {
+            ReduplicatedSyllable::None => {}
+            ReduplicatedSyllable::Initial => syllables.insert(0, syllables[0].clone()),
+            ReduplicatedSyllable::Final => syllables.push(syllables[syllables.len() - 1].clone()),
+        }
+    }
+}
+
+/// The syllable copied when a word is reduplicated.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq, Serialize)]
+enum ReduplicatedSyllable {
+    #[default]
+    None,
+    Initial,
+    Final,
+}
+
+impl ReduplicatedSyllable {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::None, Self::Initial, Self::Final].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Initial => "Initial",
+            Self::Final => "Final",
+        }
+    }
+}
+
+/// A generated morpheme, split into its syllables.
+#[derive(Clone)]
+pub struct Morpheme {
+    pub syllables: Vec<String>,
+}
+
+impl Morpheme {
+    /// Return true if the morpheme contains no graphemes, otherwise false.
+    pub fn is_empty(&self) -> bool {
+        self.syllables.iter().all(String::is_empty)
+    }
+
+    /// Return the morpheme as a string, with the given separator between each syllable.
+    pub fn join(&self, separator: &str) -> String {
+        self.syllables.join(separator)
+    }
+}
+
+impl Display for Morpheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.syllables.concat())
+    }
+}
+
+/// A preset distribution of word lengths.
+#[derive(Clone, Copy)]
+enum LengthPreset {
+    MostlyMonosyllabic,
+    BellCurve,
+    Uniform,
+}
+
+impl LengthPreset {
+    fn iter() -> impl Iterator<Item = Self> {
+        [Self::MostlyMonosyllabic, Self::BellCurve, Self::Uniform].into_iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::MostlyMonosyllabic => "Mostly Monosyllabic",
+            Self::BellCurve => "Bell Curve Around 2",
+            Self::Uniform => "Uniform",
+        }
+    }
+
+    /// Return the relative probabilities of words with 1 to `max_syllables` syllables.
+    fn weights(&self, max_syllables: usize) -> Vec<f64> {
+        (1..=max_syllables)
+            .map(|syllables| {
+                let syllables = syllables as f64;
+                match self {
+                    Self::MostlyMonosyllabic => 0.25_f64.powf(syllables - 1.0),
+                    Self::BellCurve => (-(syllables - 2.0).powi(2) / 2.0).exp(),
+                    Self::Uniform => 1.0,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Render contents of the 'synthesis' tab. If the user chooses to add a sample word to the
+/// lexicon, return it.
+pub fn draw_synthesis_tab(
+    ui: &mut egui::Ui,
+    data: &mut SynthesisTab,
+    lexicon: &Lexicon,
+) -> Option<String> {
+    egui::ScrollArea::vertical()
+        .show(ui, |ui| {
+            let added_word = draw_test_generator(ui, data);
+            ui.add_space(10.0);
+            draw_minimal_pairs(ui, data);
+            ui.add_space(10.0);
+            draw_graphemic_inventory(ui, data, lexicon);
+            ui.add_space(10.0);
+            draw_syllable_rules(ui, data);
+            ui.add_space(10.0);
+            draw_word_constraints(ui, data);
+            ui.add_space(10.0);
+            draw_allophony_rules(ui, data);
+            ui.add_space(10.0);
+            draw_reduplication_settings(ui, data);
+            ui.add_space(10.0);
+            draw_syllable_counter(ui, data);
+            ui.add_space(10.0);
+            draw_stress_settings(ui, data);
+            added_word
+        })
+        .inner
+}
+
+fn draw_test_generator(ui: &mut egui::Ui, data: &mut SynthesisTab) -> Option<String> {
+    let mut added_word = None;
     ui.heading("Sample Generation");
     ui.label("Use the buttons below to generate sample words using the current configuration.");
     ui.add_space(5.0);
     ui.horizontal(|ui| {
-        let err_text = "The word length probabilities do not add up to 100%";
+        let err_text = "The word length weights are all zero";
         let function_wgts = &data.syllable_wgts.0;
         let content_wgts = &data.syllable_wgts.1;
         let function_btn = ui
@@ -183,66 +948,627 @@ fn draw_test_generator(ui: &mut egui::Ui, data: &mut SynthesisTab) {
             )
             .on_disabled_hover_text(err_text);
         if function_btn.clicked() || content_btn.clicked() {
-            let weights = if function_btn.clicked() {
-                function_wgts
+            let class = if function_btn.clicked() {
+                WordClass::Function
             } else {
-                content_wgts
+                WordClass::Content
             };
-            let producer = || synthesize_morpheme(&data.syllable_vars, weights);
+            let producer = || synthesize_morpheme_structured(data, class);
             data.test_words = std::iter::repeat_with(producer)
                 .take(24) // 3 columns of 8
-                .map(|word| {
-                    if !word.is_empty() {
-                        word
-                    } else {
-                        "(blank)".to_owned()
-                    }
-                })
                 .collect();
             ui.close_menu();
         }
+        ui.checkbox(&mut data.show_syllables, "Show Syllables");
+        ui.checkbox(&mut data.color_graphemes, "Color Graphemes")
+            .on_hover_text("Color each grapheme by its category");
+        ui.checkbox(&mut data.show_ipa, "Show IPA")
+            .on_hover_text("Show how each word is pronounced, based on the graphemes' IPA");
+        ui.separator();
+        let stats_btn = ui
+            .add_enabled(
+                verify_weights(content_wgts),
+                egui::Button::new("Statistics"),
+            )
+            .on_hover_text(format!(
+                "Generate {STATS_SAMPLE_SIZE} content words and show which patterns are most common"
+            ))
+            .on_disabled_hover_text(err_text);
+        if stats_btn.clicked() {
+            data.sample_stats = Some(compute_sample_stats(data, WordClass::Content));
+        }
     });
     if !data.test_words.is_empty() {
         ui.add_space(5.0);
         ui.group(|ui| {
             ui.columns(3, |columns| {
                 for (i, word) in data.test_words.iter().enumerate() {
-                    columns[i % 3].label(word);
+                    let text = if word.is_empty() {
+                        "(blank)".to_owned()
+                    } else if data.show_syllables {
+                        word.join("·")
+                    } else {
+                        word.to_string()
+                    };
+                    columns[i % 3].horizontal(|ui| {
+                        if !word.is_empty() && ui.small_button("▶").on_hover_text("Play").clicked()
+                        {
+                            let phonemes = pronounce(data, &word.to_string());
+                            audio::play_word(&phonemes.iter().map(String::as_str).collect_vec());
+                        }
+                        let add_btn = ui
+                            .add_enabled(!word.is_empty(), egui::Button::new("+").small())
+                            .on_hover_text("Add to the lexicon");
+                        if add_btn.clicked() {
+                            added_word = Some(word.to_string());
+                        }
+                        let label = if data.color_graphemes && !word.is_empty() {
+                            egui::Label::new(colored_graphemes(ui, data, &text))
+                        } else {
+                            egui::Label::new(text)
+                        };
+                        if word.is_empty() {
+                            ui.add(label);
+                            return;
+                        }
+                        let ipa = data.show_ipa.then(|| to_ipa(data, &word.to_string()));
+                        let response = ui
+                            .add(label.sense(egui::Sense::click()))
+                            .on_hover_text("Click to copy, or right-click for more options");
+                        if response.clicked() {
+                            ui.output_mut(|output| output.copied_text = word.to_string());
+                        }
+                        if let Some(ipa) = ipa {
+                            ui.weak(format!("/{ipa}/"));
+                        }
+                        response.context_menu(|ui| {
+                            if ui.button("Copy").clicked() {
+                                ui.output_mut(|output| output.copied_text = word.to_string());
+                                ui.close_menu();
+                            }
+                            if ui.button("Add to Lexicon...").clicked() {
+                                added_word = Some(word.to_string());
+                                ui.close_menu();
+                            }
+                        });
+                    });
                 }
             })
         });
     }
+    if let Some(stats) = &data.sample_stats {
+        ui.add_space(5.0);
+        ui.group(|ui| {
+            ui.columns(3, |columns| {
+                draw_histogram(&mut columns[0], "Initial Clusters", &stats.initial_clusters);
+                draw_histogram(&mut columns[1], "Syllable Shapes", &stats.syllable_shapes);
+                draw_histogram(&mut columns[2], "Length (Graphemes)", &stats.word_lengths);
+            });
+            ui.add_space(5.0);
+            egui::CollapsingHeader::new("Grapheme Frequencies")
+                .id_source("grapheme frequencies")
+                .show(ui, |ui| {
+                    draw_grapheme_frequencies(ui, stats, &mut data.frequency_sort)
+                });
+        });
+    }
+    added_word
 }
 
-fn draw_graphemic_inventory(ui: &mut egui::Ui, data: &mut SynthesisTab) {
-    ui.heading("Graphemic Inventory");
-    ui.label("The graphemic inventory is the set of recognized graphemes (unique letters or glyphs) in the \
-        language. It can also contain multigraphs, like the English <ch> and <sh>.");
-    ui.add_space(5.0);
-    ui.add(grapheme::GraphemeInputField::new(
-        &mut data.graphemes,
-        &mut data.new_grapheme,
-        "new grapheme",
-    ));
+/// Render a table of how often each grapheme appeared in the sample, which can be sorted by
+/// clicking its headers, and a button to copy the table.
+fn draw_grapheme_frequencies(
+    ui: &mut egui::Ui,
+    stats: &SampleStats,
+    sort: &mut (FrequencyColumn, bool),
+) {
+    let mut rows: Vec<&(String, usize)> = stats.grapheme_counts.iter().collect();
+    match sort.0 {
+        FrequencyColumn::Grapheme => rows.sort_by(|(a, _), (b, _)| a.cmp(b)),
+        FrequencyColumn::Count => rows.sort_by_key(|(_, count)| *count),
+    }
+    if !sort.1 {
+        rows.reverse();
+    }
+    let percent = |count: usize| 100.0 * count as f32 / stats.total_graphemes.max(1) as f32;
 
-    // show error if empty
-    if data.graphemes.is_empty() {
-        ui.add_space(5.0);
-        ui.colored_label(
-            egui::Color32::RED,
-            "The graphemic inventory must contain at least one grapheme",
-        );
+    if ui
+        .button("Copy as CSV")
+        .on_hover_text("Copy the table, to paste into a spreadsheet")
+        .clicked()
+    {
+        let mut csv = "Grapheme,Count,Percent\n".to_owned();
+        for (grapheme, count) in &rows {
+            csv += &format!("{},{},{:.2}\n", grapheme, count, percent(*count));
+        }
+        ui.output_mut(|output| output.copied_text = csv);
     }
+    egui::Grid::new("grapheme frequencies")
+        .striped(true)
+        .min_col_width(60.0)
+        .show(ui, |ui| {
+            for (column, name) in [
+                (FrequencyColumn::Grapheme, "Grapheme"),
+                (FrequencyColumn::Count, "Count"),
+            ] {
+                let arrow = match (sort.0 == column, sort.1) {
+                    (false, _) => "",
+                    (true, true) => " ⏶",
+                    (true, false) => " ⏷",
+                };
+                let header = ui.selectable_label(sort.0 == column, format!("{name}{arrow}"));
+                if header.on_hover_text("Sort by this column").clicked() {
+                    *sort = (column, sort.0 == column && !sort.1);
+                }
+            }
+            ui.strong("Percent");
+            ui.end_row();
+            for (grapheme, count) in rows {
+                ui.monospace(grapheme);
+                ui.label(count.to_string());
+                ui.label(format!("{:.1}%", percent(*count)));
+                ui.end_row();
+            }
+        });
 }
 
-fn draw_syllable_counter(ui: &mut egui::Ui, data: &mut SynthesisTab) {
-    ui.heading("Word Length");
-    ui.label(
+/// Render a list of (label, fraction) pairs as a column of bars.
+fn draw_histogram(ui: &mut egui::Ui, title: &str, rows: &[(String, f32)]) {
+    ui.strong(title);
+    for (label, fraction) in rows {
+        let text = format!("{}  {:.1}%", label, fraction * 100.0);
+        ui.add(egui::ProgressBar::new(*fraction).text(text));
+    }
+}
+
+fn draw_minimal_pairs(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    ui.heading("Minimal Pairs");
+    ui.label(
+        "Minimal pairs are words that differ by only one grapheme, and are useful for checking \
+        whether two sounds contrast well. Choose a random set from the syllable rules to generate \
+        content words that swap one of its graphemes for another.",
+    );
+    ui.add_space(5.0);
+    let sets = random_sets(data);
+    ui.horizontal(|ui| {
+        let selected_text = sets
+            .get(data.pair_set)
+            .map_or("(no sets)", |(label, _)| label.as_str());
+        egui::ComboBox::from_id_source("minimal pair set")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for (i, (label, _)) in sets.iter().enumerate() {
+                    ui.selectable_value(&mut data.pair_set, i, label);
+                }
+            });
+        let enabled = sets.get(data.pair_set).is_some() && verify_weights(&data.syllable_wgts.1);
+        let button = ui
+            .add_enabled(enabled, egui::Button::new("Generate Pairs"))
+            .on_disabled_hover_text(
+                "Requires a random set with at least two graphemes, and content word \
+                length weights that aren't all zero",
+            );
+        if button.clicked() {
+            let (_, members) = &sets[data.pair_set];
+            data.minimal_pairs = generate_minimal_pairs(data, members, 8);
+        }
+    });
+    if !data.minimal_pairs.is_empty() {
+        ui.add_space(5.0);
+        ui.group(|ui| {
+            egui::Grid::new("minimal pairs")
+                .min_col_width(60.0)
+                .show(ui, |ui| {
+                    for (first, second) in &data.minimal_pairs {
+                        ui.label(first);
+                        ui.label("~");
+                        ui.label(second);
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+}
+
+fn draw_graphemic_inventory(ui: &mut egui::Ui, data: &mut SynthesisTab, lexicon: &Lexicon) {
+    ui.heading("Graphemic Inventory");
+    ui.label("The graphemic inventory is the set of recognized graphemes (unique letters or glyphs) in the \
+        language. It can also contain multigraphs, like the English <ch> and <sh>.");
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        ui.menu_button("Load Preset", |ui| {
+            for (name, graphemes) in INVENTORY_PRESETS {
+                if ui.button(name).clicked() {
+                    // merge with the existing inventory, which ignores duplicates
+                    data.graphemes.extend(
+                        graphemes
+                            .iter()
+                            .map(|&grapheme| grapheme::Grapheme::new(grapheme)),
+                    );
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text("Add a common set of graphemes to the inventory");
+        ui.checkbox(&mut data.group_multigraphs, "Group Multigraphs")
+            .on_hover_text("Show graphemes made of several characters separately");
+        ui.checkbox(&mut data.comma_separators, "Comma Separators")
+            .on_hover_text("Let commas separate graphemes as well as whitespace");
+    });
+    ui.add_space(5.0);
+    let usage_grapheme = &mut data.usage_grapheme;
+    let categories = &mut data.grapheme_categories;
+    let new_category = &mut data.new_category;
+    let comma_separators = data.comma_separators;
+    let mut renamed = None;
+    let mut draw_field = |ui: &mut egui::Ui,
+                          graphemes: &mut grapheme::MasterGraphemeStorage,
+                          input: &mut String,
+                          id: &str| {
+        ui.add(
+            grapheme::GraphemeInputField::new(graphemes, input, id)
+                .comma_separated(comma_separators)
+                .on_edit(|old, new| renamed = Some((old.clone(), new.clone())))
+                .context_menu(|ui, grapheme| {
+                    if ui.button("Find in Lexicon").clicked() {
+                        *usage_grapheme = Some(grapheme.to_string());
+                        ui.close_menu();
+                    }
+                    ui.menu_button("Category", |ui| {
+                        draw_category_menu(ui, categories, new_category, grapheme.as_str());
+                    });
+                }),
+        );
+    };
+    let old_graphemes = data.graphemes.clone();
+    if data.group_multigraphs {
+        // split the inventory for display, then merge it back together with any changes
+        let (mut multigraphs, mut singles): (grapheme::MasterGraphemeStorage, _) = data
+            .graphemes
+            .iter()
+            .cloned()
+            .partition(grapheme::Grapheme::is_multigraph);
+        ui.label("Single characters:");
+        draw_field(ui, &mut singles, &mut data.new_grapheme, "new grapheme");
+        ui.label("Multigraphs:");
+        draw_field(
+            ui,
+            &mut multigraphs,
+            &mut data.new_multigraph,
+            "new multigraph",
+        );
+        // keep the alphabetical order of the graphemes that are left, with new ones at the end and
+        // edited ones in their old place
+        if let Some((old, new)) = &renamed {
+            data.graphemes.rename(old, new.clone());
+        }
+        data.graphemes
+            .retain(|grapheme| singles.contains(grapheme) || multigraphs.contains(grapheme));
+        data.graphemes
+            .extend(singles.into_iter().chain(multigraphs));
+    } else {
+        draw_field(
+            ui,
+            &mut data.graphemes,
+            &mut data.new_grapheme,
+            "new grapheme",
+        );
+    }
+    if let Some((old, new)) = renamed {
+        rename_grapheme(data, &old, &new);
+    }
+    note_removed_graphemes(data, &old_graphemes);
+    draw_removed_graphemes(ui, data);
+    egui::CollapsingHeader::new("Alphabetical Order")
+        .id_source("grapheme alphabetical order")
+        .show(ui, |ui| draw_alphabetical_order(ui, &mut data.graphemes));
+    draw_ambiguous_multigraphs(ui, data);
+    draw_category_members(ui, data);
+    egui::CollapsingHeader::new("Pronunciation (IPA)")
+        .id_source("grapheme pronunciations")
+        .show(ui, |ui| draw_pronunciations(ui, data));
+    draw_grapheme_usages(ui, data, lexicon);
+
+    // show error if empty
+    if data.graphemes.is_empty() {
+        ui.add_space(5.0);
+        ui.colored_label(
+            egui::Color32::RED,
+            "The graphemic inventory must contain at least one grapheme",
+        );
+    }
+}
+
+/// Draw the inventory's graphemes in alphabetical order. Each can be dragged to a new position.
+fn draw_alphabetical_order(ui: &mut egui::Ui, graphemes: &mut grapheme::MasterGraphemeStorage) {
+    ui.label(
+        "Drag the graphemes to set the order of the alphabet, which the lexicon can be sorted by.",
+    );
+    let mut moved_grapheme = None;
+    let mut dragging_grapheme = false; // ignore drags that came from some other list
+    ui.horizontal_wrapped(|ui| {
+        for (index, grapheme) in graphemes.iter().enumerate() {
+            let id = egui::Id::new(("alphabetical order", index));
+            dragging_grapheme |= ui.memory(|mem| mem.is_being_dragged(id));
+            util::draw_reorderable(EditMode::Edit, ui, id, index, &mut moved_grapheme, |ui| {
+                let button = egui::Button::new(grapheme.as_str()).sense(egui::Sense::drag());
+                let response = ui.add(button);
+                (response.clone(), response)
+            });
+        }
+
+        // leave room after the last grapheme to drop a grapheme at the end
+        let size = egui::Vec2::new(12.0, ui.spacing().interact_size.y);
+        let (_, response) = ui.allocate_exact_size(size, egui::Sense::hover());
+        util::draw_reorder_drop_area(ui, graphemes.len(), &mut moved_grapheme, &response);
+    });
+    if let Some(reordering) = moved_grapheme.filter(|_| dragging_grapheme) {
+        graphemes.reorder(&reordering);
+    }
+    if ui
+        .button("Sort by Unicode")
+        .on_hover_text("Reset the alphabet to the order of the graphemes' Unicode code points")
+        .clicked()
+    {
+        graphemes.sort();
+    }
+}
+
+/// Remember the graphemes that were just removed from the inventory but are still used by the
+/// syllable rules, so the user can be offered to remove them from the rules too.
+fn note_removed_graphemes(
+    data: &mut SynthesisTab,
+    old_graphemes: &grapheme::MasterGraphemeStorage,
+) {
+    let removed: Vec<_> = old_graphemes
+        .iter()
+        .filter(|grapheme| !data.graphemes.contains(grapheme))
+        .collect();
+    if removed.is_empty() {
+        return;
+    }
+    let used = data.syllable_vars.graphemes();
+    for grapheme in removed {
+        let grapheme_str = grapheme.to_string();
+        if used.contains(grapheme) && !data.removed_graphemes.contains(&grapheme_str) {
+            data.removed_graphemes.push(grapheme_str);
+        }
+    }
+}
+
+/// Update everything that refers to a grapheme after it's edited in the inventory, so the rules,
+/// categories, pronunciations, constraints, and allophony rules use the new grapheme.
+fn rename_grapheme(data: &mut SynthesisTab, old: &grapheme::Grapheme, new: &grapheme::Grapheme) {
+    data.graphemes.rename(old, new.clone());
+    data.syllable_vars.rename_grapheme(old.as_str(), new);
+    for map in [&mut data.grapheme_categories, &mut data.pronunciations] {
+        if let Some(value) = map.remove(old.as_str()) {
+            map.entry(new.to_string()).or_insert(value);
+        }
+    }
+    for constraint in &mut data.word_constraints {
+        rename_in_pattern(&mut constraint.pattern, old.as_str(), new.as_str());
+    }
+    for rule in &mut data.allophony {
+        for text in [
+            &mut rule.target,
+            &mut rule.replacement,
+            &mut rule.preceding,
+            &mut rule.following,
+        ] {
+            rename_in_pattern(text, old.as_str(), new.as_str());
+        }
+    }
+}
+
+/// Replace a grapheme in a space-separated pattern of graphemes, leaving the pattern untouched if
+/// it doesn't use the grapheme.
+fn rename_in_pattern(pattern: &mut String, old: &str, new: &str) {
+    if pattern.split_whitespace().any(|element| element == old) {
+        *pattern = pattern
+            .split_whitespace()
+            .map(|element| if element == old { new } else { element })
+            .join(" ");
+    }
+}
+
+/// Offer to remove graphemes that were removed from the inventory from the syllable rules too.
+fn draw_removed_graphemes(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    // forget graphemes that were added back, or that the rules stopped using
+    let used = data.syllable_vars.graphemes();
+    data.removed_graphemes.retain(|grapheme| {
+        let grapheme = grapheme::Grapheme::new(grapheme.as_str());
+        !data.graphemes.contains(&grapheme) && used.contains(&grapheme)
+    });
+
+    let color = ui.visuals().warn_fg_color;
+    let mut dismissed = None;
+    for (i, grapheme) in data.removed_graphemes.iter().enumerate() {
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                color,
+                format!("<{grapheme}> was removed, but syllable rules still use it."),
+            );
+            if ui
+                .small_button("Remove from All Rules Too")
+                .on_hover_text(format!("Remove <{grapheme}> from every syllable rule"))
+                .clicked()
+            {
+                data.syllable_vars.remove_grapheme(grapheme);
+                dismissed = Some(i);
+            }
+            if ui.small_button("Keep in Rules").clicked() {
+                dismissed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = dismissed {
+        data.removed_graphemes.remove(i);
+    }
+}
+
+/// Warn about multigraphs that can also be spelled with shorter graphemes, since words containing
+/// them can't be split into graphemes unambiguously.
+fn draw_ambiguous_multigraphs(ui: &mut egui::Ui, data: &SynthesisTab) {
+    let ambiguous = grapheme::find_ambiguous_multigraphs(&data.graphemes);
+    if ambiguous.is_empty() {
+        return;
+    }
+    let color = ui.visuals().warn_fg_color;
+    ui.add_space(5.0);
+    ui.colored_label(
+        color,
+        "Some multigraphs can also be spelled with shorter graphemes, so words that contain them \
+        can be split more than one way:",
+    );
+    for (multigraph, parts) in ambiguous {
+        let parts = parts.iter().map(|part| format!("<{part}>")).join(" + ");
+        ui.colored_label(color, format!("  * <{multigraph}> = {parts}"));
+    }
+}
+
+/// Render a menu for choosing the category of a grapheme, including a field for making a new
+/// custom category.
+fn draw_category_menu(
+    ui: &mut egui::Ui,
+    categories: &mut BTreeMap<String, String>,
+    new_category: &mut String,
+    grapheme: &str,
+) {
+    let choices: BTreeSet<String> = DEFAULT_CATEGORIES
+        .into_iter()
+        .map(str::to_owned)
+        .chain(categories.values().cloned())
+        .collect();
+    let current = categories.get(grapheme).cloned();
+    for choice in choices {
+        if ui
+            .radio(current.as_ref() == Some(&choice), choice.as_str())
+            .clicked()
+        {
+            categories.insert(grapheme.to_owned(), choice);
+            ui.close_menu();
+        }
+    }
+    if ui.radio(current.is_none(), "None").clicked() {
+        categories.remove(grapheme);
+        ui.close_menu();
+    }
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(new_category)
+                .hint_text("New category")
+                .desired_width(100.0),
+        );
+        let name = new_category.trim();
+        if ui
+            .add_enabled(!name.is_empty(), egui::Button::new("Add"))
+            .clicked()
+        {
+            categories.insert(grapheme.to_owned(), name.to_owned());
+            new_category.clear();
+            ui.close_menu();
+        }
+    });
+}
+
+/// Render the graphemes in each category, if any graphemes have been categorized.
+fn draw_category_members(ui: &mut egui::Ui, data: &SynthesisTab) {
+    for category in data.category_names() {
+        ui.horizontal_wrapped(|ui| {
+            ui.strong(format!("{category}:"));
+            ui.label(data.category_members(&category).join(" "));
+        });
+    }
+}
+
+/// Draw a field for the IPA pronunciation of each grapheme. Blank fields mean a grapheme is
+/// pronounced the way it's written.
+fn draw_pronunciations(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    if data.graphemes.is_empty() {
+        ui.label("(no graphemes)");
+        return;
+    }
+    egui::Grid::new("grapheme pronunciations").show(ui, |ui| {
+        for grapheme in &data.graphemes {
+            let grapheme = grapheme.as_str();
+            let mut ipa = data
+                .pronunciations
+                .get(grapheme)
+                .cloned()
+                .unwrap_or_default();
+            ui.monospace(format!("<{grapheme}>"));
+            let field = egui::TextEdit::singleline(&mut ipa)
+                .hint_text(grapheme)
+                .desired_width(60.0);
+            if ui.add(field).changed() {
+                if ipa.is_empty() {
+                    data.pronunciations.remove(grapheme);
+                } else {
+                    data.pronunciations.insert(grapheme.to_owned(), ipa);
+                }
+            }
+            ui.end_row();
+        }
+    });
+}
+
+/// Draw a list of the lexicon entries whose conlang form contains the grapheme selected through
+/// the inventory's "Find in Lexicon" menu, if there is one.
+fn draw_grapheme_usages(ui: &mut egui::Ui, data: &mut SynthesisTab, lexicon: &Lexicon) {
+    if let Some(selected) = &data.usage_grapheme {
+        let mut close = false;
+        ui.add_space(5.0);
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.strong(format!("Lexicon entries containing <{}>", selected));
+                close = ui.small_button("Close").clicked();
+            });
+
+            // segment each word so that <c> doesn't match the <ch> in a word
+            let mut usages: Vec<_> = lexicon
+                .iter()
+                .map(|(native, entry)| (native, &entry.word))
+                .filter(|(_, conlang)| {
+                    grapheme::segment(conlang, &data.graphemes).contains(&selected.as_str())
+                })
+                .collect();
+            usages.sort();
+            if usages.is_empty() {
+                ui.label("(none)");
+            } else {
+                egui::Grid::new("grapheme usages")
+                    .striped(true)
+                    .min_col_width(100.0)
+                    .show(ui, |ui| {
+                        for (native, conlang) in usages {
+                            ui.label(conlang);
+                            ui.label(native);
+                            ui.end_row();
+                        }
+                    });
+            }
+        });
+        if close {
+            data.usage_grapheme = None;
+        }
+    }
+}
+
+fn draw_syllable_counter(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    ui.heading("Word Length");
+    ui.label(
         "Word length is measured in syllables. The settings below determine the probability \
-        of generating a word with the given number of syllables. On average, function words \
-        (conjunctions, determiners, etc.) often have fewer syllables than content words.",
+        of generating a word with the given number of syllables, relative to the other values in \
+        the same column. On average, function words (conjunctions, determiners, etc.) often have \
+        fewer syllables than content words. Words can also be limited to a maximum number of \
+        graphemes.",
     );
     ui.add_space(5.0);
+    let func_total: u16 = data.syllable_wgts.0.iter().sum();
+    let content_total: u16 = data.syllable_wgts.1.iter().sum();
     ui.group(|ui| {
         egui::Grid::new("syllable count").show(ui, |ui| {
             // header row
@@ -265,10 +1591,20 @@ fn draw_syllable_counter(ui: &mut egui::Ui, data: &mut SynthesisTab) {
                 .resize(data.max_syllables.1 as usize, 0);
             ui.end_row();
 
+            // max grapheme row
+            ui.label("Max Graphemes:").on_hover_text(
+                "Words longer than this are generated again, with a new number of syllables. This \
+                is best effort: if no short enough word turns up after several tries, the last \
+                word is kept even though it's too long.",
+            );
+            draw_max_graphemes_field(ui, &mut data.max_graphemes.0);
+            draw_max_graphemes_field(ui, &mut data.max_graphemes.1);
+            ui.end_row();
+
             // hardcoded first weight (so it doesn't say "1 Syllables")
             ui.label("1 Syllable:");
-            ui.add(int_field_percent(&mut data.syllable_wgts.0[0]));
-            ui.add(int_field_percent(&mut data.syllable_wgts.1[0]));
+            draw_weight_field(ui, &mut data.syllable_wgts.0[0], func_total);
+            draw_weight_field(ui, &mut data.syllable_wgts.1[0], content_total);
             ui.end_row();
 
             // all other weights
@@ -284,46 +1620,253 @@ fn draw_syllable_counter(ui: &mut egui::Ui, data: &mut SynthesisTab) {
                 ui.label(format!("{} Syllables:", row_num + 2));
                 match wgts {
                     EitherOrBoth::Both(wgt1, wgt2) => {
-                        ui.add(int_field_percent(wgt1));
-                        ui.add(int_field_percent(wgt2));
+                        draw_weight_field(ui, wgt1, func_total);
+                        draw_weight_field(ui, wgt2, content_total);
                     }
                     EitherOrBoth::Left(wgt) => {
-                        ui.add(int_field_percent(wgt));
+                        draw_weight_field(ui, wgt, func_total);
                     }
                     EitherOrBoth::Right(wgt) => {
                         ui.scope(|_| {}); // empty cell
-                        ui.add(int_field_percent(wgt));
+                        draw_weight_field(ui, wgt, content_total);
                     }
                 }
                 ui.end_row();
             }
+
+            // preset row
+            ui.label("Presets:");
+            draw_length_preset_menu(ui, &mut data.syllable_wgts.0);
+            draw_length_preset_menu(ui, &mut data.syllable_wgts.1);
+            ui.end_row();
         });
     });
 
-    // check each column sums to 100
-    let func_total: u16 = data.syllable_wgts.0.iter().sum();
-    let content_total: u16 = data.syllable_wgts.1.iter().sum();
-    if func_total != 100 || content_total != 100 {
+    // count how many different words the syllable rules can generate
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        ui.label("Word Space:");
+        ui.add(int_field_1_to_100(&mut data.space_syllables));
+        let num_syllables = data.space_syllables.max(1) as usize;
+        match count_words(data, num_syllables) {
+            Some(count) => ui.label(format!(
+                "≈ {} possible {num_syllables}-syllable words",
+                format_count(count)
+            )),
+            None => ui.label(format!(
+                "An unbounded number of possible {num_syllables}-syllable words"
+            )),
+        }
+        .on_hover_text(
+            "Counted from the syllable rules. Different paths through the rules that spell the \
+            same word are counted separately, so the true number may be lower.",
+        );
+    });
+
+    // check each column has a nonzero weight
+    let func_valid = verify_weights(&data.syllable_wgts.0);
+    let content_valid = verify_weights(&data.syllable_wgts.1);
+    if !func_valid || !content_valid {
         ui.add_space(5.0);
-        ui.colored_label(egui::Color32::RED, "Each column should add up to 100%:");
-        if func_total != 100 {
+        ui.colored_label(
+            egui::Color32::RED,
+            "Each column should have at least one nonzero value:",
+        );
+        if !func_valid {
             ui.colored_label(
                 egui::Color32::RED,
-                format!(
-                    "  * The column \"Function Words\" adds up to {}%",
-                    func_total
-                ),
+                "  * The column \"Function Words\" is all zeros",
             );
         }
-        if content_total != 100 {
+        if !content_valid {
             ui.colored_label(
                 egui::Color32::RED,
-                format!(
-                    "  * The column \"Content Words\" adds up to {}%",
-                    content_total
-                ),
+                "  * The column \"Content Words\" is all zeros",
+            );
+        }
+    }
+}
+
+/// Draw a field for a word length weight, followed by the weight's share of its column's total.
+fn draw_weight_field(ui: &mut egui::Ui, weight: &mut u16, total: u16) {
+    ui.horizontal(|ui| {
+        ui.add(int_field_weight(weight));
+        let percent = if total > 0 {
+            f32::from(*weight) / f32::from(total) * 100.0
+        } else {
+            0.0
+        };
+        ui.weak(format!("{:.0}%", percent));
+    });
+}
+
+/// Draw a field for the optional maximum word length in graphemes, with a checkbox to turn the
+/// limit on and off.
+fn draw_max_graphemes_field(ui: &mut egui::Ui, max_graphemes: &mut Option<u8>) {
+    ui.horizontal(|ui| {
+        let mut limited = max_graphemes.is_some();
+        if ui.checkbox(&mut limited, "").changed() {
+            *max_graphemes = limited.then_some(DEFAULT_MAX_GRAPHEMES);
+        }
+        match max_graphemes {
+            Some(max) => ui.add(int_field_1_to_100(max)),
+            None => ui.weak("(no limit)"),
+        };
+    });
+}
+
+/// Draw a menu for filling a column of word length weights with a preset distribution.
+fn draw_length_preset_menu(ui: &mut egui::Ui, weights: &mut Vec<u16>) {
+    ui.menu_button("Fill...", |ui| {
+        for preset in LengthPreset::iter() {
+            if ui.button(preset.name()).clicked() {
+                *weights = normalize_weights(&preset.weights(weights.len()));
+                ui.close_menu();
+            }
+        }
+        ui.separator();
+        let normalize_btn = ui
+            .button("Normalize")
+            .on_hover_text("Rescale the current values so that they add up to 100%");
+        if normalize_btn.clicked() {
+            let raw: Vec<f64> = weights.iter().map(|&weight| f64::from(weight)).collect();
+            *weights = normalize_weights(&raw);
+            ui.close_menu();
+        }
+    });
+}
+
+fn draw_word_constraints(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    ui.heading("Word Constraints");
+    ui.label(
+        "Constraints forbid sequences of graphemes anywhere in a word, even across syllables. \
+        Write a sequence as graphemes and category names separated by spaces, like \"Vowel Vowel\" \
+        or \"n p\". Write # to match the edge of the word, like \"# ŋ\" or \"# Vowel #\". Words \
+        that break a constraint are generated again.",
+    );
+    ui.add_space(5.0);
+    let mut removed = None;
+    for (i, constraint) in data.word_constraints.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label("No word may");
+            egui::ComboBox::from_id_source(("constraint position", i))
+                .selected_text(constraint.position.name())
+                .show_ui(ui, |ui| {
+                    for position in ConstraintPosition::iter() {
+                        ui.selectable_value(&mut constraint.position, position, position.name());
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut constraint.pattern)
+                    .hint_text("e.g. Vowel Vowel")
+                    .desired_width(150.0),
+            );
+            if ui.small_button("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        data.word_constraints.remove(i);
+    }
+    if ui.button("Add Constraint").clicked() {
+        data.word_constraints.push(Default::default());
+    }
+}
+
+fn draw_allophony_rules(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    ui.heading("Allophony");
+    ui.label(
+        "Allophony rules change how a grapheme is realized depending on its neighbors, like \
+        <n> becoming <ŋ> before <k>. Each part is a grapheme or a category name, and the contexts \
+        can be sequences of them, like \"Vowel Vowel\". Leave a context blank to match anything, \
+        or write # to match the edge of the word. Rules are applied in order to generated words \
+        and to pronunciations.",
+    );
+    ui.add_space(5.0);
+    let mut removed = None;
+    for (i, rule) in data.allophony.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.add(allophony_field(&mut rule.target, "n"));
+            ui.label("becomes");
+            ui.add(allophony_field(&mut rule.replacement, "ŋ"));
+            ui.label("after");
+            ui.add(allophony_field(&mut rule.preceding, "(anything)"));
+            ui.label("and before");
+            ui.add(allophony_field(&mut rule.following, "k"));
+            if ui.small_button("Remove").clicked() {
+                removed = Some(i);
+            }
+        });
+    }
+    if let Some(i) = removed {
+        data.allophony.remove(i);
+    }
+    if ui.button("Add Rule").clicked() {
+        data.allophony.push(Default::default());
+    }
+}
+
+fn draw_reduplication_settings(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    ui.heading("Reduplication");
+    ui.label(
+        "Some words can be formed by repeating one of their syllables, like \"bobo\" or \
+        \"kotata\". The copy is placed next to the original syllable.",
+    );
+    ui.add_space(5.0);
+    let reduplication = &mut data.reduplication;
+    ui.horizontal(|ui| {
+        ui.label("Repeated Syllable:");
+        for copied in ReduplicatedSyllable::iter() {
+            ui.selectable_value(&mut reduplication.copied, copied, copied.name());
+        }
+    });
+    ui.add_enabled_ui(reduplication.copied != ReduplicatedSyllable::None, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Words Reduplicated:");
+            ui.add(
+                egui::DragValue::new(&mut reduplication.chance)
+                    .clamp_range(0..=100)
+                    .suffix("%"),
             );
+        });
+    });
+}
+
+fn draw_stress_settings(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    ui.heading("Stress");
+    ui.label(
+        "In words with more than one syllable, one syllable can be stressed (pronounced with more \
+        emphasis than the others). The settings below determine which syllable is stressed and how \
+        the stress is written.",
+    );
+    ui.add_space(5.0);
+    ui.horizontal(|ui| {
+        ui.label("Stressed Syllable:");
+        for position in StressPosition::iter() {
+            ui.selectable_value(&mut data.stress_position, position, position.name());
         }
+    });
+    ui.add_enabled_ui(data.stress_position != StressPosition::None, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Written With:");
+            for mark in StressMark::iter() {
+                ui.selectable_value(&mut data.stress_mark, mark, mark.name());
+            }
+        });
+    });
+    if data.stress_position == StressPosition::Random {
+        ui.horizontal(|ui| {
+            ui.label("Weights:").on_hover_text(
+                "How likely each syllable is to be stressed. If every weight is 0, each syllable \
+                is equally likely.",
+            );
+            let total = data.stress_weights.iter().sum();
+            for (name, weight) in STRESS_WEIGHT_NAMES.iter().zip(&mut data.stress_weights) {
+                ui.label(*name);
+                draw_weight_field(ui, weight, total);
+            }
+        });
     }
 }
 
@@ -333,38 +1876,72 @@ fn draw_syllable_rules(ui: &mut egui::Ui, data: &mut SynthesisTab) {
         graphemes. There are four types of syllables: initial, middle, terminal, and single (for words with \
         only one syllable). Each syllable type is generated based on the rules you define in this section.");
     ui.add_space(5.0);
-    EditMode::draw_mode_picker(ui, &mut data.syllable_edit_mode);
+    ui.horizontal(|ui| {
+        EditMode::draw_mode_picker(ui, &mut data.syllable_edit_mode);
+        ui.separator();
+        let copy_btn = ui
+            .button("Copy as Text")
+            .on_hover_text("Copy these rules to the clipboard in a readable text form");
+        if copy_btn.clicked() {
+            let text = syllable_vars_to_text(&data.syllable_vars);
+            ui.output_mut(|output| output.copied_text = text);
+        }
+        let import_btn = ui
+            .button("Import from Text")
+            .on_hover_text("Replace these rules with rules written in the same text form");
+        if import_btn.clicked() && data.rules_import.is_none() {
+            data.rules_import = Some(syllable_vars_to_text(&data.syllable_vars));
+            data.rules_import_error = None;
+        }
+    });
+    draw_rules_import(ui, data);
     ui.add_space(5.0);
     ui.group(|ui| {
         ui.set_width(ui.available_width()); // fill available width
         ui.spacing_mut().interact_size.y = 20.0; // fix row height
+        let categories = data.category_names();
 
         // remove vars that are both unreachable and empty
         flag_reachable_vars(&mut data.syllable_vars);
+        let SyllableVars {
+            vars, reachable, ..
+        } = &mut data.syllable_vars;
+        vars.retain(|var, rule| reachable.contains(var) || rule.head.head.initialized());
+        flag_recursive_vars(&mut data.syllable_vars);
+        let expansions = data
+            .syllable_vars
+            .iter()
+            .map(|(var, rule)| (var.to_owned(), or_rule_to_text(rule)))
+            .collect();
         let SyllableVars {
             roots,
             vars,
             reachable,
+            recursive,
         } = &mut data.syllable_vars;
-        vars.retain(|var, rule| reachable.contains(var) || rule.head.head.initialized());
 
-        // data updated by certain visited nodes
-        let mut order = 0; // incremented for each leaf node visited
-        let mut new_var = None; // set if a new variable is referenced
+        // names that a variable or category node can refer to
+        let defined = DefinedNames {
+            vars: expansions,
+            categories,
+        };
+
+        let mut visit = RuleVisit::default();
 
         // 4 root rules
         for (name, rule) in SyllableRoots::names().zip(roots.iter_mut()) {
-            ui.horizontal_wrapped(|ui| {
-                ui.monospace(format!("{} =", name));
+            let error = recursive.contains(name).then_some(RECURSIVE_VAR_ERROR);
+            let response = draw_collapsible_rule(ui, name, error, |ui| {
                 draw_or_node(
                     ui,
                     rule,
                     data.syllable_edit_mode,
                     &data.graphemes,
-                    &mut order,
-                    &mut new_var,
+                    &defined,
+                    &mut visit,
                 );
             });
+            draw_navigation_target(ui, name, &response, &mut data.navigation);
             ui.add_space(3.0);
         }
 
@@ -372,71 +1949,247 @@ fn draw_syllable_rules(ui: &mut egui::Ui, data: &mut SynthesisTab) {
         if !vars.is_empty() {
             ui.separator();
             for (var, rule) in vars.iter_mut() {
-                ui.horizontal_wrapped(|ui| {
-                    if reachable.contains(var) {
-                        ui.monospace(format!("{} =", var));
-                    } else {
-                        let red_text = egui::RichText::new(var)
-                            .monospace()
-                            .color(egui::Color32::RED);
-                        ui.label(red_text).on_hover_ui(|ui| {
-                            ui.colored_label(
-                                egui::Color32::RED,
-                                "Not reachable from a start variable",
-                            );
-                        });
-                        ui.monospace("=");
-                    }
+                let error = if !reachable.contains(var) {
+                    Some("Not reachable from a start variable")
+                } else if recursive.contains(var) {
+                    Some(RECURSIVE_VAR_ERROR)
+                } else {
+                    None
+                };
+                let response = draw_collapsible_rule(ui, var, error, |ui| {
                     draw_or_node(
                         ui,
                         rule,
                         data.syllable_edit_mode,
                         &data.graphemes,
-                        &mut order,
-                        &mut new_var,
+                        &defined,
+                        &mut visit,
                     );
                 });
+                draw_navigation_target(ui, var, &response, &mut data.navigation);
                 ui.add_space(3.0);
             }
         }
 
+        // scroll to a variable's rule if a reference to it was clicked
+        if let Some(var) = visit.clicked_var {
+            data.navigation = Some(VarNavigation {
+                var,
+                scrolled: false,
+                highlight_until: 0.0,
+            });
+        }
+
         // add new variable if an unrecognized name was used
-        if let Some(new_var) = new_var {
+        if let Some(new_var) = visit.new_var {
             // we have to use all() instead of contains() because we're comparing &str to String
             if SyllableRoots::names().all(|s| *s != new_var) {
                 vars.entry(new_var).or_insert_with(Default::default);
             }
         }
     });
+    draw_orphaned_graphemes(ui, data);
 }
 
-fn draw_or_node(
-    ui: &mut egui::Ui,
-    rule: &mut OrRule,
-    mode: EditMode,
-    graphemes: &grapheme::MasterGraphemeStorage,
-    order: &mut usize,
-    new_var: &mut Option<String>,
-) {
-    // draw head node
-    let should_delete = draw_and_node(ui, &mut rule.head, mode, graphemes, order, new_var);
-    if should_delete {
-        rule.head.head = LeafRule::Uninitialized;
+/// Render the text box for importing syllable rules, if it is open.
+fn draw_rules_import(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    let Some(text) = &mut data.rules_import else {
+        return;
+    };
+    ui.add_space(5.0);
+    ui.add(
+        egui::TextEdit::multiline(text)
+            .code_editor()
+            .hint_text("InitialSyllable = {p t k} a | Other")
+            .desired_width(ui.available_width()),
+    );
+    let (import_clicked, cancel_clicked) = ui
+        .horizontal(|ui| (ui.button("Import").clicked(), ui.button("Cancel").clicked()))
+        .inner;
+    if import_clicked {
+        match syllable_vars_from_text(text, &data.graphemes) {
+            Ok(vars) => {
+                data.syllable_vars = vars;
+                data.rules_import = None;
+                data.rules_import_error = None;
+            }
+            Err(error) => data.rules_import_error = Some(error),
+        }
+    } else if cancel_clicked {
+        data.rules_import = None;
+        data.rules_import_error = None;
     }
+    if let Some(error) = &data.rules_import_error {
+        ui.colored_label(egui::Color32::RED, error);
+    }
+}
 
-    // draw remaining nodes
-    rule.tail.retain_mut(|and_rule| {
-        ui.heading("OR");
-        !draw_and_node(ui, and_rule, mode, graphemes, order, new_var)
-    });
-
-    // draw button to insert new OR clause
-    if mode.is_edit() && rule.head.head.initialized() {
-        ui.add_space(12.0);
-        LeafRule::menu(ui, "OR...", |new_rule| {
-            rule.tail.push(AndRule::new(new_rule))
+/// Draw a list of the graphemes used in syllable rules that aren't in the graphemic inventory,
+/// with buttons to remove them from the rules.
+fn draw_orphaned_graphemes(ui: &mut egui::Ui, data: &mut SynthesisTab) {
+    let orphans = find_orphaned_graphemes(data);
+    if orphans.is_empty() {
+        return;
+    }
+    ui.add_space(5.0);
+    ui.colored_label(
+        egui::Color32::RED,
+        "Some rules use graphemes that aren't in the graphemic inventory:",
+    );
+    for (var, grapheme) in orphans {
+        ui.horizontal(|ui| {
+            ui.colored_label(egui::Color32::RED, format!("  * <{}> in {}", grapheme, var));
+            let remove_btn = ui
+                .small_button("Remove")
+                .on_hover_text(format!("Remove <{}> from {}", grapheme, var));
+            if remove_btn.clicked() {
+                if let Some(rule) = data.syllable_vars.get_mut(&var) {
+                    rule.iter_mut()
+                        .flat_map(NonEmptyList::iter_mut)
+                        .for_each(|leaf| leaf.remove_grapheme(&grapheme));
+                }
+            }
+        });
+    }
+    ui.checkbox(
+        &mut data.orphans_are_errors,
+        "Don't allow translation until these are fixed",
+    );
+}
+
+/// Draw the name on the left-hand side of a rule. If an error is given, the name is drawn in red
+/// and the error is shown when it is hovered over.
+fn draw_var_name(ui: &mut egui::Ui, name: &str, error: Option<&str>) {
+    if let Some(error) = error {
+        let red_text = egui::RichText::new(name)
+            .monospace()
+            .color(egui::Color32::RED);
+        ui.label(red_text).on_hover_ui(|ui| {
+            ui.colored_label(egui::Color32::RED, error);
+        });
+        ui.monospace("=");
+    } else {
+        ui.monospace(format!("{} =", name));
+    }
+}
+
+/// Draw a variable's rule under a header that can be clicked to fold it away.
+fn draw_collapsible_rule(
+    ui: &mut egui::Ui,
+    name: &str,
+    error: Option<&str>,
+    add_body: impl FnOnce(&mut egui::Ui),
+) -> egui::Response {
+    let id = ui.make_persistent_id(("syllable rule", name));
+    let (_, header, body) =
+        egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true)
+            .show_header(ui, |ui| draw_var_name(ui, name, error))
+            .body(|ui| ui.horizontal_wrapped(add_body));
+    match body {
+        Some(body) => header.response.union(body.response),
+        None => header.response,
+    }
+}
+
+/// If navigation to this variable's rule was requested, scroll to the rule and then highlight it
+/// for a moment.
+fn draw_navigation_target(
+    ui: &mut egui::Ui,
+    var: &str,
+    response: &egui::Response,
+    navigation: &mut Option<VarNavigation>,
+) {
+    let Some(target) = navigation else {
+        return;
+    };
+    if target.var != var {
+        return;
+    }
+    let now = ui.input(|input| input.time);
+    if !target.scrolled {
+        response.scroll_to_me(Some(egui::Align::Center));
+        target.scrolled = true;
+        target.highlight_until = now + NAVIGATION_HIGHLIGHT_SECS;
+    }
+    if now < target.highlight_until {
+        let color = ui.visuals().selection.bg_fill.linear_multiply(0.4);
+        ui.painter()
+            .rect_filled(response.rect.expand(2.0), 3.0, color);
+        ui.ctx().request_repaint();
+    } else {
+        *navigation = None;
+    }
+}
+
+fn draw_or_node(
+    ui: &mut egui::Ui,
+    rule: &mut OrRule,
+    mode: EditMode,
+    graphemes: &grapheme::MasterGraphemeStorage,
+    defined: &DefinedNames,
+    visit: &mut RuleVisit,
+) {
+    if mode.is_edit() {
+        draw_reorderable_or_clauses(ui, rule, graphemes, defined, visit);
+    } else {
+        // draw head node
+        let should_delete = draw_and_node(ui, &mut rule.head, mode, graphemes, defined, visit);
+        if should_delete {
+            rule.head.head = LeafRule::Uninitialized;
+        }
+
+        // draw remaining nodes
+        rule.tail.retain_mut(|and_rule| {
+            ui.heading("OR");
+            !draw_and_node(ui, and_rule, mode, graphemes, defined, visit)
+        });
+    }
+}
+
+/// Draw the clauses of an OR node in edit mode, each with a handle that can be dragged to move
+/// the clause to a new position.
+fn draw_reorderable_or_clauses(
+    ui: &mut egui::Ui,
+    rule: &mut OrRule,
+    graphemes: &grapheme::MasterGraphemeStorage,
+    defined: &DefinedNames,
+    visit: &mut RuleVisit,
+) {
+    let mode = EditMode::Edit;
+    let base_id = egui::Id::new(("or clause", visit.order));
+    let mut moved_clause = None;
+    let mut dragging_clause = false; // ignore drags that came from some other list
+    for (index, and_rule) in rule.iter_mut().enumerate() {
+        if index > 0 {
+            ui.heading("OR");
+        }
+        let clause_id = base_id.with(index);
+        dragging_clause |= ui.memory(|mem| mem.is_being_dragged(clause_id));
+        util::draw_reorderable(mode, ui, clause_id, index, &mut moved_clause, |ui| {
+            let clause = ui.horizontal_wrapped(|ui| {
+                let handle = util::draw_drag_handle(ui);
+                draw_and_node(ui, and_rule, mode, graphemes, defined, visit);
+                handle
+            });
+            (clause.response, clause.inner)
         });
     }
+
+    // draw button to insert new OR clause, which doubles as the drop zone for dragging a clause
+    // to the end
+    if rule.head.head.initialized() {
+        ui.add_space(12.0);
+        let response = LeafRule::menu(ui, "OR...", |new_rule| rule.push(AndRule::new(new_rule)));
+        util::draw_reorder_drop_area(ui, rule.len(), &mut moved_clause, &response);
+    }
+
+    // if any clauses were dragged and released, move them now
+    if let Some(reordering) = moved_clause.filter(|_| dragging_clause) {
+        let placeholder = OrRule::new(AndRule::new(LeafRule::Uninitialized));
+        let mut clauses: Vec<AndRule> = std::mem::replace(rule, placeholder).into();
+        reordering.apply(&mut clauses);
+        *rule = OrRule::from_vec(clauses).expect("reordering keeps every clause");
+    }
 }
 
 /// Draw an AND rule node. Return true if it should be deleted.
@@ -445,8 +2198,8 @@ fn draw_and_node(
     rule: &mut AndRule,
     mode: EditMode,
     graphemes: &grapheme::MasterGraphemeStorage,
-    order: &mut usize,
-    new_var: &mut Option<String>,
+    defined: &DefinedNames,
+    visit: &mut RuleVisit,
 ) -> bool {
     // draw button to insert node at beginning
     if mode.is_edit() && rule.head.initialized() {
@@ -454,7 +2207,7 @@ fn draw_and_node(
     }
 
     // draw first node
-    let should_delete = draw_leaf_node(ui, &mut rule.head, mode, graphemes, order, new_var);
+    let should_delete = draw_leaf_node(ui, &mut rule.head, mode, graphemes, defined, visit);
     if should_delete {
         if rule.tail.is_empty() {
             return true; // this was the last node, so delete this whole AndRule
@@ -467,26 +2220,26 @@ fn draw_and_node(
         EditMode::View => {
             for rule in &mut rule.tail {
                 ui.label("+");
-                draw_leaf_node(ui, rule, mode, graphemes, order, new_var);
+                draw_leaf_node(ui, rule, mode, graphemes, defined, visit);
             }
         }
         EditMode::Edit => {
             for i in 0..rule.tail.len() {
                 LeafRule::menu(ui, "+", |new_rule| rule.tail.insert(i, new_rule));
-                draw_leaf_node(ui, &mut rule.tail[i], mode, graphemes, order, new_var);
+                draw_leaf_node(ui, &mut rule.tail[i], mode, graphemes, defined, visit);
             }
         }
         EditMode::Delete => {
             rule.tail.retain_mut(|rule| {
                 ui.label("+");
-                !draw_leaf_node(ui, rule, mode, graphemes, order, new_var)
+                !draw_leaf_node(ui, rule, mode, graphemes, defined, visit)
             });
         }
     }
 
     // draw button to insert node at end
     if mode.is_edit() && rule.head.initialized() {
-        LeafRule::menu(ui, "+", |new_rule| rule.tail.push(new_rule));
+        LeafRule::menu(ui, "+", |new_rule| rule.push(new_rule));
     }
 
     false // don't delete this AndRule
@@ -498,10 +2251,10 @@ fn draw_leaf_node(
     rule: &mut LeafRule,
     mode: EditMode,
     graphemes: &grapheme::MasterGraphemeStorage,
-    order: &mut usize,
-    new_var: &mut Option<String>,
+    defined: &DefinedNames,
+    visit: &mut RuleVisit,
 ) -> bool {
-    *order += 1; // increment for each leaf node visited
+    visit.order += 1;
     let response = match rule {
         LeafRule::Uninitialized => {
             if mode.is_edit() {
@@ -511,18 +2264,80 @@ fn draw_leaf_node(
             }
             return false; // not deleteable
         }
-        LeafRule::Sequence(string, input) => ui.add(
-            grapheme::GraphemeInputField::new(string, input, *order)
+        LeafRule::Sequence(string, input) => {
+            let mut toggled = None;
+            let mut field = grapheme::GraphemeInputField::new(string, input, visit.order)
                 .link(graphemes)
+                .sequence_notation(true)
                 .small(true)
                 .allow_editing(mode.is_edit())
-                .interactable(!mode.is_delete()),
-        ),
+                .interactable(!mode.is_delete());
+            let mut make_optional = false;
+            if mode.is_edit() {
+                field = field.context_menu(|ui, grapheme| {
+                    if ui.button("Toggle Optional").clicked() {
+                        toggled = Some(grapheme.clone());
+                        ui.close_menu();
+                    }
+                    if ui.button("Make Whole String Optional").clicked() {
+                        make_optional = true;
+                        ui.close_menu();
+                    }
+                });
+            }
+            let mut response = ui.add(field);
+            if mode.is_edit() {
+                response = response.on_hover_text(
+                    "Write (n) for an optional grapheme, or a/e to pick one of several",
+                );
+            }
+            if let Some(toggled) = toggled {
+                let toggled = toggled.as_str();
+                for element in string
+                    .iter_mut()
+                    .filter(|element| element.to_string() == toggled)
+                {
+                    element.optional = !element.optional;
+                }
+            }
+            if make_optional {
+                rule.toggle_optional();
+            }
+            return util::draw_deletion_overlay(mode, ui, &response);
+        }
+        LeafRule::Optional(inner, chance) => {
+            let should_delete = draw_leaf_node(ui, inner, mode, graphemes, defined, visit);
+            let response = if mode.is_edit() {
+                ui.add(
+                    egui::DragValue::new(chance)
+                        .clamp_range(0..=100)
+                        .prefix("?")
+                        .suffix("%"),
+                )
+            } else {
+                ui.weak(format!("?{chance}%"))
+            };
+            let response =
+                response.on_hover_text("The chance that the node before this is included");
+            let mut make_required = false;
+            if mode.is_edit() {
+                response.context_menu(|ui| {
+                    if ui.button("Make Required").clicked() {
+                        make_required = true;
+                        ui.close_menu();
+                    }
+                });
+            }
+            if make_required {
+                rule.toggle_optional();
+            }
+            return should_delete;
+        }
         LeafRule::Set(set, input) => {
             ui.scope(|ui| {
                 ui.label("{");
                 ui.add(
-                    grapheme::GraphemeInputField::new(set, input, *order)
+                    grapheme::GraphemeInputField::new(set, input, visit.order)
                         .link(graphemes)
                         .small(true)
                         .allow_editing(mode.is_edit())
@@ -542,20 +2357,41 @@ fn draw_leaf_node(
                 );
                 if response.changed() && !input.is_empty() {
                     input.retain(|c| !c.is_whitespace());
-                    *new_var = Some(input.clone());
+                    visit.new_var = Some(input.clone());
                 }
                 response
             } else {
-                let text = if !input.is_empty() {
-                    egui::RichText::new(&*input).monospace()
-                } else {
+                let expansion = defined.vars.get(input.as_str());
+                let undefined = !input.is_empty() && expansion.is_none();
+                let text = if input.is_empty() {
                     egui::RichText::new("(no variable given)").color(egui::Color32::RED)
+                } else if undefined {
+                    egui::RichText::new(&*input)
+                        .monospace()
+                        .color(egui::Color32::RED)
+                } else {
+                    egui::RichText::new(&*input).monospace()
                 };
-                ui.add(
+                let response = ui.add(
                     egui::Label::new(text)
                         .selectable(mode.is_view())
                         .sense(egui::Sense::click()),
-                )
+                );
+                match expansion {
+                    _ if undefined => response.on_hover_ui(|ui| {
+                        ui.colored_label(egui::Color32::RED, "No variable with this name");
+                    }),
+                    Some(expansion) if mode.is_view() => {
+                        if response.clicked() {
+                            visit.clicked_var = Some(input.clone());
+                        }
+                        response.on_hover_ui(|ui| {
+                            ui.monospace(format!("{input} = {expansion}"));
+                            ui.weak("Click to go to this rule");
+                        })
+                    }
+                    _ => response,
+                }
             }
         }
         LeafRule::Blank => ui.add(
@@ -563,7 +2399,84 @@ fn draw_leaf_node(
                 .selectable(mode.is_view())
                 .sense(egui::Sense::click()),
         ),
+        LeafRule::Linker(list, input, chance) => ui
+            .scope(|ui| {
+                ui.add(
+                    grapheme::GraphemeInputField::new(list, input, visit.order)
+                        .link(graphemes)
+                        .small(true)
+                        .allow_editing(mode.is_edit())
+                        .interactable(!mode.is_delete()),
+                );
+                if mode.is_edit() {
+                    ui.add(
+                        egui::DragValue::new(chance)
+                            .clamp_range(0..=100)
+                            .suffix("%"),
+                    );
+                } else {
+                    ui.weak(format!("{chance}%"));
+                }
+            })
+            .response
+            .on_hover_text(
+                "These graphemes are inserted with this chance, and otherwise nothing is",
+            ),
+        LeafRule::Category(category) => {
+            if mode.is_edit() {
+                let selected = if category.is_empty() {
+                    "(choose)"
+                } else {
+                    category.as_str()
+                };
+                let choices: BTreeSet<&str> = DEFAULT_CATEGORIES
+                    .into_iter()
+                    .chain(defined.categories.iter().map(String::as_str))
+                    .collect();
+                egui::ComboBox::from_id_source(("category", visit.order))
+                    .selected_text(format!("[{selected}]"))
+                    .show_ui(ui, |ui| {
+                        for choice in choices {
+                            ui.selectable_value(category, choice.to_owned(), choice);
+                        }
+                    })
+                    .response
+            } else {
+                let empty = !defined.categories.contains(category.as_str());
+                let text = if category.is_empty() {
+                    egui::RichText::new("(no category given)").color(egui::Color32::RED)
+                } else if empty {
+                    egui::RichText::new(format!("[{category}]")).color(egui::Color32::RED)
+                } else {
+                    egui::RichText::new(format!("[{category}]"))
+                };
+                let response = ui.add(
+                    egui::Label::new(text)
+                        .selectable(mode.is_view())
+                        .sense(egui::Sense::click()),
+                );
+                if empty && !category.is_empty() {
+                    response.on_hover_ui(|ui| {
+                        ui.colored_label(egui::Color32::RED, "No graphemes in this category");
+                    })
+                } else {
+                    response
+                }
+            }
+        }
     };
+    if mode.is_edit() {
+        let mut make_optional = false;
+        response.context_menu(|ui| {
+            if ui.button("Make Optional").clicked() {
+                make_optional = true;
+                ui.close_menu();
+            }
+        });
+        if make_optional {
+            rule.toggle_optional();
+        }
+    }
     util::draw_deletion_overlay(mode, ui, &response)
 }
 
@@ -575,7 +2488,7 @@ fn flag_reachable_vars(vars: &mut SyllableVars) {
     while let Some(next) = stack.pop_back() {
         next.iter()
             .flat_map(NonEmptyList::iter)
-            .filter_map(|leaf| match leaf {
+            .filter_map(|leaf| match leaf.required() {
                 LeafRule::Variable(var) => Some(var),
                 _ => None,
             })
@@ -585,69 +2498,1025 @@ fn flag_reachable_vars(vars: &mut SyllableVars) {
     }
 }
 
+/// Find the variables that can never finish expanding, because every one of their OR clauses leads
+/// back into a cycle. These variables are stored in the set `vars.recursive`.
+fn flag_recursive_vars(vars: &mut SyllableVars) {
+    let names: Vec<&str> = SyllableRoots::names()
+        .chain(vars.vars.keys().map(String::as_str))
+        .collect();
+
+    // a variable terminates if any of its OR clauses only references terminating variables, so
+    // keep marking variables until we stop finding new ones
+    let mut terminating: HashSet<&str> = HashSet::new();
+    loop {
+        let mut found_new = false;
+        for &name in &names {
+            if terminating.contains(name) {
+                continue;
+            }
+            let terminates = vars.get(name).map_or(true, |rule| {
+                rule.iter().any(|and_rule| {
+                    and_rule.iter().all(|leaf| match leaf {
+                        LeafRule::Optional(_, chance) if *chance < 100 => true, // can be skipped
+                        LeafRule::Optional(inner, _) => match inner.required() {
+                            LeafRule::Variable(var) => {
+                                terminating.contains(var.as_str()) || vars.get(var).is_none()
+                            }
+                            _ => true,
+                        },
+                        LeafRule::Variable(var) => {
+                            terminating.contains(var.as_str()) || vars.get(var).is_none()
+                        }
+                        _ => true,
+                    })
+                })
+            });
+            if terminates {
+                terminating.insert(name);
+                found_new = true;
+            }
+        }
+        if !found_new {
+            break;
+        }
+    }
+
+    let recursive = names
+        .into_iter()
+        .filter(|name| !terminating.contains(name))
+        .map(str::to_owned)
+        .collect();
+    vars.recursive = recursive;
+}
+
+/// Return roughly how many distinct words with the given number of syllables the syllable rules
+/// can generate, or None if a recursive variable makes the count unbounded.
+fn count_words(data: &SynthesisTab, num_syllables: usize) -> Option<f64> {
+    let roots = &data.syllable_vars.roots;
+    let count = |rule| count_or_rule(rule, data, &mut Vec::new());
+    if num_syllables == 1 {
+        return count(&roots.single);
+    }
+    let mut total = count(&roots.initial)? * count(&roots.terminal)?;
+    if num_syllables > 2 {
+        total *= count(&roots.middle)?.powi(num_syllables as i32 - 2);
+    }
+    Some(total)
+}
+
+/// Return roughly how many distinct words the syllable rules can generate across every word
+/// length with a nonzero weight, or None if the count is unbounded.
+pub fn count_word_space(data: &SynthesisTab, weights: &[u16]) -> Option<f64> {
+    let mut total = 0.0;
+    for (i, _) in weights.iter().enumerate().filter(|(_, &weight)| weight > 0) {
+        total += count_words(data, i + 1)?;
+    }
+    Some(total)
+}
+
+/// Count the strings an OR node can generate: the sum over its clauses of the product over each
+/// clause's leaves. `stack` holds the variables currently being expanded, so a cycle can be
+/// reported as unbounded (None).
+fn count_or_rule<'a>(
+    rule: &'a OrRule,
+    data: &'a SynthesisTab,
+    stack: &mut Vec<&'a str>,
+) -> Option<f64> {
+    let mut total = 0.0;
+    for and_rule in rule.iter() {
+        let mut product = 1.0;
+        for leaf in and_rule.iter() {
+            product *= count_leaf_rule(leaf, data, stack)?;
+        }
+        total += product;
+    }
+    Some(total)
+}
+
+/// Count the strings a leaf node can generate. See `count_or_rule`.
+fn count_leaf_rule<'a>(
+    leaf: &'a LeafRule,
+    data: &'a SynthesisTab,
+    stack: &mut Vec<&'a str>,
+) -> Option<f64> {
+    let count = match leaf {
+        LeafRule::Sequence(list, _) => list
+            .iter()
+            .map(|element| (element.alternatives.len().max(1) + element.optional as usize) as f64)
+            .product(),
+        LeafRule::Set(list, _) => list.len().max(1) as f64,
+        LeafRule::Category(category) => data.category_members(category).count().max(1) as f64,
+        LeafRule::Variable(var) => match data.syllable_vars.get(var) {
+            _ if stack.contains(&var.as_str()) => return None,
+            Some(var_rule) => {
+                stack.push(var);
+                let count = count_or_rule(var_rule, data, stack)?;
+                stack.pop();
+                count
+            }
+            None => 1.0,
+        },
+        LeafRule::Linker(list, _, chance) if !list.is_empty() && (1..100).contains(chance) => 2.0,
+        LeafRule::Optional(inner, chance) => {
+            let skipped = if *chance < 100 { 1.0 } else { 0.0 }; // leaving the node out
+            count_leaf_rule(inner, data, stack)? + skipped
+        }
+        LeafRule::Linker(..) | LeafRule::Blank | LeafRule::Uninitialized => 1.0,
+    };
+    Some(count)
+}
+
+/// Format a possibly huge count for display, switching to scientific notation for large values.
+fn format_count(count: f64) -> String {
+    if count < 1e9 {
+        format!("{count:.0}")
+    } else {
+        format!("{count:.2e}")
+    }
+}
+
+/// Lay out text with each grapheme colored by its category, so that the structure of words is
+/// easy to see. Text that isn't a categorized grapheme keeps the normal text color.
+pub fn colored_graphemes(ui: &egui::Ui, data: &SynthesisTab, text: &str) -> egui::text::LayoutJob {
+    let mut categories: Vec<String> = DEFAULT_CATEGORIES.map(str::to_owned).to_vec();
+    categories.extend(
+        data.category_names()
+            .into_iter()
+            .filter(|category| !DEFAULT_CATEGORIES.contains(&category.as_str())),
+    );
+
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let mut job = egui::text::LayoutJob::default();
+    for (original, segment) in segment_ignoring_case(text, &data.graphemes) {
+        let color = data
+            .grapheme_categories
+            .get(&segment)
+            .and_then(|category| categories.iter().position(|name| name == category))
+            .map_or(ui.visuals().text_color(), |i| {
+                CATEGORY_COLORS[i % CATEGORY_COLORS.len()]
+            });
+        job.append(
+            original,
+            0.0,
+            egui::TextFormat::simple(font_id.clone(), color),
+        );
+    }
+    job
+}
+
+/// Split text into graphemes like `grapheme::segment`, but find capitalized graphemes too. Each
+/// piece of the original text is returned with the lowercase segment it was matched as.
+fn segment_ignoring_case<'a>(
+    text: &'a str,
+    inventory: &grapheme::MasterGraphemeStorage,
+) -> Vec<(&'a str, String)> {
+    // lowercasing can change the length of a character, so remember where each lowercase byte
+    // ends in the original text
+    let mut lowercase = String::with_capacity(text.len());
+    let mut original_ends = Vec::with_capacity(text.len());
+    for (i, chr) in text.char_indices() {
+        lowercase.extend(chr.to_lowercase());
+        original_ends.resize(lowercase.len(), i + chr.len_utf8());
+    }
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut lowercase_end = 0;
+    for segment in grapheme::segment(&lowercase, inventory) {
+        // a segment that ends partway through a character's lowercase form takes the whole
+        // character, so the next segment may have nothing left to show
+        lowercase_end += segment.len();
+        let end = original_ends[lowercase_end - 1];
+        if end > start {
+            pieces.push((&text[start..end], segment.to_owned()));
+            start = end;
+        }
+    }
+    pieces
+}
+
+/// Return a function that tells whether a character can be part of a word written in the conlang.
+/// The characters of the graphemes are collected up front, so each check is quick.
+pub fn word_char_checker(data: &SynthesisTab) -> impl Fn(char) -> bool {
+    let grapheme_chars: HashSet<char> = data
+        .graphemes
+        .iter()
+        .flat_map(|grapheme| grapheme.as_str().chars())
+        .collect();
+    move |chr| chr.is_alphanumeric() || chr == STRESS_MARK || grapheme_chars.contains(&chr)
+}
+
 /// Return true if the synthesis configuration is in a valid state, otherwise false.
 pub fn is_config_valid(data: &SynthesisTab) -> bool {
-    verify_weights(&data.syllable_wgts.0) && verify_weights(&data.syllable_wgts.1)
+    config_errors(data).is_empty()
+}
+
+/// Describe each problem with the synthesis configuration that is shown as an error in its tab.
+pub fn config_errors(data: &SynthesisTab) -> Vec<String> {
+    let mut errors = Vec::new();
+    if data.graphemes.is_empty() {
+        errors.push("The graphemic inventory is empty".to_owned());
+    }
+    if !verify_weights(&data.syllable_wgts.0) {
+        errors.push("Every function word length has a weight of zero".to_owned());
+    }
+    if !verify_weights(&data.syllable_wgts.1) {
+        errors.push("Every content word length has a weight of zero".to_owned());
+    }
+    for var in undefined_vars(&data.syllable_vars) {
+        errors.push(format!("Variable '{var}' is used but not defined"));
+    }
+    for category in empty_categories(data) {
+        errors.push(format!("Category '{category}' has no graphemes"));
+    }
+    if data.orphans_are_errors {
+        for (var, grapheme) in find_orphaned_graphemes(data) {
+            errors.push(format!(
+                "<{grapheme}> is used in {var} but isn't in the graphemic inventory"
+            ));
+        }
+    }
+    errors
+}
+
+/// Return each (variable, grapheme) pair where the variable's rule uses a grapheme that isn't in
+/// the graphemic inventory.
+fn find_orphaned_graphemes(data: &SynthesisTab) -> Vec<(String, String)> {
+    let mut orphans = Vec::new();
+    for (var, rule) in data.syllable_vars.iter() {
+        for leaf in rule.iter().flat_map(NonEmptyList::iter) {
+            for grapheme in leaf.graphemes() {
+                let orphan = (var.to_owned(), grapheme.to_string());
+                if !data.graphemes.contains(&grapheme) && !orphans.contains(&orphan) {
+                    orphans.push(orphan);
+                }
+            }
+        }
+    }
+    orphans
+}
+
+/// Return the variables that reachable rules reference but that don't exist, in alphabetical order.
+fn undefined_vars(vars: &SyllableVars) -> Vec<&str> {
+    let mut undefined: Vec<&str> = vars
+        .reachable
+        .iter()
+        .map(String::as_str)
+        .filter(|var| vars.get(var).is_none())
+        .collect();
+    undefined.sort_unstable();
+    undefined
+}
+
+/// Return the categories with no graphemes in them that reachable rules refer to, in the order
+/// they're first referred to.
+fn empty_categories(data: &SynthesisTab) -> Vec<&str> {
+    let categories = data.category_names();
+    data.syllable_vars
+        .iter()
+        .filter(|(var, _)| {
+            data.syllable_vars.reachable.contains(*var)
+                || SyllableRoots::names().any(|root| root == *var)
+        })
+        .flat_map(|(_, rule)| rule.iter().flat_map(NonEmptyList::iter))
+        .filter_map(|leaf| match leaf.required() {
+            LeafRule::Category(category) if !categories.contains(category) => {
+                Some(category.as_str())
+            }
+            _ => None,
+        })
+        .unique()
+        .collect()
+}
+
+/// Generate a batch of words and tally their initial consonant clusters, syllable shapes (such as
+/// CVC), and lengths. The batch size is fixed so this stays fast enough to run on the UI thread.
+fn compute_sample_stats(data: &SynthesisTab, class: WordClass) -> SampleStats {
+    let mut clusters = HashMap::new();
+    let mut shapes = HashMap::new();
+    let mut lengths = BTreeMap::new();
+    let mut grapheme_counts: BTreeMap<String, usize> = data
+        .graphemes
+        .iter()
+        .map(|grapheme| (grapheme.as_str().to_lowercase(), 0))
+        .collect();
+    for _ in 0..STATS_SAMPLE_SIZE {
+        let word = synthesize_morpheme_structured(data, class);
+        let mut cluster = String::new();
+        let mut in_cluster = true;
+        let mut length = 0;
+        for syllable in &word.syllables {
+            let syllable = syllable.to_lowercase();
+            let segments: Vec<_> = grapheme::segment(&syllable, &data.graphemes)
+                .into_iter()
+                .filter(|segment| !segment.starts_with(STRESS_MARK))
+                .collect();
+            length += segments.len();
+            for segment in &segments {
+                *grapheme_counts.entry(segment.to_string()).or_insert(0) += 1;
+            }
+            if in_cluster {
+                for segment in &segments {
+                    if is_vowel(data, segment) {
+                        in_cluster = false;
+                        break;
+                    }
+                    cluster.push_str(segment);
+                }
+            }
+            let shape: String = segments
+                .iter()
+                .map(|segment| if is_vowel(data, segment) { 'V' } else { 'C' })
+                .collect();
+            if !shape.is_empty() {
+                *shapes.entry(shape).or_insert(0) += 1;
+            }
+        }
+        if cluster.is_empty() {
+            cluster = "(none)".to_owned();
+        }
+        *clusters.entry(cluster).or_insert(0) += 1;
+        *lengths.entry(length).or_insert(0) += 1;
+    }
+    SampleStats {
+        initial_clusters: most_common(clusters),
+        syllable_shapes: most_common(shapes),
+        word_lengths: lengths
+            .into_iter()
+            .map(|(length, count)| {
+                let fraction = count as f32 / STATS_SAMPLE_SIZE as f32;
+                (length.to_string(), fraction)
+            })
+            .collect(),
+        total_graphemes: grapheme_counts.values().sum(),
+        grapheme_counts: grapheme_counts.into_iter().collect(),
+    }
+}
+
+/// Return the most frequent keys along with the fraction of the total count they make up.
+fn most_common(counts: HashMap<String, usize>) -> Vec<(String, f32)> {
+    let total: usize = counts.values().sum();
+    counts
+        .into_iter()
+        .sorted_by(|(key1, count1), (key2, count2)| count2.cmp(count1).then(key1.cmp(key2)))
+        .take(STATS_MAX_ROWS)
+        .map(|(key, count)| (key, count as f32 / total as f32))
+        .collect()
+}
+
+/// Split a word into graphemes, apply the allophony rules, and return the IPA pronunciation of
+/// each grapheme. Graphemes without a pronunciation are assumed to be written in IPA already.
+pub fn pronounce(data: &SynthesisTab, word: &str) -> Vec<String> {
+    let lowercase = word.to_lowercase();
+    let mut segments: Vec<String> = grapheme::segment(&lowercase, &data.graphemes)
+        .into_iter()
+        .map(str::to_owned)
+        .collect();
+    apply_allophony(data, &mut segments);
+    for segment in &mut segments {
+        if let Some(ipa) = data.pronunciations.get(segment.as_str()) {
+            *segment = ipa.clone();
+        }
+    }
+    segments
+}
+
+/// Transcribe a word into IPA using the pronunciation of each grapheme.
+pub fn to_ipa(data: &SynthesisTab, word: &str) -> String {
+    pronounce(data, word).concat()
+}
+
+/// Return true if a grapheme is in the vowel category, or if it is uncategorized and looks like a
+/// vowel based on its first letter.
+fn is_vowel(data: &SynthesisTab, grapheme: &str) -> bool {
+    match data.grapheme_categories.get(grapheme).map(String::as_str) {
+        Some("Vowel") => true,
+        Some("Consonant") => false,
+        _ => grapheme
+            .chars()
+            .next()
+            .is_some_and(|chr| VOWEL_LETTERS.contains(chr)),
+    }
+}
+
+/// Estimate the number of syllables in a word by counting its runs of vowels.
+pub fn count_syllables(data: &SynthesisTab, word: &str) -> usize {
+    let lowercase = word.to_lowercase();
+    grapheme::segment(&lowercase, &data.graphemes)
+        .into_iter()
+        .map(|segment| is_vowel(data, segment))
+        .dedup()
+        .filter(|&vowel| vowel)
+        .count()
+}
+
+/// Format the syllable grammar as text, with one line per variable in the form
+/// `Name = {p t k} a | Other`.
+fn syllable_vars_to_text(vars: &SyllableVars) -> String {
+    vars.iter()
+        .map(|(name, rule)| format!("{} = {}", name, or_rule_to_text(rule)))
+        .join("\n")
+}
+
+fn or_rule_to_text(rule: &OrRule) -> String {
+    rule.iter().map(and_rule_to_text).join(" | ")
+}
+
+fn and_rule_to_text(rule: &AndRule) -> String {
+    rule.iter().map(leaf_rule_to_text).join(" ")
+}
+
+fn leaf_rule_to_text(rule: &LeafRule) -> String {
+    match rule {
+        LeafRule::Sequence(list, _) if !list.is_empty() => list.iter().join(" "),
+        LeafRule::Linker(list, _, chance) if !list.is_empty() => {
+            format!("{BLANK_SYMBOL}/{}/{chance}%", list.iter().join("+"))
+        }
+        LeafRule::Sequence(..) | LeafRule::Linker(..) | LeafRule::Blank => BLANK_SYMBOL.to_owned(),
+        LeafRule::Set(set, _) => format!("{{{}}}", set.iter().join(" ")),
+        LeafRule::Category(category) if !category.is_empty() => format!("[{category}]"),
+        LeafRule::Variable(var) if !var.is_empty() => var.clone(),
+        LeafRule::Variable(_) | LeafRule::Category(_) | LeafRule::Uninitialized => {
+            UNSET_SYMBOL.to_owned()
+        }
+        LeafRule::Optional(inner, chance) => {
+            format!("{} {OPTIONAL_SYMBOL}{chance}%", leaf_rule_to_text(inner))
+        }
+    }
+}
+
+/// Parse syllable rules written in the text form produced by `syllable_vars_to_text`. Variables
+/// may be used before they are defined, and every grapheme must be in the master inventory. On
+/// failure, return a message giving the line and column of the error.
+fn syllable_vars_from_text(
+    text: &str,
+    inventory: &grapheme::MasterGraphemeStorage,
+) -> Result<SyllableVars, String> {
+    // collect every definition first so we know all of the variable names
+    let mut definitions = Vec::new(); // (line number, name, body, column of body)
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_num = line_idx + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((name, body)) = line.split_once('=') else {
+            return Err(syntax_error(
+                line_num,
+                1,
+                "expected a rule like `Name = ...`",
+            ));
+        };
+        let name = name.trim();
+        if name.is_empty() || name.contains(|chr: char| chr.is_whitespace() || "{}|".contains(chr))
+        {
+            return Err(syntax_error(line_num, 1, "invalid variable name"));
+        }
+        let body_start = line.len() - body.len();
+        let body_col = line[..body_start].chars().count() + 1;
+        definitions.push((line_num, name, body, body_col));
+    }
+    let names: HashSet<&str> = SyllableRoots::names()
+        .chain(definitions.iter().map(|(_, name, ..)| *name))
+        .collect();
+
+    let mut vars = SyllableVars::default();
+    let mut seen = HashSet::new();
+    for (line_num, name, body, body_col) in definitions {
+        if !seen.insert(name) {
+            let message = format!("`{name}` is defined more than once");
+            return Err(syntax_error(line_num, 1, &message));
+        }
+        let rule = parse_or_rule(body, &names, inventory)
+            .map_err(|(col, message)| syntax_error(line_num, body_col + col, &message))?;
+        if let Some(root) = vars.get_mut(name) {
+            *root = rule;
+        } else {
+            vars.vars.insert(name.to_owned(), rule);
+        }
+    }
+    Ok(vars)
+}
+
+/// Parse the right-hand side of a rule in the text form of the syllable grammar. On failure,
+/// return the (zero-based) column of the error and a message.
+fn parse_or_rule(
+    body: &str,
+    names: &HashSet<&str>,
+    inventory: &grapheme::MasterGraphemeStorage,
+) -> Result<OrRule, (usize, String)> {
+    let mut alternatives = Vec::new();
+    let mut leaves = Vec::new();
+    let mut set: Option<(usize, BTreeSet<grapheme::Grapheme>)> = None; // (column, graphemes)
+    for (col, token) in tokenize_rule(body) {
+        match token {
+            "|" => {
+                if set.is_some() {
+                    return Err((col, "`|` can't appear inside `{...}`".to_owned()));
+                }
+                let alternative = NonEmptyList::from_vec(std::mem::take(&mut leaves))
+                    .ok_or((col, "expected something before `|`".to_owned()))?;
+                alternatives.push(alternative);
+            }
+            "{" => {
+                if set.is_some() {
+                    return Err((col, "sets can't be nested".to_owned()));
+                }
+                set = Some((col, BTreeSet::new()));
+            }
+            "}" => match set.take() {
+                None => return Err((col, "unmatched `}`".to_owned())),
+                Some((_, graphemes)) if graphemes.is_empty() => {
+                    return Err((col, "a set needs at least one grapheme".to_owned()))
+                }
+                Some((_, graphemes)) => leaves.push(LeafRule::Set(graphemes, String::new())),
+            },
+            word => {
+                let is_grapheme = inventory.contains(&grapheme::Grapheme::new(word));
+                if let Some((_, graphemes)) = &mut set {
+                    if !is_grapheme {
+                        return Err((col, format!("`{word}` is not in the graphemic inventory")));
+                    }
+                    graphemes.insert(grapheme::Grapheme::new(word));
+                } else if word == UNSET_SYMBOL {
+                    leaves.push(LeafRule::Uninitialized);
+                } else if let Some(chance) = word.strip_prefix(OPTIONAL_SYMBOL) {
+                    let chance = chance
+                        .strip_suffix('%')
+                        .and_then(|chance| chance.parse::<u8>().ok())
+                        .map(|chance| chance.min(100))
+                        .ok_or((col, "expected a chance like `?40%`".to_owned()))?;
+                    let leaf = leaves
+                        .pop()
+                        .ok_or((col, "expected something before `?`".to_owned()))?;
+                    leaves.push(LeafRule::Optional(Box::new(leaf), chance));
+                } else if word == BLANK_SYMBOL {
+                    leaves.push(LeafRule::Blank);
+                } else if let Some(linker) = word
+                    .strip_prefix(BLANK_SYMBOL)
+                    .and_then(|word| word.strip_prefix('/'))
+                {
+                    leaves.push(parse_linker(linker, inventory).map_err(|message| (col, message))?);
+                } else if let Some(category) = word
+                    .strip_prefix('[')
+                    .and_then(|word| word.strip_suffix(']'))
+                    .filter(|category| !category.is_empty())
+                {
+                    leaves.push(LeafRule::Category(category.to_owned()));
+                } else if names.contains(word) {
+                    leaves.push(LeafRule::Variable(word.to_owned()));
+                } else if let Some(element) = grapheme::SequenceElement::parse(word) {
+                    // optional graphemes and alternatives are written like `(n)` and `a/e`
+                    let missing = element
+                        .alternatives
+                        .iter()
+                        .find(|alternative| !inventory.contains(alternative));
+                    if let Some(missing) = missing {
+                        let message = if element.is_plain() {
+                            format!("`{word}` is not a grapheme or variable")
+                        } else {
+                            format!("`{missing}` is not in the graphemic inventory")
+                        };
+                        return Err((col, message));
+                    }
+
+                    // adjacent graphemes form a single sequence
+                    match leaves.last_mut() {
+                        Some(LeafRule::Sequence(list, _)) => list.push(element),
+                        _ => leaves.push(LeafRule::Sequence(vec![element], String::new())),
+                    }
+                } else {
+                    let message = format!("`{word}` is not a grapheme or variable");
+                    return Err((col, message));
+                }
+            }
+        }
+    }
+    if let Some((col, _)) = set {
+        return Err((col, "unclosed `{`".to_owned()));
+    }
+    let end_col = body.chars().count();
+    let alternative =
+        NonEmptyList::from_vec(leaves).ok_or((end_col, "expected something here".to_owned()))?;
+    alternatives.push(alternative);
+    Ok(NonEmptyList::from_vec(alternatives).expect("at least one alternative was pushed"))
+}
+
+/// Parse the text form of a Linker node, `ε/a+b/30%`, with the leading `ε/` already removed.
+fn parse_linker(
+    text: &str,
+    inventory: &grapheme::MasterGraphemeStorage,
+) -> Result<LeafRule, String> {
+    const USAGE: &str = "expected a linker like `ε/a+b/30%`";
+    let (graphemes, chance) = text.split_once('/').ok_or(USAGE)?;
+    let chance = chance
+        .strip_suffix('%')
+        .and_then(|chance| chance.parse::<u8>().ok())
+        .map(|chance| chance.min(100))
+        .ok_or(USAGE)?;
+    let list = graphemes
+        .split('+')
+        .map(|word| {
+            let new_grapheme = grapheme::Grapheme::new(word);
+            if inventory.contains(&new_grapheme) {
+                Ok(new_grapheme)
+            } else {
+                Err(format!("`{word}` is not in the graphemic inventory"))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(LeafRule::Linker(list, String::new(), chance))
+}
+
+/// Split the right-hand side of a rule into tokens, in (zero-based column, token) format. Each
+/// `|`, `{`, and `}` is its own token, and other tokens are separated by whitespace.
+fn tokenize_rule(body: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut word_start = None; // (column, byte index)
+    for (col, (i, chr)) in body.char_indices().enumerate() {
+        let is_special = "|{}".contains(chr);
+        if chr.is_whitespace() || is_special {
+            if let Some((start_col, start)) = word_start.take() {
+                tokens.push((start_col, &body[start..i]));
+            }
+            if is_special {
+                tokens.push((col, &body[i..i + chr.len_utf8()]));
+            }
+        } else if word_start.is_none() {
+            word_start = Some((col, i));
+        }
+    }
+    if let Some((start_col, start)) = word_start {
+        tokens.push((start_col, &body[start..]));
+    }
+    tokens
+}
+
+/// Format an error message for the text form of the syllable grammar.
+fn syntax_error(line: usize, column: usize, message: &str) -> String {
+    format!("Line {line}, column {column}: {message}")
+}
+
+/// Create a random but usable synthesis configuration: a vowel and consonant inventory, syllable
+/// shapes built from those two categories, and word length weights. The result always passes
+/// `is_config_valid()`.
+pub fn random_config(rng: &mut impl Rng) -> SynthesisTab {
+    let mut data = SynthesisTab::default();
+
+    // pick the inventory, always keeping the most common sounds
+    let num_vowels = rng.gen_range(3..=7);
+    let mut vowels = RANDOM_VOWELS[..3].to_vec();
+    vowels.extend(RANDOM_VOWELS[3..].choose_multiple(rng, num_vowels - 3));
+    let num_consonants = rng.gen_range(6..=16);
+    let mut consonants = RANDOM_CONSONANTS[..6].to_vec();
+    consonants.extend(RANDOM_CONSONANTS[6..].choose_multiple(rng, num_consonants - 6));
+    for (category, graphemes) in [("Vowel", vowels), ("Consonant", consonants)] {
+        for grapheme in graphemes {
+            data.graphemes.insert(grapheme::Grapheme::new(grapheme));
+            data.grapheme_categories
+                .insert(grapheme.to_owned(), category.to_owned());
+        }
+    }
+
+    // pick the syllable shapes, always allowing CV
+    let mut shapes = vec!["CV"];
+    for (shape, probability) in [("V", 0.5), ("CVC", 0.6), ("CCV", 0.2), ("CVCC", 0.1)] {
+        if rng.gen_bool(probability) {
+            shapes.push(shape);
+        }
+    }
+    let to_and_rule = |shape: &str| {
+        let leaves = shape.chars().map(|class| {
+            let category = if class == 'V' { "Vowel" } else { "Consonant" };
+            LeafRule::Category(category.to_owned())
+        });
+        NonEmptyList::from_vec(leaves.collect()).unwrap()
+    };
+    let syllable = NonEmptyList::from_vec(shapes.into_iter().map(to_and_rule).collect()).unwrap();
+    let vars = &mut data.syllable_vars;
+    vars.vars.insert("Syllable".to_owned(), syllable);
+    for root in vars.roots.iter_mut() {
+        *root = NonEmptyList::new(NonEmptyList::new(LeafRule::Variable("Syllable".to_owned())));
+    }
+    flag_reachable_vars(vars);
+
+    // function words are kept short, while content words get a random length distribution
+    data.max_syllables = (2, rng.gen_range(3..=4));
+    let content_preset = *[LengthPreset::MostlyMonosyllabic, LengthPreset::BellCurve]
+        .choose(rng)
+        .unwrap();
+    data.syllable_wgts = (
+        normalize_weights(&LengthPreset::MostlyMonosyllabic.weights(2)),
+        normalize_weights(&content_preset.weights(data.max_syllables.1 as usize)),
+    );
+    data
 }
 
 /// Generate and return a new morpheme using the given settings.
-pub fn synthesize_morpheme(vars: &SyllableVars, weights: &[u16]) -> String {
-    let mut output = String::new();
+pub fn synthesize_morpheme(data: &SynthesisTab, class: WordClass) -> String {
+    synthesize_morpheme_structured(data, class).to_string()
+}
+
+/// Generate and return a new morpheme using the given settings, keeping track of where each
+/// syllable begins and ends.
+pub fn synthesize_morpheme_structured(data: &SynthesisTab, class: WordClass) -> Morpheme {
     let mut rng = thread_rng();
-    let num_syllables = 1 + WeightedIndex::new(weights)
-        .unwrap() // weights already sanitized by front end (don't do this for secure stuff!)
-        .sample(&mut rng);
+    // weights already sanitized by front end (don't do this for secure stuff!)
+    let lengths = WeightedIndex::new(data.syllable_weights(class)).unwrap();
+    let mut generate = || {
+        // pick the length again each time, so a long word that breaks the grapheme limit can be
+        // replaced by a shorter one
+        let num_syllables = 1 + lengths.sample(&mut rng);
+        let mut syllables = synthesize_syllables(data, num_syllables, &mut rng);
+        data.reduplication.apply(&mut syllables, &mut rng);
+        apply_allophony_to_syllables(data, &mut syllables);
+        syllables
+    };
+    let mut syllables = generate();
+    let max_graphemes = data.max_graphemes(class);
+    for _ in 1..MAX_CONSTRAINT_ATTEMPTS {
+        if !violates_constraints(data, &syllables) && !is_too_long(data, max_graphemes, &syllables)
+        {
+            break;
+        }
+        syllables = generate();
+    }
+    let stressed = data
+        .stress_position
+        .choose(syllables.len(), &data.stress_weights, &mut rng);
+    if let Some(stressed) = stressed {
+        data.stress_mark.apply(&mut syllables[stressed]);
+    }
+    Morpheme { syllables }
+}
+
+/// Return true if an unstressed word, split into syllables, breaks any of the word constraints.
+fn violates_constraints(data: &SynthesisTab, syllables: &[String]) -> bool {
+    if data.word_constraints.is_empty() {
+        return false;
+    }
+    let word = syllables.concat();
+    let segments = grapheme::segment(&word, &data.graphemes);
+    data.word_constraints
+        .iter()
+        .any(|constraint| constraint.is_violated_by(&segments, data))
+}
+
+/// Return true if an unstressed word, split into syllables, has more graphemes than the limit.
+fn is_too_long(data: &SynthesisTab, max_graphemes: Option<u8>, syllables: &[String]) -> bool {
+    max_graphemes.is_some_and(|max| {
+        let word = syllables.concat();
+        grapheme::segment(&word, &data.graphemes).len() > max as usize
+    })
+}
+
+/// Generate the syllables of a word with the given number of syllables, without stress.
+fn synthesize_syllables(
+    data: &SynthesisTab,
+    num_syllables: usize,
+    rng: &mut impl Rng,
+) -> Vec<String> {
+    let vars = &data.syllable_vars;
+    let mut syllables = Vec::with_capacity(num_syllables);
+    let mut add_syllable = |rule: &OrRule| {
+        let mut syllable = String::new();
+        synthesize_syllable(rule, data, &mut syllable, &mut *rng, 0);
+        syllables.push(syllable);
+    };
     if num_syllables == 1 {
-        synthesize_syllable(&vars.roots.single, vars, &mut output, &mut rng);
+        add_syllable(&vars.roots.single);
     } else {
-        synthesize_syllable(&vars.roots.initial, vars, &mut output, &mut rng);
+        add_syllable(&vars.roots.initial);
         for _ in 0..num_syllables - 2 {
-            synthesize_syllable(&vars.roots.middle, vars, &mut output, &mut rng);
+            add_syllable(&vars.roots.middle);
         }
-        synthesize_syllable(&vars.roots.terminal, vars, &mut output, &mut rng);
+        add_syllable(&vars.roots.terminal);
     }
-    output
+    syllables
 }
 
-/// Generate a syllable using the provided rule and append it to `output`.
+/// Generate a syllable using the provided rule and append it to `output`. Variables nested more
+/// than `MAX_VAR_DEPTH` levels deep are skipped, so that a recursive rule can't overflow the stack.
 fn synthesize_syllable(
     rule: &OrRule,
-    vars: &SyllableVars,
+    data: &SynthesisTab,
     output: &mut String,
     rng: &mut impl Rng,
+    depth: usize,
 ) {
+    if depth > MAX_VAR_DEPTH {
+        return;
+    }
     let or_clause = rule.iter().choose(rng).unwrap();
     for rule in or_clause.iter() {
-        match rule {
-            LeafRule::Sequence(list, _) => {
-                for grapheme in list {
-                    output.push_str(grapheme.as_str());
+        synthesize_leaf(rule, data, output, rng, depth);
+    }
+}
+
+/// Generate the graphemes for a single leaf node and append them to `output`.
+fn synthesize_leaf(
+    rule: &LeafRule,
+    data: &SynthesisTab,
+    output: &mut String,
+    rng: &mut impl Rng,
+    depth: usize,
+) {
+    match rule {
+        LeafRule::Sequence(list, _) => {
+            for element in list {
+                if element.optional && rng.gen_bool(0.5) {
+                    continue;
                 }
-            }
-            LeafRule::Set(list, _) => {
-                if let Some(grapheme) = list.iter().choose(rng) {
+                if let Some(grapheme) = element.alternatives.iter().choose(rng) {
                     output.push_str(grapheme.as_str());
                 }
             }
-            LeafRule::Variable(var) => {
-                if let Some(new_rule) = vars.get(var) {
-                    synthesize_syllable(new_rule, vars, output, rng);
-                }
+        }
+        LeafRule::Set(list, _) => {
+            if let Some(grapheme) = list.iter().choose(rng) {
+                output.push_str(grapheme.as_str());
+            }
+        }
+        LeafRule::Category(category) => {
+            if let Some(grapheme) = data.category_members(category).choose(rng) {
+                output.push_str(grapheme.as_str());
+            }
+        }
+        LeafRule::Variable(var) => {
+            if let Some(new_rule) = data.syllable_vars.get(var) {
+                synthesize_syllable(new_rule, data, output, rng, depth + 1);
+            }
+        }
+        LeafRule::Linker(list, _, chance) => {
+            if rng.gen_ratio((*chance).min(100) as u32, 100) {
+                output.extend(list.iter().map(grapheme::Grapheme::as_str));
+            }
+        }
+        LeafRule::Optional(inner, chance) => {
+            if rng.gen_ratio((*chance).min(100) as u32, 100) {
+                synthesize_leaf(inner, data, output, rng, depth);
+            }
+        }
+        LeafRule::Blank | LeafRule::Uninitialized => {}
+    }
+}
+
+/// Return every random set in the syllable rules that has at least two graphemes, in
+/// (label, graphemes) format.
+fn random_sets(data: &SynthesisTab) -> Vec<(String, Vec<String>)> {
+    let mut sets = Vec::new();
+    for (var, rule) in data.syllable_vars.iter() {
+        for leaf in rule.iter().flat_map(NonEmptyList::iter) {
+            let (label, members): (_, Vec<String>) = match leaf.required() {
+                LeafRule::Set(set, _) => (
+                    format!("{} {{{}}}", var, set.iter().join(" ")),
+                    set.iter().map(ToString::to_string).collect(),
+                ),
+                LeafRule::Category(category) => (
+                    format!("{} [{}]", var, category),
+                    data.category_members(category)
+                        .map(ToString::to_string)
+                        .collect(),
+                ),
+                _ => continue,
+            };
+            if members.len() >= 2 {
+                sets.push((label, members));
+            }
+        }
+    }
+    sets
+}
+
+/// Generate up to `count` minimal pairs of content words. Each pair consists of a generated word
+/// and a copy of it where one grapheme from `set` is swapped for a different grapheme from `set`.
+fn generate_minimal_pairs(
+    data: &SynthesisTab,
+    set: &[String],
+    count: usize,
+) -> Vec<(String, String)> {
+    let mut rng = thread_rng();
+    let mut pairs = Vec::with_capacity(count);
+    // give up eventually, in case few words contain a grapheme from the set
+    for _ in 0..count * 20 {
+        if pairs.len() == count {
+            break;
+        }
+        let morpheme = synthesize_morpheme_structured(data, WordClass::Content);
+        let mut segmented: Vec<Vec<&str>> = morpheme
+            .syllables
+            .iter()
+            .map(|syllable| grapheme::segment(syllable, &data.graphemes))
+            .collect();
+
+        // pick a (syllable, grapheme) position that holds a member of the set
+        let sites: Vec<(usize, usize)> = segmented
+            .iter()
+            .enumerate()
+            .flat_map(|(i, syllable)| {
+                syllable
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, grapheme)| set.iter().any(|member| member == *grapheme))
+                    .map(move |(j, _)| (i, j))
+            })
+            .collect();
+        if let Some(&(i, j)) = sites.choose(&mut rng) {
+            let original = segmented[i][j];
+            let replacement = set
+                .iter()
+                .filter(|member| *member != original)
+                .choose(&mut rng);
+            if let Some(replacement) = replacement {
+                segmented[i][j] = replacement.as_str();
+                let changed = segmented.iter().map(|syllable| syllable.concat()).collect();
+                pairs.push((morpheme.to_string(), changed));
             }
-            LeafRule::Blank | LeafRule::Uninitialized => {}
         }
     }
+    pairs
+}
+
+/// Scale a list of relative weights into whole percentages that add up to exactly 100. If the
+/// weights are all zero, they are treated as equal.
+fn normalize_weights(weights: &[f64]) -> Vec<u16> {
+    let total: f64 = weights.iter().sum();
+    if weights.is_empty() {
+        return vec![];
+    } else if total <= 0.0 {
+        return normalize_weights(&vec![1.0; weights.len()]);
+    }
+    let scaled: Vec<f64> = weights
+        .iter()
+        .map(|weight| weight / total * 100.0)
+        .collect();
+    let mut percents: Vec<u16> = scaled
+        .iter()
+        .map(|percent| percent.floor() as u16)
+        .collect();
+
+    // hand out the points lost to rounding, starting with the values that lost the most
+    let remaining = 100 - percents.iter().sum::<u16>();
+    let mut order: Vec<usize> = (0..scaled.len()).collect();
+    order.sort_by(|&a, &b| scaled[b].fract().total_cmp(&scaled[a].fract()));
+    for i in order.into_iter().take(remaining as usize) {
+        percents[i] += 1;
+    }
+    percents
 }
 
-/// Return true if the sum of a slice of weights equals 100, otherwise false.
+/// Return true if a slice of relative weights can be sampled from (it has at least one nonzero
+/// weight), otherwise false.
 fn verify_weights(weights: &[u16]) -> bool {
-    weights.iter().sum::<u16>() == 100
+    weights.iter().any(|&weight| weight > 0)
 }
 
 fn int_field_1_to_100(value: &mut u8) -> egui::DragValue {
     egui::DragValue::new(value).clamp_range(1..=100).speed(0.05)
 }
 
-fn int_field_percent(value: &mut u16) -> egui::DragValue {
-    egui::DragValue::new(value).clamp_range(0..=100).suffix("%")
+fn allophony_field<'a>(text: &'a mut String, hint: &str) -> egui::TextEdit<'a> {
+    egui::TextEdit::singleline(text)
+        .hint_text(hint)
+        .desired_width(60.0)
+}
+
+fn int_field_weight(value: &mut u16) -> egui::DragValue {
+    egui::DragValue::new(value).clamp_range(0..=100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unset_nodes_survive_export_and_import() {
+        let unset = vec![
+            LeafRule::Uninitialized,
+            LeafRule::Variable(String::new()),
+            LeafRule::Category(String::new()),
+        ];
+        let mut vars = SyllableVars::default();
+        let rule = NonEmptyList::from_vec(unset).unwrap();
+        vars.vars
+            .insert("Onset".to_owned(), NonEmptyList::new(rule));
+
+        let text = syllable_vars_to_text(&vars);
+        let inventory = grapheme::MasterGraphemeStorage::default();
+        let imported = syllable_vars_from_text(&text, &inventory)
+            .unwrap_or_else(|err| panic!("couldn't import {text:?}: {err}"));
+        assert_eq!(syllable_vars_to_text(&imported), text);
+    }
+
+    #[test]
+    fn segmenting_ignores_case_when_lengths_change() {
+        // both are 5 bytes, but each character changes length when lowercased
+        let text = "ẞİ";
+        assert_eq!(text.len(), text.to_lowercase().len());
+        let inventory = grapheme::MasterGraphemeStorage::default();
+        let pieces: Vec<_> = segment_ignoring_case(text, &inventory)
+            .into_iter()
+            .map(|(original, _)| original)
+            .collect();
+        assert_eq!(pieces, ["ẞ", "İ"]);
+    }
 }
@@ -1,12 +1,122 @@
 use eframe::egui;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
-use crate::{lexicon, synthesis};
+use crate::{grammar, grapheme, lexicon, synthesis};
 
-#[derive(Default, Deserialize, Serialize)]
+/// How long the input has to go unchanged before it's translated in live mode, in seconds.
+const LIVE_TRANSLATE_DELAY: f64 = 0.4;
+
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct TranslateTab {
     pub input_text: String,
     pub output_text: String,
+    #[serde(default)]
+    punctuation: Vec<(String, String)>, // (native punctuation, conlang punctuation)
+    #[serde(skip)]
+    direction: Direction,
+    #[serde(skip)]
+    gloss: Vec<(String, String)>, // (source word, translated word)
+    #[serde(skip)]
+    show_gloss: bool,
+    #[serde(skip)]
+    color_graphemes: bool,
+    #[serde(skip)]
+    homonyms: Vec<String>, // native words whose new translations couldn't be made unique
+    #[serde(skip)]
+    unknown_words: Vec<String>, // source words with no lexicon entry, in the order they appeared
+    #[serde(skip)]
+    live_translate: bool,
+    #[serde(skip)]
+    edited_since: Option<f64>, // when the input last changed without being translated live
+    #[serde(skip)]
+    scratch: HashMap<String, String>, // (native word, conlang word) coined by previews
+    #[serde(skip)]
+    is_preview: bool, // whether the output came from a live translation
+}
+
+/// How to handle the new words coined while translating into the conlang.
+struct Coinage<'a> {
+    preview: bool, // if true, new words are held in the scratch map instead of the lexicon
+    scratch: &'a mut HashMap<String, String>, // (native word, conlang word) not in the lexicon yet
+    taken: HashSet<String>, // lowercase conlang words that new words should avoid duplicating
+    homonyms: &'a mut Vec<String>, // native words whose new translations couldn't be made unique
+    coined: &'a mut Vec<String>, // native words that had no lexicon entry
+}
+
+/// The direction to translate in.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Direction {
+    #[default]
+    ToConlang,
+    ToNative,
+}
+
+/// The capitalization pattern of a word.
+#[derive(Clone, Copy, PartialEq)]
+enum Casing {
+    Lower, // "word"
+    Title, // "Word"
+    Upper, // "WORD"
+}
+
+impl Casing {
+    /// Detect the casing of a word or phrase from its letters. A single capital letter counts as
+    /// title case.
+    fn of(text: &str) -> Self {
+        let mut letters = text.chars().filter(|chr| chr.is_alphabetic()).peekable();
+        match letters.peek() {
+            Some(first) if first.is_uppercase() => {
+                if letters.clone().count() > 1 && letters.all(char::is_uppercase) {
+                    Self::Upper
+                } else {
+                    Self::Title
+                }
+            }
+            _ => Self::Lower,
+        }
+    }
+
+    /// Return a copy of the text with this casing applied. Lowercase leaves the text unchanged,
+    /// since conlang words may contain capitals of their own. Title case capitalizes the first
+    /// grapheme with a letter in it, so multigraphs like <ch> are treated as a single unit.
+    fn apply(&self, text: &str, inventory: &grapheme::MasterGraphemeStorage) -> String {
+        match self {
+            Self::Lower => text.to_owned(),
+            Self::Title => {
+                let mut output = String::with_capacity(text.len());
+                let mut segments = grapheme::segment(text, inventory).into_iter();
+                for segment in segments.by_ref() {
+                    if segment.chars().any(char::is_alphabetic) {
+                        output.push_str(&capitalize_grapheme(segment));
+                        break;
+                    }
+                    output.push_str(segment); // e.g. a stress mark
+                }
+                segments.for_each(|segment| output.push_str(segment));
+                output
+            }
+            Self::Upper => text.to_uppercase(),
+        }
+    }
+}
+
+/// Uppercase the first letter of a grapheme, leaving the rest of it unchanged.
+fn capitalize_grapheme(grapheme: &str) -> String {
+    match grapheme.char_indices().find(|(_, chr)| chr.is_alphabetic()) {
+        Some((i, letter)) => {
+            let rest = &grapheme[i + letter.len_utf8()..];
+            format!("{}{}{}", &grapheme[..i], letter.to_uppercase(), rest)
+        }
+        None => grapheme.to_owned(),
+    }
+}
+
+/// A piece of the input text.
+enum Token<'a> {
+    Word(&'a str),
+    Separator(&'a str),
+    Tag(grammar::WordAttribute), // an annotation like "#pl" after a word
 }
 
 /// Render contents of the 'translate' tab.
@@ -17,9 +127,11 @@ pub fn draw_translate_tab(
 ) {
     let crate::Language {
         name,
+        native_name,
         translate_tab,
         lexicon_tab,
         synthesis_tab,
+        grammar_tab,
         ..
     } = curr_lang;
 
@@ -43,72 +155,644 @@ pub fn draw_translate_tab(
         }
     });
 
-    // draw input box
+    // draw native language name field
+    ui.horizontal(|ui| {
+        ui.label("Translating from:");
+        ui.add(
+            egui::TextEdit::singleline(native_name)
+                .hint_text("Native language")
+                .desired_width(100.0),
+        );
+    });
+
+    // draw punctuation table
+    ui.add_space(5.0);
+    egui::CollapsingHeader::new("Punctuation")
+        .id_source("punctuation")
+        .show(ui, |ui| {
+            draw_punctuation_table(ui, &mut translate_tab.punctuation, native_name, name)
+        });
+
+    // draw language statistics
+    egui::CollapsingHeader::new("Statistics")
+        .id_source("statistics")
+        .show(ui, |ui| {
+            draw_language_stats(ui, lexicon_tab, synthesis_tab, grammar_tab)
+        });
+
+    // draw translation direction picker
     ui.add_space(10.0);
-    ui.add(
-        egui::TextEdit::multiline(&mut translate_tab.input_text)
-            .hint_text("Enter text to translate...")
-            .desired_width(ui.available_width() * 0.8)
-    );
+    let direction_changed = ui
+        .horizontal(|ui| {
+            let to_conlang = ui.selectable_value(
+                &mut translate_tab.direction,
+                Direction::ToConlang,
+                format!("{} to {}", native_name, name),
+            );
+            let to_native = ui.selectable_value(
+                &mut translate_tab.direction,
+                Direction::ToNative,
+                format!("{} to {}", name, native_name),
+            );
+            to_conlang.changed() || to_native.changed()
+        })
+        .inner;
+
+    // draw input box, taking ctrl+enter as a shortcut before the box can turn it into a newline
+    ui.add_space(5.0);
+    let input_id = egui::Id::new("translate input");
+    let shortcut = ui.memory(|mem| mem.has_focus(input_id))
+        && ui.input_mut(|input| input.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter));
+    let input = ui
+        .add(
+            egui::TextEdit::multiline(&mut translate_tab.input_text)
+                .id(input_id)
+                .hint_text("Enter text to translate...")
+                .desired_width(ui.available_width() * 0.8),
+        )
+        .on_hover_text(
+            "Tag a word's features with annotations like \"dog#pl\" or \"walk#past\". Press \
+            Ctrl+Enter to translate.",
+        );
+    let now = ui.input(|input| input.time);
+    if translate_tab.live_translate && (input.changed() || direction_changed) {
+        translate_tab.edited_since = Some(now);
+    }
 
     // draw translate button
     ui.add_space(10.0);
+    let to_conlang = translate_tab.direction == Direction::ToConlang;
+    let can_translate = !to_conlang || synthesis::is_config_valid(synthesis_tab);
     let button = ui
-        .add_enabled(
-            synthesis::is_config_valid(synthesis_tab),
-            egui::Button::new("Translate"),
-        )
-        .on_disabled_hover_text("This language's configuration contains errors.");
+        .horizontal(|ui| {
+            let button = ui
+                .add_enabled(can_translate, egui::Button::new("Translate"))
+                .on_hover_text("Translate the input, adding any new words to the lexicon")
+                .on_disabled_hover_text(
+                    "This language's configuration contains errors. Click \"Problems\" above \
+                    to see them.",
+                );
+            ui.checkbox(&mut translate_tab.show_gloss, "Show Gloss")
+                .on_hover_text("Show each word above its translation");
+            ui.add_enabled(
+                to_conlang,
+                egui::Checkbox::new(&mut translate_tab.color_graphemes, "Color Graphemes"),
+            )
+            .on_hover_text("Color each grapheme of the translation by its category");
+            if ui
+                .checkbox(&mut translate_tab.live_translate, "Live Translate")
+                .on_hover_text("Translate automatically whenever the input changes")
+                .changed()
+            {
+                translate_tab.edited_since = translate_tab.live_translate.then_some(now);
+            }
+            button
+        })
+        .inner;
+
+    // in live mode, wait for the input to settle before translating it
+    let live_due = match translate_tab.edited_since {
+        Some(since) if now - since >= LIVE_TRANSLATE_DELAY => true,
+        Some(since) => {
+            let remaining = LIVE_TRANSLATE_DELAY - (now - since);
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_secs_f64(remaining));
+            false
+        }
+        None => false,
+    };
+    if live_due {
+        translate_tab.edited_since = None;
+    }
 
-    // parse input, ignoring punctuation, and translate the rest
-    if button.clicked() {
+    // parse input, ignoring punctuation, and translate the rest. live translations are only
+    // previews, so the words they coin are kept out of the lexicon until a translation is
+    // requested explicitly
+    let commit = button.clicked() || shortcut;
+    if can_translate && (commit || live_due) {
         translate_tab.output_text.clear();
-        let mut word_start = None;
-        for (i, chr) in translate_tab.input_text.char_indices() {
-            if chr.is_alphanumeric() {
-                // mark this as the start of the word if no start already exists
-                word_start.get_or_insert(i);
-            } else {
-                if let Some(start) = word_start.take() {
-                    translate_tab.output_text.push_str(translate_word(
-                        &translate_tab.input_text[start..i],
-                        &mut lexicon_tab.lexicon,
-                        &synthesis_tab.syllable_vars,
-                        &synthesis_tab.syllable_wgts,
-                    ));
-                }
-                translate_tab.output_text.push(chr);
+        translate_tab.gloss.clear();
+        translate_tab.homonyms.clear();
+        translate_tab.unknown_words.clear();
+        translate_tab.is_preview = !commit;
+        let text = &translate_tab.input_text;
+        let punctuation = &translate_tab.punctuation;
+        let output = (&mut translate_tab.output_text, &mut translate_tab.gloss);
+        if to_conlang {
+            let taken = lexicon_tab
+                .lexicon
+                .values()
+                .map(|entry| &entry.word)
+                .chain(translate_tab.scratch.values())
+                .map(|word| word.to_lowercase())
+                .collect();
+            let coinage = Coinage {
+                preview: !commit,
+                scratch: &mut translate_tab.scratch,
+                taken,
+                homonyms: &mut translate_tab.homonyms,
+                coined: &mut translate_tab.unknown_words,
+            };
+            translate_to_conlang(
+                text,
+                lexicon_tab,
+                synthesis_tab,
+                grammar_tab,
+                punctuation,
+                output,
+                coinage,
+            );
+            if commit {
+                // whatever is left was coined for text that's no longer in the input
+                translate_tab.scratch.clear();
             }
-        }
-        if let Some(start) = word_start {
-            // translate and add trailing word if input doesn't end with a full stop
-            translate_tab.output_text.push_str(translate_word(
-                &translate_tab.input_text[start..],
-                &mut lexicon_tab.lexicon,
-                &synthesis_tab.syllable_vars,
-                &synthesis_tab.syllable_wgts,
-            ));
+        } else {
+            translate_to_native(
+                text,
+                &lexicon_tab.lexicon,
+                synthesis_tab,
+                punctuation,
+                output,
+                &mut translate_tab.unknown_words,
+            );
         }
     }
 
     // draw output box
     ui.add_space(10.0);
+    let color = translate_tab.color_graphemes && to_conlang;
+    let draw_output = |ui: &mut egui::Ui, text: &str| {
+        if color {
+            ui.label(synthesis::colored_graphemes(ui, synthesis_tab, text));
+        } else {
+            ui.label(text);
+        }
+    };
     ui.group(|ui| {
         ui.set_width(ui.available_width() * 0.8);
-        ui.label(&translate_tab.output_text);
+        if translate_tab.show_gloss {
+            ui.horizontal_wrapped(|ui| {
+                for (source, translation) in &translate_tab.gloss {
+                    ui.vertical(|ui| {
+                        ui.weak(source);
+                        draw_output(ui, translation);
+                    });
+                }
+            });
+        } else {
+            draw_output(ui, &translate_tab.output_text);
+        }
+    });
+
+    // explain that a live translation hasn't touched the lexicon
+    if translate_tab.is_preview && to_conlang {
+        ui.add_space(5.0);
+        ui.weak(
+            "This is a preview. New words won't be added to the lexicon until you click \
+            \"Translate\".",
+        );
+    }
+
+    // list the words that weren't in the lexicon, which may need the user's attention
+    if !translate_tab.unknown_words.is_empty() {
+        ui.add_space(5.0);
+        let (heading, explanation) = if to_conlang {
+            (
+                "Newly coined",
+                "These words had no lexicon entry, so new words were generated for them. You may \
+                want to review them in the lexicon.",
+            )
+        } else {
+            (
+                "Not in the lexicon",
+                "These words have no lexicon entry, so they couldn't be translated.",
+            )
+        };
+        // each unknown word is listed once, so compare it against the distinct words
+        let distinct_words: HashSet<_> = translate_tab
+            .gloss
+            .iter()
+            .map(|(source, _)| source.to_lowercase())
+            .collect();
+        ui.label(format!(
+            "{} ({} of {} distinct words): {}",
+            heading,
+            translate_tab.unknown_words.len(),
+            distinct_words.len(),
+            translate_tab.unknown_words.join(", ")
+        ))
+        .on_hover_text(explanation);
+    }
+
+    // warn about new words that had to be homonyms
+    if !translate_tab.homonyms.is_empty() {
+        ui.add_space(5.0);
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!(
+                "No unique word could be generated for: {}. The syllable rules may be running out \
+                of new words, so consider expanding them.",
+                translate_tab.homonyms.join(", ")
+            ),
+        );
+    }
+}
+
+/// Draw a summary of how developed a language is: the size of its lexicon, grammar, and grapheme
+/// inventory.
+fn draw_language_stats(
+    ui: &mut egui::Ui,
+    lexicon_tab: &lexicon::LexiconTab,
+    synthesis_tab: &synthesis::SynthesisTab,
+    grammar_tab: &grammar::GrammarTab,
+) {
+    let lexicon = &lexicon_tab.lexicon;
+    let generated = lexicon.values().filter(|entry| entry.generated).count();
+    let total_syllables: usize = lexicon
+        .values()
+        .map(|entry| synthesis::count_syllables(synthesis_tab, &entry.word))
+        .sum();
+    let avg_syllables = if lexicon.is_empty() {
+        "-".to_owned()
+    } else {
+        format!("{:.1}", total_syllables as f64 / lexicon.len() as f64)
+    };
+    egui::Grid::new("language_stats").show(ui, |ui| {
+        let rows = [
+            ("Lexicon entries:", lexicon.len().to_string()),
+            ("Generated words:", generated.to_string()),
+            ("Manual words:", (lexicon.len() - generated).to_string()),
+            ("Average syllables per word:", avg_syllables),
+            ("Grammar rules:", grammar_tab.num_rules().to_string()),
+            ("Graphemes:", synthesis_tab.graphemes.len().to_string()),
+        ];
+        for (label, value) in rows {
+            ui.label(label);
+            ui.label(value);
+            ui.end_row();
+        }
     });
 }
 
-/// Given an input word, translates it and updates the lexicon if the word
-/// hasn't been translated before.
-fn translate_word<'a>(
+/// Draw an editable table of native punctuation and the conlang punctuation that replaces it.
+fn draw_punctuation_table(
+    ui: &mut egui::Ui,
+    punctuation: &mut Vec<(String, String)>,
+    native_name: &str,
+    lang_name: &str,
+) {
+    ui.label(
+        "Punctuation in the left column is replaced by the right column when translating into \
+        the conlang, and the other way around when translating out of it.",
+    );
+    let mut removed = None;
+    egui::Grid::new("punctuation table").show(ui, |ui| {
+        ui.strong(native_name);
+        ui.strong(lang_name);
+        ui.end_row();
+        for (i, (native, conlang)) in punctuation.iter_mut().enumerate() {
+            ui.add(egui::TextEdit::singleline(native).desired_width(60.0));
+            ui.add(egui::TextEdit::singleline(conlang).desired_width(60.0));
+            if ui.small_button("Remove").clicked() {
+                removed = Some(i);
+            }
+            ui.end_row();
+        }
+    });
+    if let Some(i) = removed {
+        punctuation.remove(i);
+    }
+    if ui.button("Add").clicked() {
+        punctuation.push(Default::default());
+    }
+}
+
+/// Translate native text into the conlang, generating new words for any that aren't in the lexicon
+/// yet and inflecting single words with the grammar's affixes. Separators have their punctuation
+/// replaced according to the punctuation table. The translation is appended to the output text,
+/// and each (source, translation) pair is appended to the gloss. New words are stored according
+/// to `coinage`.
+fn translate_to_conlang(
+    text: &str,
+    lexicon_tab: &mut lexicon::LexiconTab,
+    synthesis: &synthesis::SynthesisTab,
+    grammar: &grammar::GrammarTab,
+    punctuation: &[(String, String)],
+    (output, gloss): (&mut String, &mut Vec<(String, String)>),
+    mut coinage: Coinage<'_>,
+) {
+    let tokens = parse_tags(tokenize(text, char::is_alphanumeric));
+    let max_words = max_phrase_words(lexicon_tab.lexicon.keys());
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::Word(word) => {
+                let phrase = match_phrase(&tokens[i..], max_words, |phrase| {
+                    lexicon_tab.lexicon.contains_key(phrase)
+                });
+                let (translation, len) = match phrase {
+                    Some((phrase, len)) => (lexicon_tab.lexicon[&phrase].word.clone(), len),
+                    None => {
+                        let tags = tokens[i + 1..]
+                            .iter()
+                            .map_while(|token| match token {
+                                Token::Tag(attribute) => Some(*attribute),
+                                _ => None,
+                            })
+                            .collect();
+                        let translation =
+                            inflect_word(word, tags, lexicon_tab, synthesis, grammar, &mut coinage);
+                        (translation, 1)
+                    }
+                };
+                let source = join_tokens(&tokens[i..i + len]);
+                let translation = Casing::of(&source).apply(&translation, &synthesis.graphemes);
+                output.push_str(&translation);
+                gloss.push((source, translation));
+                i += len;
+            }
+            Token::Separator(separator) => {
+                output.push_str(&replace_punctuation(separator, punctuation, false));
+                i += 1;
+            }
+            Token::Tag(_) => i += 1, // already applied to the preceding word
+        }
+    }
+}
+
+/// Translate conlang text into the native language. Since we can't make up native words, unknown
+/// words are marked instead. Separators have the punctuation table applied in reverse. The
+/// translation is appended to the output text, each (source, translation) pair is appended to
+/// the gloss, and each unknown word is appended to `unknown` once.
+fn translate_to_native(
+    text: &str,
+    lexicon: &lexicon::Lexicon,
+    synthesis: &synthesis::SynthesisTab,
+    punctuation: &[(String, String)],
+    (output, gloss): (&mut String, &mut Vec<(String, String)>),
+    unknown: &mut Vec<String>,
+) {
+    let reversed = lexicon::reverse_lexicon(lexicon);
+    let tokens = tokenize(text, synthesis::word_char_checker(synthesis));
+    let max_words = max_phrase_words(reversed.keys());
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            Token::Word(word) => {
+                let phrase = match_phrase(&tokens[i..], max_words, |phrase| {
+                    reversed.contains_key(phrase)
+                });
+                let (key, len) = phrase.unwrap_or_else(|| (word.to_lowercase(), 1));
+                let source = join_tokens(&tokens[i..i + len]);
+                let translation = match reversed.get(&key) {
+                    Some(natives) => {
+                        // the native language's graphemes aren't known, so treat each character
+                        // as its own grapheme
+                        let natives = natives.join("/");
+                        Casing::of(&source).apply(&natives, &Default::default())
+                    }
+                    None => {
+                        if !unknown.contains(&key) {
+                            unknown.push(key);
+                        }
+                        format!("[{}?]", word)
+                    }
+                };
+                output.push_str(&translation);
+                gloss.push((source, translation));
+                i += len;
+            }
+            Token::Separator(separator) => {
+                output.push_str(&replace_punctuation(separator, punctuation, true));
+                i += 1;
+            }
+            Token::Tag(_) => i += 1, // already applied to the preceding word
+        }
+    }
+}
+
+/// Replace the punctuation in a separator using (native, conlang) pairs, or (conlang, native)
+/// pairs if `reverse` is true. The longest match at each position is preferred, and text that
+/// matches no pair is copied unchanged.
+fn replace_punctuation(separator: &str, punctuation: &[(String, String)], reverse: bool) -> String {
+    let mut output = String::with_capacity(separator.len());
+    let mut rest = separator;
+    while let Some(chr) = rest.chars().next() {
+        let replacement = punctuation
+            .iter()
+            .map(|(native, conlang)| {
+                if reverse {
+                    (conlang, native)
+                } else {
+                    (native, conlang)
+                }
+            })
+            .filter(|(from, _)| !from.is_empty() && rest.starts_with(from.as_str()))
+            .max_by_key(|(from, _)| from.len());
+        match replacement {
+            Some((from, to)) => {
+                output.push_str(to);
+                rest = &rest[from.len()..];
+            }
+            None => {
+                output.push(chr);
+                rest = &rest[chr.len_utf8()..];
+            }
+        }
+    }
+    output
+}
+
+/// Return the largest number of words in any of the given phrases.
+fn max_phrase_words<'a>(phrases: impl Iterator<Item = &'a String>) -> usize {
+    phrases
+        .map(|phrase| phrase.split_whitespace().count())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Find the longest phrase of two or more words at the start of `tokens` for which `is_known`
+/// returns true. The words of a phrase can only be separated by whitespace. Return the phrase,
+/// in lowercase with single spaces between words, and the number of tokens it spans.
+fn match_phrase(
+    tokens: &[Token<'_>],
+    max_words: usize,
+    is_known: impl Fn(&str) -> bool,
+) -> Option<(String, usize)> {
+    let mut words = Vec::new();
+    let mut candidates = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if words.len() == max_words {
+            break;
+        }
+        match token {
+            Token::Word(word) => {
+                words.push(word.to_lowercase());
+                if words.len() >= 2 {
+                    candidates.push((words.join(" "), i + 1));
+                }
+            }
+            Token::Separator(separator) if separator.chars().all(char::is_whitespace) => {}
+            Token::Separator(_) | Token::Tag(_) => break,
+        }
+    }
+    candidates
+        .into_iter()
+        .rev()
+        .find(|(phrase, _)| is_known(phrase))
+}
+
+/// Concatenate the original text of a run of tokens.
+fn join_tokens(tokens: &[Token<'_>]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Word(text) | Token::Separator(text) => *text,
+            Token::Tag(_) => "",
+        })
+        .collect()
+}
+
+/// Split text into words and the separators between them. Words are maximal runs of characters
+/// for which `is_word_char` returns true.
+fn tokenize(text: &str, is_word_char: impl Fn(char) -> bool) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut token_start = 0;
+    let mut in_word = false;
+    for (i, chr) in text.char_indices() {
+        let word_char = is_word_char(chr);
+        if i > 0 && word_char != in_word {
+            tokens.push(make_token(&text[token_start..i], in_word));
+            token_start = i;
+        }
+        in_word = word_char;
+    }
+    if token_start < text.len() {
+        tokens.push(make_token(&text[token_start..], in_word));
+    }
+    tokens
+}
+
+/// Replace each annotation like "#pl" that directly follows a word (or another annotation) with a
+/// tag token. A '#' followed by anything other than a known tag is left as ordinary text.
+fn parse_tags(tokens: Vec<Token<'_>>) -> Vec<Token<'_>> {
+    let mut parsed = Vec::with_capacity(tokens.len());
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        let tag = match (&token, parsed.last(), tokens.peek()) {
+            (
+                Token::Separator("#"),
+                Some(Token::Word(_) | Token::Tag(_)),
+                Some(Token::Word(tag)),
+            ) => grammar::WordAttribute::from_tag(tag),
+            _ => None,
+        };
+        if let Some(attribute) = tag {
+            parsed.push(Token::Tag(attribute));
+            tokens.next();
+        } else {
+            parsed.push(token);
+        }
+    }
+    parsed
+}
+
+fn make_token(text: &str, is_word: bool) -> Token<'_> {
+    if is_word {
+        Token::Word(text)
+    } else {
+        Token::Separator(text)
+    }
+}
+
+/// Translate a single native word and attach the affixes for its word type and attributes. If the
+/// word isn't in the lexicon but looks like the plural of a word that is, the singular word is
+/// translated instead. Unless a number was given in the word's tags, the word is marked as plural
+/// or singular accordingly. Words with no known type are left uninflected.
+fn inflect_word(
+    word: &str,
+    mut attributes: Vec<grammar::WordAttribute>,
+    lexicon_tab: &mut lexicon::LexiconTab,
+    synthesis: &synthesis::SynthesisTab,
+    grammar: &grammar::GrammarTab,
+    coinage: &mut Coinage<'_>,
+) -> String {
+    let lexicon = &lexicon_tab.lexicon;
+    let mut key = word.to_lowercase();
+    let mut number = grammar::Number::Singular;
+    if !lexicon.contains_key(&key) {
+        if let Some(singular) = singularize(&key, lexicon) {
+            key = singular;
+            number = grammar::Number::Plural;
+        }
+    }
+    let number = grammar::WordAttribute::Number(number);
+    if !attributes.iter().any(|tag| tag.same_feature(&number)) {
+        attributes.push(number);
+    }
+    let stem = translate_word(&key, lexicon_tab, synthesis, coinage);
+    match lexicon_tab
+        .lexicon
+        .get(&key)
+        .and_then(|entry| entry.word_type.as_ref())
+    {
+        Some(word_type) => grammar.inflect(&stem, word_type, &attributes),
+        None => stem,
+    }
+}
+
+/// Guess the singular form of a lowercase plural word using regular English spelling, e.g.
+/// "cities" -> "city" or "boxes" -> "box". Only guesses that are in the lexicon are returned.
+fn singularize(word: &str, lexicon: &lexicon::Lexicon) -> Option<String> {
+    let guesses = [
+        word.strip_suffix("ies").map(|stem| format!("{stem}y")),
+        word.strip_suffix("es").map(str::to_owned),
+        word.strip_suffix('s').map(str::to_owned),
+    ];
+    guesses
+        .into_iter()
+        .flatten()
+        .find(|guess| !guess.is_empty() && lexicon.contains_key(guess))
+}
+
+/// Given an input word, translates it. If the word hasn't been translated before (or its entry's
+/// word is blank), a new word is coined, or the one coined by an earlier preview is reused. A
+/// preview keeps the new word in the scratch map; otherwise the lexicon is updated. If the new
+/// word couldn't be made unique when homonyms aren't allowed, the input word is appended to the
+/// coinage's homonyms. Words without a lexicon entry are appended to its coined words once.
+fn translate_word(
     word: &str,
-    lexicon: &'a mut lexicon::Lexicon,
-    vars: &synthesis::SyllableVars,
-    weights: &(Vec<u16>, Vec<u16>),
-) -> &'a str {
-    let generate_new = || synthesis::synthesize_morpheme(vars, &weights.1); // todo distinguish content and function weights
-    lexicon
-        .entry(word.to_lowercase())
-        .or_insert_with(generate_new)
+    lexicon_tab: &mut lexicon::LexiconTab,
+    synthesis: &synthesis::SynthesisTab,
+    coinage: &mut Coinage<'_>,
+) -> String {
+    let unique_attempts = lexicon_tab.unique_attempts();
+    let lexicon = &mut lexicon_tab.lexicon;
+    let key = word.to_lowercase();
+    if let Some(entry) = lexicon.get(&key).filter(|entry| !entry.word.is_empty()) {
+        return entry.word.clone();
+    }
+    if !coinage.coined.contains(&key) {
+        coinage.coined.push(key.clone());
+    }
+    let new_word = match coinage.scratch.get(&key) {
+        Some(new_word) => new_word.clone(),
+        None => {
+            let new_word = lexicon::generate_word(synthesis, &coinage.taken, unique_attempts);
+            if !coinage.taken.insert(new_word.to_lowercase()) && unique_attempts.is_some() {
+                coinage.homonyms.push(word.to_owned());
+            }
+            new_word
+        }
+    };
+    if coinage.preview {
+        coinage.scratch.insert(key, new_word.clone());
+    } else {
+        coinage.scratch.remove(&key);
+        lexicon
+            .entry(key)
+            .or_insert_with(|| lexicon::LexiconEntry::generated(""))
+            .word = new_word.clone();
+    }
+    new_word
 }
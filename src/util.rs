@@ -3,8 +3,14 @@ use std::sync::Arc;
 use eframe::egui;
 use serde::{Deserialize, Serialize};
 
+/// The egui memory key for whether deletions must be confirmed.
+const CONFIRM_DELETIONS_KEY: &str = "confirm deletions";
+
+/// The egui memory key for the ID of the item awaiting deletion confirmation.
+const PENDING_DELETION_KEY: &str = "pending deletion";
+
 /// A Vec that is guaranteed to have at least one element.
-#[derive(Default, Deserialize, Serialize)]
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct NonEmptyList<T> {
     pub head: T,
     pub tail: Vec<T>,
@@ -32,11 +38,53 @@ impl<T> NonEmptyList<T> {
         std::iter::once(&mut self.head).chain(&mut self.tail)
     }
 
+    /// Create a NonEmptyList from the elements of a Vec, or return None if the Vec is empty.
+    pub fn from_vec(list: Vec<T>) -> Option<Self> {
+        let mut elements = list.into_iter();
+        let head = elements.next()?;
+        Some(Self {
+            head,
+            tail: elements.collect(),
+        })
+    }
+
     /// Insert a new element as the head, pushing the previous head to the beginning of the tail.
     pub fn prepend(&mut self, element: T) {
         self.tail
             .insert(0, std::mem::replace(&mut self.head, element));
     }
+
+    /// Add a new element to the end of the list.
+    pub fn push(&mut self, element: T) {
+        self.tail.push(element);
+    }
+}
+
+impl<T> From<T> for NonEmptyList<T> {
+    fn from(head: T) -> Self {
+        Self::new(head)
+    }
+}
+
+impl<T> TryFrom<Vec<T>> for NonEmptyList<T> {
+    type Error = Vec<T>; // the empty Vec
+
+    fn try_from(list: Vec<T>) -> Result<Self, Self::Error> {
+        if list.is_empty() {
+            Err(list)
+        } else {
+            Ok(Self::from_vec(list).unwrap())
+        }
+    }
+}
+
+impl<T> From<NonEmptyList<T>> for Vec<T> {
+    fn from(list: NonEmptyList<T>) -> Self {
+        let mut elements = Vec::with_capacity(list.len());
+        elements.push(list.head);
+        elements.extend(list.tail);
+        elements
+    }
 }
 
 /// The edit mode for some portion of the UI.
@@ -87,23 +135,75 @@ pub fn draw_deletion_overlay(mode: EditMode, ui: &mut egui::Ui, response: &egui:
 }
 
 /// If in delete mode and the pointer is over `click_area`, draw a red overlay over
-/// `highlight_area`. Return true if `click_area` is clicked.
+/// `highlight_area`. Return true if `click_area` is clicked. If deletions must be confirmed
+/// (see `set_confirm_deletions`), a click instead opens a confirmation popup, and true is
+/// returned on the frame the deletion is confirmed.
 pub fn draw_multipart_deletion_overlay(
     mode: EditMode,
     ui: &mut egui::Ui,
     click_area: &egui::Response,
     highlight_area: &egui::Response,
 ) -> bool {
-    if mode.is_delete() && click_area.hovered() {
+    let pending_id = egui::Id::new(PENDING_DELETION_KEY);
+    let pending = ui.ctx().data(|data| data.get_temp::<egui::Id>(pending_id));
+    let mut is_pending = pending == Some(click_area.id);
+    if !mode.is_delete() {
+        if is_pending {
+            ui.ctx()
+                .data_mut(|data| data.remove::<egui::Id>(pending_id));
+        }
+        return false;
+    }
+
+    if click_area.hovered() || is_pending {
         ui.painter().rect_filled(
             highlight_area.rect.expand(2.0),
             3.0,
             egui::Color32::from_rgba_unmultiplied(255, 0, 0, 90),
         );
-        click_area.interact(egui::Sense::click()).clicked()
-    } else {
-        false
     }
+    if click_area.hovered() && click_area.interact(egui::Sense::click()).clicked() {
+        let confirm = ui.ctx().data(|data| {
+            data.get_temp::<bool>(egui::Id::new(CONFIRM_DELETIONS_KEY))
+                .unwrap_or_default()
+        });
+        if !confirm {
+            return true;
+        }
+        ui.ctx()
+            .data_mut(|data| data.insert_temp(pending_id, click_area.id));
+        is_pending = true;
+    }
+
+    // draw the confirmation popup for this item's pending deletion
+    if is_pending {
+        let mut choice = None;
+        egui::Window::new("Confirm Deletion")
+            .collapsible(false)
+            .resizable(false)
+            .show(ui.ctx(), |ui| {
+                ui.label("Delete the highlighted item?");
+                ui.horizontal(|ui| {
+                    if ui.button("Delete").clicked() {
+                        choice = Some(true);
+                    }
+                    if ui.button("Cancel").clicked() {
+                        choice = Some(false);
+                    }
+                });
+            });
+        if let Some(confirmed) = choice {
+            ui.ctx()
+                .data_mut(|data| data.remove::<egui::Id>(pending_id));
+            return confirmed;
+        }
+    }
+    false
+}
+
+/// Set whether deletions made through a deletion overlay must be confirmed in a popup first.
+pub fn set_confirm_deletions(ctx: &egui::Context, confirm: bool) {
+    ctx.data_mut(|data| data.insert_temp(egui::Id::new(CONFIRM_DELETIONS_KEY), confirm));
 }
 
 /// A reordering of an item in a list. Used for drag-and-drop reorderable lists.
@@ -115,12 +215,33 @@ pub struct Reordering {
 impl Reordering {
     pub fn apply<T>(&self, list: &mut Vec<T>) {
         let moved_item = list.remove(self.from_index);
-        let to_index = if self.to_index <= self.from_index {
+        list.insert(self.final_index(), moved_item);
+    }
+
+    /// Return the new index of the item that was at `index` before this reordering was applied.
+    pub fn remap_index(&self, index: usize) -> usize {
+        if index == self.from_index {
+            return self.final_index();
+        }
+        let index = if index > self.from_index {
+            index - 1
+        } else {
+            index
+        };
+        if index >= self.final_index() {
+            index + 1
+        } else {
+            index
+        }
+    }
+
+    /// Return the index the moved item ends up at, after it is removed from its old position.
+    fn final_index(&self) -> usize {
+        if self.to_index <= self.from_index {
             self.to_index
         } else {
             self.to_index - 1
-        };
-        list.insert(to_index, moved_item);
+        }
     }
 }
 
@@ -157,6 +278,13 @@ pub fn draw_reorderable(
     draw_multipart_deletion_overlay(mode, ui, &label_response, &full_response)
 }
 
+/// Render a small handle that can be dragged to move a reorderable item, for items that have no
+/// label of their own to use as one.
+pub fn draw_drag_handle(ui: &mut egui::Ui) -> egui::Response {
+    ui.add(egui::Label::new("☰").sense(egui::Sense::drag()))
+        .on_hover_text("Drag to reorder")
+}
+
 /// Allow dropping a reorderable item on the given Response, and draw the drag-and-drop hint line
 /// when such an item is hovered over it.
 pub fn draw_reorder_drop_area(